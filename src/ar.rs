@@ -0,0 +1,73 @@
+use std::io::{Result as IOResult, Write};
+
+const GLOBAL_HEADER: &[u8] = b"!<arch>\n";
+
+/// Member is the header of a single file within a common format `ar`
+/// archive, the container of a `.deb`.
+#[derive(Clone, Debug, Default)]
+pub struct Member {
+    pub name: String,
+    pub mtime: u64,
+    pub mode: u32,
+    pub size: u64,
+}
+impl Member {
+    /// writes the header, the caller writes `size` bytes of data followed
+    /// by `padding(size)`. Names are limited to 16 bytes, as dpkg expects
+    /// no extensions to the format.
+    pub fn write<W: Write>(&self, w: &mut W) -> IOResult<()> {
+        let header = format!(
+            "{:<16}{:<12}{:<6}{:<6}{:<8o}{:<10}`\n",
+            self.name, self.mtime, 0, 0, self.mode, self.size
+        );
+        w.write_all(header.as_bytes())
+    }
+}
+
+/// writes the magic which starts every archive
+pub fn write_global_header<W: Write>(w: &mut W) -> IOResult<()> {
+    w.write_all(GLOBAL_HEADER)
+}
+
+/// the newline which aligns `len` bytes to the next 2 byte boundary
+pub fn padding(len: u64) -> &'static [u8] {
+    if len % 2 == 0 {
+        b""
+    } else {
+        b"\n"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn headers_are_sixty_bytes() {
+        let mut archive = Vec::new();
+        write_global_header(&mut archive).unwrap();
+        let member = Member {
+            name: "control.tar.gz".to_string(),
+            mtime: 1_600_000_000,
+            mode: 0o100644,
+            size: 1234,
+        };
+        member.write(&mut archive).unwrap();
+        assert_eq!(archive.len(), 8 + 60);
+        assert_eq!(&archive[..8], b"!<arch>\n");
+        let header = std::str::from_utf8(&archive[8..]).unwrap();
+        assert_eq!(&header[0..16], "control.tar.gz  ");
+        assert_eq!(&header[16..28], "1600000000  ");
+        assert_eq!(&header[28..34], "0     ");
+        assert_eq!(&header[34..40], "0     ");
+        assert_eq!(&header[40..48], "100644  ");
+        assert_eq!(&header[48..58], "1234      ");
+        assert_eq!(&header[58..60], "`\n");
+    }
+
+    #[test]
+    fn members_align_to_two_bytes() {
+        assert_eq!(padding(4), b"");
+        assert_eq!(padding(5), b"\n");
+    }
+}
@@ -1,5 +1,9 @@
-use super::chrono::NaiveDateTime;
-use super::rpm::RPMBuilder;
+use std::collections::BTreeMap;
+use std::convert::TryFrom;
+
+use super::chrono::{Duration, NaiveDateTime};
+use super::errors::Err;
+use super::rpm::PackageBuilder;
 use super::serde::{Deserialize, Serialize};
 
 /// ChangeLogEntry describes what changed, and by whom
@@ -13,12 +17,34 @@ impl ChangeLogEntry {
     pub fn build<'a>(
         &'a self,
         when: &'a NaiveDateTime,
-    ) -> impl FnOnce(RPMBuilder) -> RPMBuilder + 'a {
-        move |arg: RPMBuilder| -> RPMBuilder {
-            // TODO: I'm not 100% sure this is correct but it feels right so idk.
-            //       review this code before 2038.
-            let timestamp = when.timestamp() as i32;
-            arg.add_changelog_entry(&self.author, &self.entry, timestamp)
+    ) -> impl FnOnce(PackageBuilder, &Err) -> Result<PackageBuilder, Err> + 'a {
+        move |arg: PackageBuilder, err: &Err| -> Result<PackageBuilder, Err> {
+            let timestamp = timestamp(when, err)?;
+            Ok(arg.add_changelog_entry(&self.author, &self.entry, timestamp))
         }
     }
 }
+
+/// the seconds since the epoch rpm records for `when`, its headers only
+/// hold 32 bit timestamps
+pub fn timestamp(when: &NaiveDateTime, err: &Err) -> Result<u32, Err> {
+    u32::try_from(when.and_utc().timestamp()).map_err(|_| {
+        err.clone()
+            .note("timestamp", when)
+            .note("error", "rpm only records times from 1970 until 2106")
+    })
+}
+
+/// adds an entry to a changelog, entries sharing a timestamp are moved
+/// apart by a second each so none are lost.
+pub fn insert_entry(
+    changelog: &mut BTreeMap<NaiveDateTime, ChangeLogEntry>,
+    when: NaiveDateTime,
+    entry: ChangeLogEntry,
+) {
+    let mut when = when;
+    while changelog.contains_key(&when) {
+        when += Duration::seconds(1);
+    }
+    changelog.insert(when, entry);
+}
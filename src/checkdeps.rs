@@ -0,0 +1,670 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs::read_to_string;
+use std::path::Path;
+
+use super::errors::Err;
+use super::rpm::DependencyFlags;
+use super::toml::de::from_str;
+
+use super::core::ConfigFile;
+use super::inspect::{Dep, Inspection};
+use super::repo::{attribute, elements, find_rpms, read_repo, split_evr, text, Entry};
+use super::subpackage::SubPackage;
+use super::vercmp::satisfies;
+use super::versions::{
+    into_dependencies, into_rich_dependencies, Constraint, Constraints, Evr, Operator,
+    RichDependency,
+};
+
+/// Candidate is a package, as far as resolving dependencies is concerned
+#[derive(Clone, Debug, Default)]
+pub struct Candidate {
+    pub name: String,
+    pub arch: String,
+    pub evr: Evr,
+    pub provides: Vec<Dep>,
+    pub requires: Vec<Dep>,
+    pub conflicts: Vec<Dep>,
+    pub obsoletes: Vec<Dep>,
+    pub files: Vec<String>,
+}
+impl Candidate {
+    pub fn from_inspection(pkg: Inspection) -> Candidate {
+        Candidate {
+            evr: Evr {
+                epoch: pkg.epoch,
+                version: pkg.version,
+                release: Option::Some(pkg.release),
+            },
+            name: pkg.name,
+            arch: pkg.arch,
+            provides: pkg.provides,
+            requires: pkg.requires,
+            conflicts: pkg.conflicts,
+            obsoletes: pkg.obsoletes,
+            files: pkg.files.into_iter().map(|f| f.path).collect(),
+        }
+        .with_self_provides()
+    }
+
+    /// reads a package back from its primary and filelists elements
+    fn from_entry(entry: &Entry) -> Candidate {
+        let version = |name: &str| attribute(&entry.primary, "version", name);
+        let deps = |table: &str| -> Vec<Dep> {
+            elements(&entry.primary, &format!("rpm:{}", table))
+                .first()
+                .map(|section| {
+                    elements(section, "rpm:entry")
+                        .into_iter()
+                        .filter_map(entry_dep)
+                        .collect()
+                })
+                .unwrap_or_default()
+        };
+        Candidate {
+            name: text(&entry.primary, "name").unwrap_or_default(),
+            arch: text(&entry.primary, "arch").unwrap_or_default(),
+            evr: Evr {
+                epoch: version("epoch").and_then(|e| e.parse().ok()),
+                version: version("ver").unwrap_or_default(),
+                release: version("rel"),
+            },
+            provides: deps("provides"),
+            requires: deps("requires"),
+            conflicts: deps("conflicts"),
+            obsoletes: deps("obsoletes"),
+            files: elements(&entry.filelists, "file")
+                .into_iter()
+                .filter_map(|file| text(file, "file"))
+                .collect(),
+        }
+        .with_self_provides()
+    }
+
+    /// describes a package of a config from its tables, as `pkg` would
+    /// build it, other than the dependencies `auto_deps` derives.
+    fn from_config(
+        config: &ConfigFile,
+        name: &str,
+        sub: &SubPackage,
+        err: &Err,
+    ) -> Result<Candidate, Err> {
+        let deps = |table: &BTreeMap<String, Constraints>,
+                    flags: DependencyFlags|
+         -> Result<Vec<Dep>, Err> {
+            let mut output = Vec::new();
+            for (name, constraints) in table.iter() {
+                let deps = into_dependencies(name, constraints, flags, err)?;
+                output.extend(deps.into_iter().map(Dep::from_dependency));
+            }
+            Ok(output)
+        };
+
+        let mut requires = deps(&sub.requires, DependencyFlags::empty())?;
+        requires.extend(deps(&sub.requires_pre, DependencyFlags::SCRIPT_PRE)?);
+        requires.extend(deps(&sub.requires_post, DependencyFlags::SCRIPT_POST)?);
+        requires.extend(deps(&sub.requires_preun, DependencyFlags::SCRIPT_PREUN)?);
+        requires.extend(deps(&sub.requires_postun, DependencyFlags::SCRIPT_POSTUN)?);
+        requires.extend(
+            into_rich_dependencies(&sub.rich_requires, err)?
+                .into_iter()
+                .map(Dep::from_dependency),
+        );
+        let mut conflicts = deps(&sub.conflicts, DependencyFlags::empty())?;
+        conflicts.extend(
+            into_rich_dependencies(&sub.rich_conflicts, err)?
+                .into_iter()
+                .map(Dep::from_dependency),
+        );
+
+        // sources which are not staged yet still provide their destination
+        let mut files = Vec::new();
+        for (source, options) in sub.contents.iter() {
+            match options.expand(source, err) {
+                Ok(expanded) => files.extend(expanded.into_iter().map(|(_, dst)| dst)),
+                Err(_) => files.push(options.dst().to_string()),
+            }
+        }
+
+        Ok(Candidate {
+            name: name.to_string(),
+            arch: config.rpm.arch.clone(),
+            evr: config.evr(),
+            provides: deps(&sub.provides, DependencyFlags::empty())?,
+            requires,
+            conflicts,
+            obsoletes: deps(&sub.obsoletes, DependencyFlags::empty())?,
+            files,
+        }
+        .with_self_provides())
+    }
+
+    /// every package provides `name = evr` and `name(arch) = evr`, rpm adds
+    /// these while building
+    fn with_self_provides(mut self) -> Candidate {
+        for name in [self.name.clone(), format!("{}({})", self.name, self.arch)].iter() {
+            if !self.provides.iter().any(|p| &p.name == name) {
+                self.provides.push(Dep {
+                    name: name.clone(),
+                    op: Option::Some("=".to_string()),
+                    version: self.evr.to_string(),
+                    flags: Vec::new(),
+                });
+            }
+        }
+        self
+    }
+
+    /// `name-[epoch:]version-release.arch`
+    pub fn nevra(&self) -> String {
+        format!("{}-{}.{}", self.name, self.evr, self.arch)
+    }
+
+    /// whether a provide, or for paths a file, of this package matches
+    fn provides(&self, name: &str, constraint: Option<&Constraint>) -> bool {
+        if name.starts_with('/') && self.files.iter().any(|f| f == name) {
+            return true;
+        }
+        self.provides.iter().any(|provide| {
+            provide.name == name
+                && match (constraint, provide.op.as_ref().map(|op| op.as_str())) {
+                    (Option::Some(constraint), Option::Some("=")) => {
+                        satisfies(&parse_evr(&provide.version), constraint)
+                    }
+                    // unversioned, or ranged, provides match any version
+                    _ => true,
+                }
+        })
+    }
+
+    /// whether this package is the one named by an obsoletes
+    fn obsoleted_by(&self, dep: &Dep) -> bool {
+        self.name == dep.name
+            && constraint(dep)
+                .map(|c| satisfies(&self.evr, &c))
+                .unwrap_or(true)
+    }
+}
+
+/// Report is every relationship of the checked packages which would fail,
+/// or change, an install from the repository
+#[derive(Clone, Debug, Default)]
+pub struct Report {
+    pub unsatisfied: Vec<(String, Dep)>,
+    pub conflicts: Vec<(String, String, Dep)>,
+    pub obsoletes: Vec<(String, String, Dep)>,
+}
+impl Report {
+    /// unsatisfied requires or conflicts prevent an install, obsoletes only
+    /// replace packages
+    pub fn is_ok(&self) -> bool {
+        self.unsatisfied.is_empty() && self.conflicts.is_empty()
+    }
+}
+
+/// the packages to check, from an rpm or from every package of a config
+pub fn load_targets(path: &str) -> Result<Vec<Candidate>, Err> {
+    if !path.ends_with(".toml") {
+        return Ok(vec![Candidate::from_inspection(Inspection::open(path)?)]);
+    }
+    let err = Err::default().note("config", path);
+    let data = read_to_string(path).map_err(|e| err.clone().note("failed to read config", e))?;
+    let config = from_str::<ConfigFile>(&data).map_err(|e| {
+        err.clone()
+            .note("failed to parse config", format_args!("{}", e))
+    })?;
+
+    let main = config.main_package();
+    let mut output = vec![Candidate::from_config(
+        &config,
+        &config.rpm.name,
+        &main,
+        &err,
+    )?];
+    for (name, _, sub, err) in config.subpackages() {
+        output.push(Candidate::from_config(&config, &name, &sub, &err)?);
+    }
+    Ok(output)
+}
+
+/// the packages of a repository, from its repodata when it has any or
+/// else from the rpms within it
+pub fn load_repo(dir: &Path) -> Result<Vec<Candidate>, Err> {
+    if let Option::Some(entries) = read_repo(dir)? {
+        return Ok(entries.iter().map(Candidate::from_entry).collect());
+    }
+    let err = Err::default().note("repo", dir.display());
+    let mut output = Vec::new();
+    for path in find_rpms(dir, &err)? {
+        let inspection = Inspection::open(&path.to_string_lossy())?;
+        output.push(Candidate::from_inspection(inspection));
+    }
+    Ok(output)
+}
+
+/// checks the targets as if they were installed from the repository.
+///
+/// Packages of the repository which share a name with a target are older
+/// builds the target replaces, so they are left out.
+pub fn check(targets: &[Candidate], repo: &[Candidate]) -> Report {
+    let others: Vec<&Candidate> = repo
+        .iter()
+        .filter(|c| !targets.iter().any(|t| t.name == c.name))
+        .collect();
+    let pool: Vec<&Candidate> = targets.iter().chain(others.iter().map(|c| *c)).collect();
+    let installed: Vec<&Candidate> = targets.iter().collect();
+
+    let mut report = Report::default();
+    for target in targets {
+        let nevra = target.nevra();
+        for dep in target.requires.iter() {
+            if dep.flags.iter().any(|f| f == "rpmlib") {
+                continue;
+            }
+            if !resolves(dep, &pool, &installed) {
+                report.unsatisfied.push((nevra.clone(), dep.clone()));
+            }
+        }
+        for other in others.iter() {
+            for dep in target.conflicts.iter() {
+                if resolves(dep, &[*other], &installed) {
+                    report
+                        .conflicts
+                        .push((nevra.clone(), other.nevra(), dep.clone()));
+                }
+            }
+            for dep in other.conflicts.iter() {
+                if resolves(dep, &[target], &installed) {
+                    report
+                        .conflicts
+                        .push((other.nevra(), nevra.clone(), dep.clone()));
+                }
+            }
+            for dep in target.obsoletes.iter() {
+                if other.obsoleted_by(dep) {
+                    report
+                        .obsoletes
+                        .push((nevra.clone(), other.nevra(), dep.clone()));
+                }
+            }
+            for dep in other.obsoletes.iter() {
+                if target.obsoleted_by(dep) {
+                    report
+                        .obsoletes
+                        .push((other.nevra(), nevra.clone(), dep.clone()));
+                }
+            }
+        }
+    }
+    report
+}
+
+/// whether any package of `pool` satisfies the dependency, the conditions
+/// of rich dependencies are evaluated against the `installed` packages
+fn resolves(dep: &Dep, pool: &[&Candidate], installed: &[&Candidate]) -> bool {
+    if dep.name.starts_with('(') {
+        return match RichDependency::parse(&dep.name) {
+            Ok(rich) => resolves_rich(&rich, pool, installed),
+            Err(_) => false,
+        };
+    }
+    let constraint = constraint(dep);
+    pool.iter()
+        .any(|c| c.provides(&dep.name, constraint.as_ref()))
+}
+
+fn resolves_rich(rich: &RichDependency, pool: &[&Candidate], installed: &[&Candidate]) -> bool {
+    let resolves = |rich: &RichDependency| resolves_rich(rich, pool, installed);
+    let otherwise = |other: &Option<Box<RichDependency>>| match other {
+        &Option::None => true,
+        &Option::Some(ref other) => resolves(other),
+    };
+    match rich {
+        &RichDependency::Atom(ref name, ref constraint) => {
+            pool.iter().any(|c| c.provides(name, constraint.as_ref()))
+        }
+        &RichDependency::And(ref terms) => terms.iter().all(|t| resolves(t)),
+        &RichDependency::Or(ref terms) => terms.iter().any(|t| resolves(t)),
+        // `with` and `without` are met by a single package
+        &RichDependency::With(ref terms) => pool
+            .iter()
+            .any(|c| terms.iter().all(|t| resolves_rich(t, &[*c], installed))),
+        &RichDependency::Without(ref term, ref not) => pool.iter().any(|c| {
+            resolves_rich(term, &[*c], installed) && !resolves_rich(not, &[*c], installed)
+        }),
+        &RichDependency::If(ref then, ref cond, ref other) => {
+            if resolves_rich(cond, installed, installed) {
+                resolves(then)
+            } else {
+                otherwise(other)
+            }
+        }
+        &RichDependency::Unless(ref then, ref cond, ref other) => {
+            if resolves_rich(cond, installed, installed) {
+                otherwise(other)
+            } else {
+                resolves(then)
+            }
+        }
+    }
+}
+
+fn constraint(dep: &Dep) -> Option<Constraint> {
+    let op = match dep.op.as_ref()?.as_str() {
+        "<" => Operator::Less,
+        "<=" => Operator::LessEq,
+        ">" => Operator::Greater,
+        ">=" => Operator::GreaterEq,
+        _ => Operator::Eq,
+    };
+    Option::Some(Constraint {
+        op,
+        evr: parse_evr(&dep.version),
+    })
+}
+
+/// unlike `Evr::parse` the components are not validated, as packages of a
+/// repository were not necessarily built by rpmbuilder
+fn parse_evr(evr: &str) -> Evr {
+    let (epoch, version, release) = split_evr(evr);
+    Evr {
+        epoch: epoch.and_then(|e| e.parse().ok()),
+        version: version.to_string(),
+        release: release.map(|r| r.to_string()),
+    }
+}
+
+/// reads an `<rpm:entry>` of primary
+fn entry_dep(entry: &str) -> Option<Dep> {
+    let attr = |name: &str| attribute(entry, "rpm:entry", name);
+    let op = attr("flags").map(|flags| {
+        match flags.as_str() {
+            "LT" => "<",
+            "LE" => "<=",
+            "GT" => ">",
+            "GE" => ">=",
+            _ => "=",
+        }
+        .to_string()
+    });
+    let mut version = attr("ver").unwrap_or_default();
+    if let Option::Some(epoch) = attr("epoch").filter(|e| e != "0") {
+        version = format!("{}:{}", epoch, version);
+    }
+    if let Option::Some(release) = attr("rel") {
+        version = format!("{}-{}", version, release);
+    }
+    Option::Some(Dep {
+        name: attr("name")?,
+        op,
+        version,
+        flags: attr("pre")
+            .filter(|pre| pre == "1")
+            .map(|_| vec!["pre".to_string()])
+            .unwrap_or_default(),
+    })
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if !self.unsatisfied.is_empty() {
+            writeln!(f, "unsatisfied:")?;
+            for (pkg, dep) in self.unsatisfied.iter() {
+                writeln!(f, "  {} requires {}", pkg, dep)?;
+            }
+        }
+        if !self.conflicts.is_empty() {
+            writeln!(f, "conflicts:")?;
+            for (pkg, other, dep) in self.conflicts.iter() {
+                writeln!(f, "  {} conflicts with {} ({})", pkg, other, dep)?;
+            }
+        }
+        if !self.obsoletes.is_empty() {
+            writeln!(f, "obsoleted:")?;
+            for (pkg, other, dep) in self.obsoletes.iter() {
+                writeln!(f, "  {} obsoletes {} ({})", pkg, other, dep)?;
+            }
+        }
+        if self.is_ok() && self.obsoletes.is_empty() {
+            writeln!(f, "every dependency is satisfied")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dep(name: &str, op: &str, version: &str) -> Dep {
+        Dep {
+            name: name.to_string(),
+            op: Option::Some(op.to_string()).filter(|op| !op.is_empty()),
+            version: version.to_string(),
+            flags: Vec::new(),
+        }
+    }
+
+    fn candidate(name: &str, evr: &str) -> Candidate {
+        Candidate {
+            name: name.to_string(),
+            arch: "x86_64".to_string(),
+            evr: parse_evr(evr),
+            ..Candidate::default()
+        }
+        .with_self_provides()
+    }
+
+    fn strings(report: &[(String, String, Dep)]) -> Vec<String> {
+        report
+            .iter()
+            .map(|(pkg, other, dep)| format!("{} {} {}", pkg, other, dep))
+            .collect()
+    }
+
+    #[test]
+    fn versioned_requires_must_match() {
+        let mut target = candidate("app", "1.0-1");
+        target.requires = vec![dep("lib", ">=", "2.0"), dep("tool", "", "")];
+        let mut tool = candidate("tools", "1-1");
+        tool.provides.push(dep("tool", "", ""));
+
+        let report = check(
+            &[target.clone()],
+            &[candidate("lib", "1.9-1"), tool.clone()],
+        );
+        assert_eq!(report.unsatisfied.len(), 1);
+        assert_eq!(report.unsatisfied[0].0, "app-1.0-1.x86_64");
+        assert_eq!(report.unsatisfied[0].1.to_string(), "lib >= 2.0");
+        assert!(!report.is_ok());
+
+        let report = check(&[target], &[candidate("lib", "2.0-1"), tool]);
+        assert!(report.is_ok(), "{}", report);
+        assert_eq!(report.to_string(), "every dependency is satisfied\n");
+    }
+
+    #[test]
+    fn path_requires_are_met_by_files() {
+        let mut target = candidate("app", "1.0-1");
+        target.requires = vec![dep("/usr/bin/python3", "", "")];
+        let mut python = candidate("python3", "3.11-1");
+        python.files.push("/usr/bin/python3".to_string());
+
+        assert!(check(&[target.clone()], &[python]).is_ok());
+        let report = check(&[target], &[candidate("python2", "2.7-1")]);
+        assert_eq!(report.unsatisfied[0].1.name, "/usr/bin/python3");
+    }
+
+    #[test]
+    fn rpmlib_requires_are_skipped() {
+        let mut target = candidate("app", "1.0-1");
+        let mut rpmlib = dep("rpmlib(CompressedFileNames)", "<=", "3.0.4-1");
+        rpmlib.flags.push("rpmlib".to_string());
+        target.requires = vec![rpmlib];
+        assert!(check(&[target], &[]).is_ok());
+    }
+
+    #[test]
+    fn conflicts_are_reported_in_either_direction() {
+        let mut target = candidate("app", "1.0-1");
+        target.conflicts = vec![dep("old", "<", "2.0")];
+        let mut other = candidate("other", "1.0-1");
+        other.conflicts = vec![dep("app", "<=", "1.0-1")];
+
+        let report = check(&[target], &[candidate("old", "1.5-1"), other]);
+        assert_eq!(
+            strings(&report.conflicts),
+            vec![
+                "app-1.0-1.x86_64 old-1.5-1.x86_64 old < 2.0",
+                "other-1.0-1.x86_64 app-1.0-1.x86_64 app <= 1.0-1",
+            ]
+        );
+        assert!(!report.is_ok());
+    }
+
+    #[test]
+    fn obsoletes_replace_without_failing() {
+        let mut target = candidate("app", "2.0-1");
+        target.obsoletes = vec![dep("app-legacy", "<", "2.0")];
+        let mut newer = candidate("newer", "1.0-1");
+        newer.obsoletes = vec![dep("app", "", "")];
+
+        let report = check(
+            &[target],
+            &[
+                candidate("app-legacy", "1.0-1"),
+                // an older build of a target is replaced, not obsoleted
+                candidate("app", "1.0-1"),
+                newer,
+            ],
+        );
+        assert!(report.is_ok());
+        assert_eq!(
+            strings(&report.obsoletes),
+            vec![
+                "app-2.0-1.x86_64 app-legacy-1.0-1.x86_64 app-legacy < 2.0",
+                "newer-1.0-1.x86_64 app-2.0-1.x86_64 app",
+            ]
+        );
+    }
+
+    #[test]
+    fn rich_dependencies_resolve() {
+        let mut both = candidate("both", "1-1");
+        both.provides.push(dep("a", "", ""));
+        both.provides.push(dep("b", "", ""));
+        let mut only_a = candidate("only-a", "1-1");
+        only_a.provides.push(dep("a", "", ""));
+        let mut only_b = candidate("only-b", "1-1");
+        only_b.provides.push(dep("b", "", ""));
+        let installed = candidate("installed", "1-1");
+
+        let pool =
+            |pkgs: &[&Candidate], rich: &str| resolves(&dep(rich, "", ""), pkgs, &[&installed]);
+        assert!(pool(&[&only_a, &only_b], "(a and b)"));
+        assert!(pool(&[&only_b], "(a or b)"));
+        assert!(!pool(&[&only_b], "(a or c)"));
+
+        // `with` needs a single package providing every term
+        assert!(pool(&[&both], "(a with b)"));
+        assert!(!pool(&[&only_a, &only_b], "(a with b)"));
+        // `without` needs a package providing the first but not the second
+        assert!(!pool(&[&both], "(a without b)"));
+        assert!(pool(&[&both, &only_a], "(a without b)"));
+
+        // conditions are evaluated against the installed packages
+        assert!(pool(&[&only_a], "(a if installed)"));
+        assert!(!pool(&[&only_b], "(a if installed)"));
+        assert!(pool(&[&only_b], "(a if missing)"));
+        assert!(pool(&[&only_b], "(a if missing else b)"));
+        assert!(!pool(&[&only_a], "(b if installed else a)"));
+        assert!(pool(&[&only_b], "(a unless installed)"));
+        assert!(!pool(&[&only_b], "(a unless missing)"));
+        assert!(pool(&[&only_b], "(a unless installed else b)"));
+
+        assert!(pool(&[&only_a], "(a >= 1 with a < 2)"));
+        assert!(!pool(&[&only_a, &only_b], "(unbalanced"));
+    }
+
+    #[test]
+    fn primary_entries_are_read() {
+        let entry =
+            entry_dep(r#"<rpm:entry name="lib" flags="GE" epoch="1" ver="2.0" rel="3" pre="1"/>"#)
+                .unwrap();
+        assert_eq!(entry.to_string(), "lib >= 1:2.0-3 (pre)");
+
+        let entry = entry_dep(r#"<rpm:entry name="lib" flags="LT" epoch="0" ver="2.0"/>"#);
+        assert_eq!(entry.unwrap().to_string(), "lib < 2.0");
+        let entry = entry_dep(r#"<rpm:entry name="lib" flags="EQ" ver="2.0" rel="1"/>"#);
+        assert_eq!(entry.unwrap().to_string(), "lib = 2.0-1");
+        let entry = entry_dep(r#"<rpm:entry name="/bin/sh"/>"#).unwrap();
+        assert_eq!(entry.to_string(), "/bin/sh");
+        assert_eq!(entry.op, Option::None);
+        assert_eq!(
+            entry_dep(r#"<rpm:entry flags="GE" ver="1"/>"#),
+            Option::None
+        );
+    }
+
+    #[test]
+    fn configs_are_candidates() {
+        let config: ConfigFile = from_str(
+            "rich_requires = ['(a or b)']\n\
+             \n\
+             [rpm]\n\
+             name = 'demo'\n\
+             version = '1.0'\n\
+             release = 2\n\
+             license = 'MIT'\n\
+             arch = 'noarch'\n\
+             desc = 'demo'\n\
+             \n\
+             [contents]\n\
+             'missing/demo.conf' = '/etc/demo.conf'\n\
+             \n\
+             [requires]\n\
+             'bash' = '>= 4.0'\n\
+             \n\
+             [requires_pre]\n\
+             'shadow-utils' = ''\n\
+             \n\
+             [provides]\n\
+             'demo-tools' = '= 1.0'\n\
+             \n\
+             [conflicts]\n\
+             'old-demo' = '< 1.0'\n\
+             \n\
+             [obsoletes]\n\
+             'legacy-demo' = ''\n\
+             \n\
+             [packages.devel]\n\
+             desc = 'headers'\n",
+        )
+        .unwrap();
+        let err = Err::default();
+        let main = Candidate::from_config(&config, "demo", &config.main_package(), &err).unwrap();
+        let deps = |deps: &[Dep]| deps.iter().map(|d| d.to_string()).collect::<Vec<_>>();
+        assert_eq!(main.nevra(), "demo-1.0-2.noarch");
+        assert_eq!(
+            deps(&main.requires),
+            vec!["bash >= 4.0", "shadow-utils (pre)", "(a or b)"]
+        );
+        assert_eq!(
+            deps(&main.provides),
+            vec!["demo-tools = 1.0", "demo = 1.0-2", "demo(noarch) = 1.0-2"]
+        );
+        assert_eq!(deps(&main.conflicts), vec!["old-demo < 1.0"]);
+        assert_eq!(deps(&main.obsoletes), vec!["legacy-demo"]);
+        // sources which are not staged yet still provide their destination
+        assert_eq!(main.files, vec!["/etc/demo.conf"]);
+
+        let (name, _, sub, err) = config.subpackages().pop().unwrap();
+        let devel = Candidate::from_config(&config, &name, &sub, &err).unwrap();
+        assert_eq!(devel.nevra(), "demo-devel-1.0-2.noarch");
+        assert_eq!(deps(&devel.requires), vec!["demo = 1.0-2"]);
+        assert!(check(&[main, devel], &[]).unsatisfied.iter().all(|(_, d)| {
+            d.name == "bash" || d.name == "shadow-utils" || d.name == "(a or b)"
+        }));
+    }
+}
@@ -20,7 +20,7 @@ pub fn cli_build<'a>() -> ArgMatches<'a> {
         )
         .subcommand(
             App::new("pkg")
-                .about("packages the rpm")
+                .about("packages the rpm, or a directory of rpms when subpackages are declared")
                 .arg(
                     Arg::with_name("config")
                         .index(1)
@@ -35,6 +35,284 @@ pub fn cli_build<'a>() -> ArgMatches<'a> {
                         .takes_value(true)
                         .required(true)
                         .multiple(false),
+                )
+                .arg(
+                    Arg::with_name("reproducible")
+                        .long("reproducible")
+                        .takes_value(false)
+                        .help("clamps timestamps to SOURCE_DATE_EPOCH for byte identical output"),
+                )
+                .arg(
+                    Arg::with_name("stream")
+                        .long("stream")
+                        .takes_value(false)
+                        .help("compresses contents through a temporary file, bounding memory use"),
+                )
+                .arg(
+                    Arg::with_name("jobs")
+                        .long("jobs")
+                        .short("j")
+                        .takes_value(true)
+                        .validator(validate_jobs)
+                        .help("threads used to read, digest, and compress contents, more than one implies --stream"),
+                ),
+        )
+        .subcommand(
+            App::new("deb")
+                .about("packages the config as a deb, or a directory of debs when subpackages are declared")
+                .arg(
+                    Arg::with_name("config")
+                        .index(1)
+                        .takes_value(true)
+                        .required(true)
+                        .multiple(false)
+                        .validator(validate_config),
+                )
+                .arg(
+                    Arg::with_name("output")
+                        .index(2)
+                        .takes_value(true)
+                        .required(true)
+                        .multiple(false),
+                )
+                .arg(
+                    Arg::with_name("reproducible")
+                        .long("reproducible")
+                        .takes_value(false)
+                        .help("clamps timestamps to SOURCE_DATE_EPOCH for byte identical output"),
+                ),
+        )
+        .subcommand(
+            App::new("srpm")
+                .about("packages the config, its scripts, and declared sources as a source rpm")
+                .arg(
+                    Arg::with_name("config")
+                        .index(1)
+                        .takes_value(true)
+                        .required(true)
+                        .multiple(false)
+                        .validator(validate_config),
+                )
+                .arg(
+                    Arg::with_name("output")
+                        .index(2)
+                        .takes_value(true)
+                        .required(true)
+                        .multiple(false),
+                )
+                .arg(
+                    Arg::with_name("reproducible")
+                        .long("reproducible")
+                        .takes_value(false)
+                        .help("clamps timestamps to SOURCE_DATE_EPOCH for byte identical output"),
+                ),
+        )
+        .subcommand(
+            App::new("init")
+                .about("generates a config from a staged install root")
+                .arg(
+                    Arg::with_name("root")
+                        .index(1)
+                        .takes_value(true)
+                        .required(true)
+                        .multiple(false)
+                        .validator(validate_dir),
+                )
+                .arg(
+                    Arg::with_name("config")
+                        .index(2)
+                        .takes_value(true)
+                        .required(true)
+                        .multiple(false),
+                ),
+        )
+        .subcommand(
+            App::new("inspect")
+                .about("prints the header, dependencies, scripts, changelog and files of an rpm")
+                .arg(
+                    Arg::with_name("rpm")
+                        .index(1)
+                        .takes_value(true)
+                        .required(true)
+                        .multiple(false),
+                )
+                .arg(
+                    Arg::with_name("json")
+                        .long("json")
+                        .takes_value(false)
+                        .help("prints json rather than text"),
+                ),
+        )
+        .subcommand(
+            App::new("import")
+                .about("extracts an rpm and generates a config which reproduces it")
+                .arg(
+                    Arg::with_name("rpm")
+                        .index(1)
+                        .takes_value(true)
+                        .required(true)
+                        .multiple(false),
+                )
+                .arg(
+                    Arg::with_name("dir")
+                        .index(2)
+                        .takes_value(true)
+                        .required(true)
+                        .multiple(false),
+                ),
+        )
+        .subcommand(
+            App::new("from-spec")
+                .about("converts an rpm spec file into a config")
+                .arg(
+                    Arg::with_name("spec")
+                        .index(1)
+                        .takes_value(true)
+                        .required(true)
+                        .multiple(false),
+                )
+                .arg(
+                    Arg::with_name("dir")
+                        .index(2)
+                        .takes_value(true)
+                        .required(true)
+                        .multiple(false),
+                ),
+        )
+        .subcommand(
+            App::new("diff")
+                .about("compares two rpms, or a config against a built rpm")
+                .arg(
+                    Arg::with_name("old")
+                        .index(1)
+                        .takes_value(true)
+                        .required(true)
+                        .multiple(false),
+                )
+                .arg(
+                    Arg::with_name("new")
+                        .index(2)
+                        .takes_value(true)
+                        .required(true)
+                        .multiple(false),
+                ),
+        )
+        .subcommand(
+            App::new("extract")
+                .about("unpacks the files of an rpm into a directory")
+                .arg(
+                    Arg::with_name("rpm")
+                        .index(1)
+                        .takes_value(true)
+                        .required(true)
+                        .multiple(false),
+                )
+                .arg(
+                    Arg::with_name("dir")
+                        .index(2)
+                        .takes_value(true)
+                        .required(true)
+                        .multiple(false),
+                )
+                .arg(
+                    Arg::with_name("owners")
+                        .long("owners")
+                        .takes_value(false)
+                        .help("applies the ownership of every file, requires root"),
+                )
+                .arg(
+                    Arg::with_name("verbose")
+                        .short("v")
+                        .long("verbose")
+                        .takes_value(false)
+                        .help("prints every path as it is extracted"),
+                ),
+        )
+        .subcommand(
+            App::new("repo")
+                .about("writes yum/dnf repository metadata for the rpms within a directory")
+                .arg(
+                    Arg::with_name("dir")
+                        .index(1)
+                        .takes_value(true)
+                        .required(true)
+                        .multiple(false)
+                        .validator(validate_dir),
+                )
+                .arg(
+                    Arg::with_name("update")
+                        .long("update")
+                        .takes_value(false)
+                        .help("reuses the existing metadata of packages whose size and mtime are unchanged"),
+                ),
+        )
+        .subcommand(
+            App::new("check-deps")
+                .about("checks the dependencies of a config or rpm against a repository")
+                .arg(
+                    Arg::with_name("target")
+                        .index(1)
+                        .takes_value(true)
+                        .required(true)
+                        .multiple(false),
+                )
+                .arg(
+                    Arg::with_name("repo")
+                        .long("repo")
+                        .takes_value(true)
+                        .required(true)
+                        .multiple(false)
+                        .validator(validate_dir)
+                        .help("directory of rpms, with or without repodata"),
+                ),
+        )
+        .subcommand(
+            App::new("simulate")
+                .about("installs an rpm into a scratch root, running its install scriptlets")
+                .arg(
+                    Arg::with_name("rpm")
+                        .index(1)
+                        .takes_value(true)
+                        .required(true)
+                        .multiple(false),
+                )
+                .arg(
+                    Arg::with_name("root")
+                        .long("root")
+                        .takes_value(true)
+                        .required(true)
+                        .multiple(false)
+                        .help("directory standing in for /, created if missing"),
+                )
+                .arg(
+                    Arg::with_name("upgrade")
+                        .long("upgrade")
+                        .takes_value(false)
+                        .help("passes scriptlets the install count of an upgrade, 2, rather than 1"),
+                )
+                .arg(
+                    Arg::with_name("unsafe-host")
+                        .long("unsafe-host")
+                        .takes_value(false)
+                        .help("runs scriptlets on the host, unconfined, so they may change anything outside of the root"),
+                ),
+        )
+        .subcommand(
+            App::new("vercmp")
+                .about("compares two [epoch:]version[-release] strings as rpm would")
+                .arg(
+                    Arg::with_name("a")
+                        .index(1)
+                        .takes_value(true)
+                        .required(true)
+                        .multiple(false),
+                )
+                .arg(
+                    Arg::with_name("b")
+                        .index(2)
+                        .takes_value(true)
+                        .required(true)
+                        .multiple(false),
                 ),
         )
         .get_matches()
@@ -43,6 +321,18 @@ pub fn cli_build<'a>() -> ArgMatches<'a> {
 pub enum AppWork<'a> {
     Format(Format<'a>),
     Package(Package<'a>),
+    SourcePackage(SourcePackage<'a>),
+    Init(Init<'a>),
+    VerCmp(VerCmp<'a>),
+    Inspect(Inspect<'a>),
+    Import(Import<'a>),
+    FromSpec(FromSpec<'a>),
+    Diff(Diff<'a>),
+    Extract(Extract<'a>),
+    Repo(Repo<'a>),
+    CheckDeps(CheckDeps<'a>),
+    Simulate(Simulate<'a>),
+    Deb(Deb<'a>),
 }
 impl<'a> AppWork<'a> {
     pub fn from_args(arg: &'a ArgMatches<'a>) -> AppWork<'a> {
@@ -53,6 +343,66 @@ impl<'a> AppWork<'a> {
             ("pkg", Option::Some(ref args)) => AppWork::Package(Package {
                 config: args.value_of("config").unwrap(),
                 output: args.value_of("output").unwrap(),
+                reproducible: args.is_present("reproducible"),
+                stream: args.is_present("stream"),
+                jobs: args
+                    .value_of("jobs")
+                    .map(|jobs| jobs.parse().unwrap())
+                    .unwrap_or(1),
+            }),
+            ("srpm", Option::Some(ref args)) => AppWork::SourcePackage(SourcePackage {
+                config: args.value_of("config").unwrap(),
+                output: args.value_of("output").unwrap(),
+                reproducible: args.is_present("reproducible"),
+            }),
+            ("init", Option::Some(ref args)) => AppWork::Init(Init {
+                root: args.value_of("root").unwrap(),
+                config: args.value_of("config").unwrap(),
+            }),
+            ("vercmp", Option::Some(ref args)) => AppWork::VerCmp(VerCmp {
+                a: args.value_of("a").unwrap(),
+                b: args.value_of("b").unwrap(),
+            }),
+            ("inspect", Option::Some(ref args)) => AppWork::Inspect(Inspect {
+                rpm: args.value_of("rpm").unwrap(),
+                json: args.is_present("json"),
+            }),
+            ("import", Option::Some(ref args)) => AppWork::Import(Import {
+                rpm: args.value_of("rpm").unwrap(),
+                dir: args.value_of("dir").unwrap(),
+            }),
+            ("from-spec", Option::Some(ref args)) => AppWork::FromSpec(FromSpec {
+                spec: args.value_of("spec").unwrap(),
+                dir: args.value_of("dir").unwrap(),
+            }),
+            ("diff", Option::Some(ref args)) => AppWork::Diff(Diff {
+                old: args.value_of("old").unwrap(),
+                new: args.value_of("new").unwrap(),
+            }),
+            ("extract", Option::Some(ref args)) => AppWork::Extract(Extract {
+                rpm: args.value_of("rpm").unwrap(),
+                dir: args.value_of("dir").unwrap(),
+                owners: args.is_present("owners"),
+                verbose: args.is_present("verbose"),
+            }),
+            ("repo", Option::Some(ref args)) => AppWork::Repo(Repo {
+                dir: args.value_of("dir").unwrap(),
+                update: args.is_present("update"),
+            }),
+            ("check-deps", Option::Some(ref args)) => AppWork::CheckDeps(CheckDeps {
+                target: args.value_of("target").unwrap(),
+                repo: args.value_of("repo").unwrap(),
+            }),
+            ("simulate", Option::Some(ref args)) => AppWork::Simulate(Simulate {
+                rpm: args.value_of("rpm").unwrap(),
+                root: args.value_of("root").unwrap(),
+                upgrade: args.is_present("upgrade"),
+                unsafe_host: args.is_present("unsafe-host"),
+            }),
+            ("deb", Option::Some(ref args)) => AppWork::Deb(Deb {
+                config: args.value_of("config").unwrap(),
+                output: args.value_of("output").unwrap(),
+                reproducible: args.is_present("reproducible"),
             }),
             (idk, _) => panic!("unrecongized subcommand: {}", idk),
         }
@@ -62,6 +412,18 @@ impl<'a> AppWork<'a> {
         match self {
             &Self::Format(ref fmt) => fmt.work(),
             &Self::Package(ref pkg) => pkg.work(),
+            &Self::SourcePackage(ref srpm) => srpm.work(),
+            &Self::Init(ref init) => init.work(),
+            &Self::VerCmp(ref vercmp) => vercmp.work(),
+            &Self::Inspect(ref inspect) => inspect.work(),
+            &Self::Import(ref import) => import.work(),
+            &Self::FromSpec(ref from_spec) => from_spec.work(),
+            &Self::Diff(ref diff) => diff.work(),
+            &Self::Extract(ref extract) => extract.work(),
+            &Self::Repo(ref repo) => repo.work(),
+            &Self::CheckDeps(ref check_deps) => check_deps.work(),
+            &Self::Simulate(ref simulate) => simulate.work(),
+            &Self::Deb(ref deb) => deb.work(),
         }
     }
 }
@@ -118,13 +480,16 @@ impl<'a> Format<'a> {
 pub struct Package<'a> {
     pub config: &'a str,
     pub output: &'a str,
+    pub reproducible: bool,
+    pub stream: bool,
+    pub jobs: usize,
 }
 impl<'a> Package<'a> {
     fn work(&self) -> Result<(), String> {
         use super::core::ConfigFile;
         use super::toml::de::from_str;
-        use std::fs::{read_to_string, OpenOptions};
-        use std::io::Write;
+        use std::fs::{create_dir_all, read_to_string};
+        use std::path::Path;
 
         let data = match read_to_string(self.config) {
             Ok(data) => data,
@@ -135,7 +500,7 @@ impl<'a> Package<'a> {
                 ))
             }
         };
-        let values = match from_str::<ConfigFile>(&data) {
+        let mut values = match from_str::<ConfigFile>(&data) {
             Ok(data) => data,
             Err(e) => {
                 return Err(format!(
@@ -144,41 +509,708 @@ impl<'a> Package<'a> {
                 ))
             }
         };
-        let output = match values.build() {
+        if self.reproducible {
+            values.rpm.reproducible = Option::Some(true);
+        }
+        // the in-memory build is single threaded, so jobs imply streaming
+        // for every package which can be streamed
+        if self.stream || (self.jobs > 1 && values.signature.is_none()) {
+            return self.work_streaming(&values);
+        }
+        if self.jobs > 1 {
+            eprintln!("warning: signed packages are built in memory, --jobs was ignored");
+        }
+
+        // without subpackages the output is the rpm itself
+        let mut warnings = Vec::new();
+        if values.packages.is_empty() {
+            let output = match values.build(&mut warnings) {
+                Ok(data) => data,
+                Err(e) => return Err(format!("failed to build RPM. error:'{:?}'", e)),
+            };
+            for warning in warnings.iter() {
+                eprintln!("warning: {}", warning);
+            }
+            return write_package(&output, self.output);
+        }
+
+        // with subpackages the output is a directory
+        let outputs = match values.build_all(&mut warnings) {
             Ok(data) => data,
             Err(e) => return Err(format!("failed to build RPM. error:'{:?}'", e)),
         };
-        let mut f = match OpenOptions::new()
-            .read(false)
-            .write(true)
-            .create(true)
-            .truncate(true)
-            .open(self.output)
-        {
-            Ok(file) => file,
+        for warning in warnings.iter() {
+            eprintln!("warning: {}", warning);
+        }
+        match create_dir_all(self.output) {
+            Ok(()) => {}
             Err(e) => {
                 return Err(format!(
-                    "failed to open/create output path:'{}' error:'{:?}'",
+                    "failed to create output directory:'{}' error:'{:?}'",
                     self.output, e
                 ))
             }
         };
-        match output.write(&mut f) {
+        for (file_name, output) in outputs.iter() {
+            let path = Path::new(self.output).join(file_name);
+            write_package(output, &path.to_string_lossy())?;
+        }
+        Ok(())
+    }
+
+    /// builds every package with `StreamPackage`, so file contents are never
+    /// held in memory.
+    fn work_streaming(&self, values: &super::core::ConfigFile) -> Result<(), String> {
+        use std::fs::create_dir_all;
+        use std::path::Path;
+
+        let outputs = match values.stream_all() {
+            Ok(data) => data,
+            Err(e) => return Err(format!("failed to build RPM. error:'{:?}'", e)),
+        };
+        if values.packages.is_empty() {
+            return write_stream(&outputs[0].1, self.output, self.jobs);
+        }
+        match create_dir_all(self.output) {
             Ok(()) => {}
             Err(e) => {
                 return Err(format!(
-                    "failed to write output to path:'{}' error:'{:?}'",
+                    "failed to create output directory:'{}' error:'{:?}'",
                     self.output, e
                 ))
             }
         };
-        match f.flush() {
-            Ok(()) => Ok(()),
-            Err(e) => Err(format!(
-                "failed to flush path:'{}' after RPM writing. error:'{:?}'",
-                self.output, e
-            )),
+        for (file_name, output) in outputs.iter() {
+            let path = Path::new(self.output).join(file_name);
+            write_stream(output, &path.to_string_lossy(), self.jobs)?;
+        }
+        Ok(())
+    }
+}
+
+/// SourcePackage allows for creating source packages
+pub struct SourcePackage<'a> {
+    pub config: &'a str,
+    pub output: &'a str,
+    pub reproducible: bool,
+}
+impl<'a> SourcePackage<'a> {
+    fn work(&self) -> Result<(), String> {
+        use super::core::ConfigFile;
+        use super::toml::de::from_str;
+        use std::fs::read_to_string;
+
+        let data = match read_to_string(self.config) {
+            Ok(data) => data,
+            Err(e) => {
+                return Err(format!(
+                    "could not read config:'{}' error:'{:?}'",
+                    self.config, e
+                ))
+            }
+        };
+        let mut values = match from_str::<ConfigFile>(&data) {
+            Ok(data) => data,
+            Err(e) => {
+                return Err(format!(
+                    "could not read config:'{}' as toml. error:'{:?}'",
+                    self.config, e
+                ))
+            }
+        };
+        if self.reproducible {
+            values.rpm.reproducible = Option::Some(true);
         }
+        let output = match values.build_srpm(self.config) {
+            Ok(data) => data,
+            Err(e) => return Err(format!("failed to build source RPM. error:'{:?}'", e)),
+        };
+        write_stream(&output, self.output, 1)
+    }
+}
+
+/// writes a built RPM to the path, replacing any existing file
+fn write_package(output: &super::rpm::Package, path: &str) -> Result<(), String> {
+    use std::fs::OpenOptions;
+    use std::io::Write;
+
+    let mut f = match OpenOptions::new()
+        .read(false)
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .open(path)
+    {
+        Ok(file) => file,
+        Err(e) => {
+            return Err(format!(
+                "failed to open/create output path:'{}' error:'{:?}'",
+                path, e
+            ))
+        }
+    };
+    match output.write(&mut f) {
+        Ok(()) => {}
+        Err(e) => {
+            return Err(format!(
+                "failed to write output to path:'{}' error:'{:?}'",
+                path, e
+            ))
+        }
+    };
+    match f.flush() {
+        Ok(()) => Ok(()),
+        Err(e) => Err(format!(
+            "failed to flush path:'{}' after RPM writing. error:'{:?}'",
+            path, e
+        )),
+    }
+}
+
+/// streams an RPM to the path, replacing any existing file
+fn write_stream(
+    output: &super::stream::StreamPackage,
+    path: &str,
+    jobs: usize,
+) -> Result<(), String> {
+    use std::fs::OpenOptions;
+    use std::io::{BufWriter, Write};
+
+    let f = match OpenOptions::new()
+        .read(false)
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .open(path)
+    {
+        Ok(file) => file,
+        Err(e) => {
+            return Err(format!(
+                "failed to open/create output path:'{}' error:'{:?}'",
+                path, e
+            ))
+        }
+    };
+    let mut f = BufWriter::new(f);
+    let warnings = match output.write(&mut f, jobs) {
+        Ok(warnings) => warnings,
+        Err(e) => {
+            return Err(format!(
+                "failed to build RPM to path:'{}' error:'{:?}'",
+                path, e
+            ))
+        }
+    };
+    for warning in warnings.iter() {
+        eprintln!("warning: {}", warning);
+    }
+    match f.flush() {
+        Ok(()) => Ok(()),
+        Err(e) => Err(format!(
+            "failed to flush path:'{}' after RPM writing. error:'{:?}'",
+            path, e
+        )),
+    }
+}
+
+/// Deb packages a config as Debian binary packages.
+pub struct Deb<'a> {
+    pub config: &'a str,
+    pub output: &'a str,
+    pub reproducible: bool,
+}
+impl<'a> Deb<'a> {
+    fn work(&self) -> Result<(), String> {
+        use super::core::ConfigFile;
+        use super::toml::de::from_str;
+        use std::fs::{create_dir_all, read_to_string};
+        use std::path::Path;
+
+        let data = match read_to_string(self.config) {
+            Ok(data) => data,
+            Err(e) => {
+                return Err(format!(
+                    "could not read config:'{}' error:'{:?}'",
+                    self.config, e
+                ))
+            }
+        };
+        let mut values = match from_str::<ConfigFile>(&data) {
+            Ok(data) => data,
+            Err(e) => {
+                return Err(format!(
+                    "could not read config:'{}' as toml. error:'{:?}'",
+                    self.config, e
+                ))
+            }
+        };
+        if self.reproducible {
+            values.rpm.reproducible = Option::Some(true);
+        }
+
+        let outputs = match values.deb_all() {
+            Ok(data) => data,
+            Err(e) => return Err(format!("failed to build deb. error:'{:?}'", e)),
+        };
+        if values.packages.is_empty() {
+            return write_deb(&outputs[0].1, self.output);
+        }
+        match create_dir_all(self.output) {
+            Ok(()) => {}
+            Err(e) => {
+                return Err(format!(
+                    "failed to create output directory:'{}' error:'{:?}'",
+                    self.output, e
+                ))
+            }
+        };
+        for (file_name, output) in outputs.iter() {
+            let path = Path::new(self.output).join(file_name);
+            write_deb(output, &path.to_string_lossy())?;
+        }
+        Ok(())
+    }
+}
+
+fn write_deb(output: &super::deb::DebPackage, path: &str) -> Result<(), String> {
+    use std::fs::OpenOptions;
+    use std::io::{BufWriter, Write};
+
+    let f = match OpenOptions::new()
+        .read(false)
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .open(path)
+    {
+        Ok(file) => file,
+        Err(e) => {
+            return Err(format!(
+                "failed to open/create output path:'{}' error:'{:?}'",
+                path, e
+            ))
+        }
+    };
+    let mut f = BufWriter::new(f);
+    let warnings = match output.write(&mut f) {
+        Ok(warnings) => warnings,
+        Err(e) => {
+            return Err(format!(
+                "failed to build deb to path:'{}' error:'{:?}'",
+                path, e
+            ))
+        }
+    };
+    for warning in warnings.iter() {
+        eprintln!("warning: {}", warning);
+    }
+    match f.flush() {
+        Ok(()) => Ok(()),
+        Err(e) => Err(format!(
+            "failed to flush path:'{}' after deb writing. error:'{:?}'",
+            path, e
+        )),
+    }
+}
+
+/// Init generates a config from a staged install root.
+pub struct Init<'a> {
+    pub root: &'a str,
+    pub config: &'a str,
+}
+impl<'a> Init<'a> {
+    fn work(&self) -> Result<(), String> {
+        use super::init::scan_root;
+        use super::toml::ser::to_string_pretty;
+        use std::fs::write;
+        use std::path::Path;
+
+        let values = match scan_root(Path::new(self.root)) {
+            Ok(values) => values,
+            Err(e) => {
+                return Err(format!(
+                    "failed to scan install root:'{}' error:'{:?}'",
+                    self.root, e
+                ))
+            }
+        };
+        let output = match to_string_pretty(&values) {
+            Ok(data) => data,
+            Err(e) => {
+                return Err(format!(
+                    "after scanning root:'{}' encounter toml error while serializing. error:'{:?}'",
+                    self.root, e
+                ))
+            }
+        };
+        match write(self.config, output.as_bytes()) {
+            Ok(()) => Ok(()),
+            Err(e) => Err(format!(
+                "failed to write generated config:'{}' error:'{:?}'",
+                self.config, e
+            )),
+        }
+    }
+}
+
+/// VerCmp compares two versions using rpm's ordering.
+pub struct VerCmp<'a> {
+    pub a: &'a str,
+    pub b: &'a str,
+}
+impl<'a> VerCmp<'a> {
+    fn work(&self) -> Result<(), String> {
+        use super::vercmp::compare_evr;
+        use super::versions::Evr;
+        use std::cmp::Ordering;
+
+        let a = match Evr::parse(self.a) {
+            Ok(evr) => evr,
+            Err(e) => {
+                return Err(format!(
+                    "could not parse version:'{}' error:'{}'",
+                    self.a, e
+                ))
+            }
+        };
+        let b = match Evr::parse(self.b) {
+            Ok(evr) => evr,
+            Err(e) => {
+                return Err(format!(
+                    "could not parse version:'{}' error:'{}'",
+                    self.b, e
+                ))
+            }
+        };
+        let op = match compare_evr(&a, &b) {
+            Ordering::Less => "<",
+            Ordering::Equal => "==",
+            Ordering::Greater => ">",
+        };
+        println!("{} {} {}", self.a, op, self.b);
+        Ok(())
+    }
+}
+
+/// Inspect prints what an existing RPM contains.
+pub struct Inspect<'a> {
+    pub rpm: &'a str,
+    pub json: bool,
+}
+impl<'a> Inspect<'a> {
+    fn work(&self) -> Result<(), String> {
+        use super::inspect::Inspection;
+        use super::serde_json::to_string_pretty;
+
+        let inspection = match Inspection::open(self.rpm) {
+            Ok(inspection) => inspection,
+            Err(e) => return Err(format!("failed to read RPM. error:'{:?}'", e)),
+        };
+        if !self.json {
+            print!("{}", inspection);
+            return Ok(());
+        }
+        match to_string_pretty(&inspection) {
+            Ok(data) => {
+                println!("{}", data);
+                Ok(())
+            }
+            Err(e) => Err(format!(
+                "failed to serialize rpm:'{}' as json. error:'{:?}'",
+                self.rpm, e
+            )),
+        }
+    }
+}
+
+/// Import converts an existing RPM into a config.
+pub struct Import<'a> {
+    pub rpm: &'a str,
+    pub dir: &'a str,
+}
+impl<'a> Import<'a> {
+    fn work(&self) -> Result<(), String> {
+        use super::import::import_rpm;
+        use super::toml::ser::to_string_pretty;
+        use std::fs::{create_dir_all, write};
+        use std::path::Path;
+
+        let dir = Path::new(self.dir);
+        match create_dir_all(dir) {
+            Ok(()) => {}
+            Err(e) => {
+                return Err(format!(
+                    "failed to create output directory:'{}' error:'{:?}'",
+                    self.dir, e
+                ))
+            }
+        };
+        let (values, warnings) = match import_rpm(self.rpm, dir) {
+            Ok(values) => values,
+            Err(e) => {
+                return Err(format!(
+                    "failed to import rpm:'{}' error:'{:?}'",
+                    self.rpm, e
+                ))
+            }
+        };
+        for warning in warnings.iter() {
+            eprintln!("warning: {}", warning);
+        }
+        let output = match to_string_pretty(&values) {
+            Ok(data) => data,
+            Err(e) => {
+                return Err(format!(
+                    "after importing rpm:'{}' encounter toml error while serializing. error:'{:?}'",
+                    self.rpm, e
+                ))
+            }
+        };
+        let config = dir.join("config.toml");
+        match write(&config, output.as_bytes()) {
+            Ok(()) => Ok(()),
+            Err(e) => Err(format!(
+                "failed to write generated config:'{}' error:'{:?}'",
+                config.display(),
+                e
+            )),
+        }
+    }
+}
+
+/// FromSpec converts an rpm spec file into a config.
+pub struct FromSpec<'a> {
+    pub spec: &'a str,
+    pub dir: &'a str,
+}
+impl<'a> FromSpec<'a> {
+    fn work(&self) -> Result<(), String> {
+        use super::spec::from_spec;
+        use super::toml::ser::to_string_pretty;
+        use std::fs::{create_dir_all, write};
+        use std::path::Path;
+
+        let dir = Path::new(self.dir);
+        match create_dir_all(dir) {
+            Ok(()) => {}
+            Err(e) => {
+                return Err(format!(
+                    "failed to create output directory:'{}' error:'{:?}'",
+                    self.dir, e
+                ))
+            }
+        };
+        let (values, warnings) = match from_spec(self.spec, dir) {
+            Ok(values) => values,
+            Err(e) => {
+                return Err(format!(
+                    "failed to convert spec:'{}' error:'{:?}'",
+                    self.spec, e
+                ))
+            }
+        };
+        for warning in warnings.iter() {
+            eprintln!("warning: {}", warning);
+        }
+        let output = match to_string_pretty(&values) {
+            Ok(data) => data,
+            Err(e) => {
+                return Err(format!(
+                "after converting spec:'{}' encounter toml error while serializing. error:'{:?}'",
+                self.spec, e
+            ))
+            }
+        };
+        let config = dir.join("config.toml");
+        match write(&config, output.as_bytes()) {
+            Ok(()) => Ok(()),
+            Err(e) => Err(format!(
+                "failed to write generated config:'{}' error:'{:?}'",
+                config.display(),
+                e
+            )),
+        }
+    }
+}
+
+/// Diff reports what changed between two packages, either of which may be
+/// a config.
+pub struct Diff<'a> {
+    pub old: &'a str,
+    pub new: &'a str,
+}
+impl<'a> Diff<'a> {
+    fn work(&self) -> Result<(), String> {
+        use super::diff;
+
+        let (old, new) = match diff::load(self.old, self.new) {
+            Ok(values) => values,
+            Err(e) => {
+                return Err(format!(
+                    "failed to load old:'{}' or new:'{}' error:'{:?}'",
+                    self.old, self.new, e
+                ))
+            }
+        };
+        print!("{}", diff::Diff::new(&old, &new));
+        Ok(())
+    }
+}
+
+/// Extract unpacks the payload of an RPM.
+pub struct Extract<'a> {
+    pub rpm: &'a str,
+    pub dir: &'a str,
+    pub owners: bool,
+    pub verbose: bool,
+}
+impl<'a> Extract<'a> {
+    fn work(&self) -> Result<(), String> {
+        use super::extract::extract_rpm;
+        use std::fs::create_dir_all;
+        use std::path::Path;
+
+        let dir = Path::new(self.dir);
+        match create_dir_all(dir) {
+            Ok(()) => {}
+            Err(e) => {
+                return Err(format!(
+                    "failed to create output directory:'{}' error:'{:?}'",
+                    self.dir, e
+                ))
+            }
+        };
+        let (files, warnings) = match extract_rpm(self.rpm, dir, self.owners) {
+            Ok(values) => values,
+            Err(e) => {
+                return Err(format!(
+                    "failed to extract rpm:'{}' error:'{:?}'",
+                    self.rpm, e
+                ))
+            }
+        };
+        if self.verbose {
+            for file in files.iter() {
+                println!("{}", file.path);
+            }
+        }
+        for warning in warnings.iter() {
+            eprintln!("warning: {}", warning);
+        }
+        Ok(())
+    }
+}
+
+/// Repo writes the repodata of a directory of RPMs.
+pub struct Repo<'a> {
+    pub dir: &'a str,
+    pub update: bool,
+}
+impl<'a> Repo<'a> {
+    fn work(&self) -> Result<(), String> {
+        use super::repo::write_repo;
+        use std::path::Path;
+
+        match write_repo(Path::new(self.dir), self.update) {
+            Ok(_) => Ok(()),
+            Err(e) => Err(format!(
+                "failed to write repodata for dir:'{}' error:'{:?}'",
+                self.dir, e
+            )),
+        }
+    }
+}
+
+/// CheckDeps resolves the dependencies of packages against a repository.
+pub struct CheckDeps<'a> {
+    pub target: &'a str,
+    pub repo: &'a str,
+}
+impl<'a> CheckDeps<'a> {
+    fn work(&self) -> Result<(), String> {
+        use super::checkdeps::{check, load_repo, load_targets};
+        use std::path::Path;
+
+        let targets = match load_targets(self.target) {
+            Ok(targets) => targets,
+            Err(e) => {
+                return Err(format!(
+                    "failed to load target:'{}' error:'{:?}'",
+                    self.target, e
+                ))
+            }
+        };
+        let repo = match load_repo(Path::new(self.repo)) {
+            Ok(repo) => repo,
+            Err(e) => {
+                return Err(format!(
+                    "failed to load repo:'{}' error:'{:?}'",
+                    self.repo, e
+                ))
+            }
+        };
+        let report = check(&targets, &repo);
+        print!("{}", report);
+        if report.is_ok() {
+            Ok(())
+        } else {
+            Err(format!(
+                "target:'{}' cannot be installed from repo:'{}'",
+                self.target, self.repo
+            ))
+        }
+    }
+}
+
+/// Simulate installs an RPM into a scratch root to exercise its scriptlets.
+pub struct Simulate<'a> {
+    pub rpm: &'a str,
+    pub root: &'a str,
+    pub upgrade: bool,
+    pub unsafe_host: bool,
+}
+impl<'a> Simulate<'a> {
+    fn work(&self) -> Result<(), String> {
+        use super::simulate::simulate_rpm;
+        use std::path::Path;
+
+        let root = Path::new(self.root);
+        let simulation = match simulate_rpm(self.rpm, root, self.upgrade, self.unsafe_host) {
+            Ok(simulation) => simulation,
+            Err(e) => {
+                return Err(format!(
+                    "failed to simulate rpm:'{}' root:'{}' error:'{:?}'",
+                    self.rpm, self.root, e
+                ))
+            }
+        };
+        print!("{}", simulation);
+        for warning in simulation.warnings.iter() {
+            eprintln!("warning: {}", warning);
+        }
+        if simulation.is_ok() {
+            Ok(())
+        } else {
+            Err(format!("a scriptlet of rpm:'{}' failed", self.rpm))
+        }
+    }
+}
+
+fn validate_jobs(jobs: String) -> Result<(), String> {
+    match jobs.parse::<usize>() {
+        Ok(0) => Err("jobs must be at least 1".to_string()),
+        Ok(_) => Ok(()),
+        Err(e) => Err(format!("jobs:'{}' is not a number error:'{:?}'", &jobs, e)),
+    }
+}
+
+fn validate_dir(path: String) -> Result<(), String> {
+    use std::path::Path;
+
+    if Path::new(&path).is_dir() {
+        Ok(())
+    } else {
+        Err(format!("path:'{}' is not a directory", &path))
     }
 }
 
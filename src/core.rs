@@ -2,20 +2,33 @@ use std::collections::BTreeMap;
 
 use super::chrono::NaiveDateTime;
 use super::errors::Err;
-use super::rpm::{Dependency, RPMBuilder, RPMError, RPMPackage};
+use super::rpm::{Package, PackageBuilder};
 use super::serde::{Deserialize, Serialize};
 
-use super::changelog::ChangeLogEntry;
+use super::changelog::{timestamp, ChangeLogEntry};
+use super::deb::{deb_arch, DebPackage};
 use super::fileopts::FileOptions;
 use super::rpm_meta::RPM;
 use super::scripts::Scripts;
 use super::sign::Sign;
-use super::versions::into_dependency;
+use super::srpm::Srpm;
+use super::stream::StreamPackage;
+use super::subpackage::SubPackage;
+use super::vercmp::satisfies;
+use super::versions::{Constraint, Constraints, Evr};
 
 /// ConfigFile is the top level format for specifying how to
 /// build an RPM.
 #[derive(Clone, Hash, Debug, Deserialize, Serialize, Default)]
 pub struct ConfigFile {
+    /*
+     * plain values must be serialized before any table
+     *
+     */
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub rich_requires: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub rich_conflicts: Vec<String>,
     #[serde(default)]
     pub rpm: RPM,
     #[serde(default)]
@@ -23,69 +36,323 @@ pub struct ConfigFile {
     #[serde(default)]
     pub changelog: BTreeMap<NaiveDateTime, ChangeLogEntry>,
     #[serde(default)]
-    pub requires: BTreeMap<String, String>,
+    pub requires: BTreeMap<String, Constraints>,
     #[serde(default)]
-    pub obsoletes: BTreeMap<String, String>,
+    pub requires_pre: BTreeMap<String, Constraints>,
     #[serde(default)]
-    pub conflicts: BTreeMap<String, String>,
+    pub requires_post: BTreeMap<String, Constraints>,
     #[serde(default)]
-    pub provides: BTreeMap<String, String>,
+    pub requires_preun: BTreeMap<String, Constraints>,
+    #[serde(default)]
+    pub requires_postun: BTreeMap<String, Constraints>,
+    #[serde(default)]
+    pub obsoletes: BTreeMap<String, Constraints>,
+    #[serde(default)]
+    pub conflicts: BTreeMap<String, Constraints>,
+    #[serde(default)]
+    pub provides: BTreeMap<String, Constraints>,
+    #[serde(default)]
+    pub recommends: BTreeMap<String, Constraints>,
+    #[serde(default)]
+    pub suggests: BTreeMap<String, Constraints>,
+    #[serde(default)]
+    pub supplements: BTreeMap<String, Constraints>,
+    #[serde(default)]
+    pub enhances: BTreeMap<String, Constraints>,
     #[serde(default)]
     pub scripts: Option<Scripts>,
     #[serde(default)]
     pub signature: Option<Sign>,
+    #[serde(default)]
+    pub srpm: Option<Srpm>,
+    #[serde(default)]
+    pub packages: BTreeMap<String, SubPackage>,
 }
 impl ConfigFile {
-    /// build the RPM in memory
-    pub fn build(&self) -> Result<RPMPackage, Err> {
-        let mut builder = self.rpm.build();
+    /// build the RPM in memory, anything which was skipped is reported
+    /// within `warnings`
+    pub fn build(&self, warnings: &mut Vec<String>) -> Result<Package, Err> {
+        let err = Err::default()
+            .note("rpm", &self.rpm.name)
+            .note("version", &self.rpm.version)
+            .note("desc", &self.rpm.desc);
 
+        let main = self.main_package();
+        self.check_self_references(&self.rpm.name, &main, &err)?;
+        self.build_package(&self.rpm.name, &self.rpm.desc, &main, warnings, &err)
+    }
+
+    /// builds the main package, and every subpackage declared within
+    /// `packages`. Each package is returned with its file name.
+    pub fn build_all(&self, warnings: &mut Vec<String>) -> Result<Vec<(String, Package)>, Err> {
+        let mut output = vec![(self.file_name(&self.rpm.name), self.build(warnings)?)];
+        for (name, desc, sub, err) in self.subpackages() {
+            self.check_self_references(&name, &sub, &err)?;
+            let pkg = self.build_package(&name, &desc, &sub, warnings, &err)?;
+            output.push((self.file_name(&name), pkg));
+        }
+        Ok(output)
+    }
+
+    /// like `build_all`, but every package is streamed to disk by
+    /// `StreamPackage::write` rather than being built in memory.
+    pub fn stream_all(&self) -> Result<Vec<(String, StreamPackage<'_>)>, Err> {
         let err = Err::default()
             .note("rpm", &self.rpm.name)
             .note("version", &self.rpm.version)
             .note("desc", &self.rpm.desc);
+        let main = self.main_package();
+        self.check_self_references(&self.rpm.name, &main, &err)?;
 
-        // package the contents
-        builder = self
-            .contents
+        let main = StreamPackage::new(self, &self.rpm.name, &self.rpm.desc, main, err);
+        let mut output = vec![(self.file_name(&self.rpm.name), main)];
+        for (name, desc, sub, err) in self.subpackages() {
+            self.check_self_references(&name, &sub, &err)?;
+            let file_name = self.file_name(&name);
+            output.push((file_name, StreamPackage::new(self, &name, &desc, sub, err)));
+        }
+        Ok(output)
+    }
+
+    /// like `stream_all`, but every package is built as a `.deb` by
+    /// `DebPackage::write`.
+    pub fn deb_all(&self) -> Result<Vec<(String, DebPackage<'_>)>, Err> {
+        let err = Err::default()
+            .note("deb", &self.rpm.name)
+            .note("version", &self.rpm.version)
+            .note("desc", &self.rpm.desc);
+        let main = self.main_package();
+        self.check_self_references(&self.rpm.name, &main, &err)?;
+
+        let main = DebPackage::new(self, &self.rpm.name, &self.rpm.desc, main, err);
+        let mut output = vec![(self.deb_file_name(&self.rpm.name), main)];
+        for (name, desc, sub, err) in self.subpackages() {
+            self.check_self_references(&name, &sub, &err)?;
+            let file_name = self.deb_file_name(&name);
+            output.push((file_name, DebPackage::new(self, &name, &desc, sub, err)));
+        }
+        Ok(output)
+    }
+
+    /// the name, description, contents and error context of every
+    /// subpackage declared within `packages`
+    pub fn subpackages(&self) -> Vec<(String, String, SubPackage, Err)> {
+        self.packages
             .iter()
-            .map(|(source, options)| options.build(source))
-            .fold(Ok(builder), |builder_res, opts_bundle| {
-                (opts_bundle)(builder_res, &err)
-            })?;
+            .map(|(suffix, sub)| {
+                let name = format!("{}-{}", self.rpm.name, suffix);
+                let desc = sub.desc.as_ref().unwrap_or(&self.rpm.desc).clone();
+                let err = Err::default()
+                    .note("rpm", &name)
+                    .note("version", &self.rpm.version)
+                    .note("desc", &desc);
+
+                // subpackages require the exact build of the main package,
+                // alongside any constraint the config places on it
+                let pin = format!("= {}", self.evr());
+                let mut sub = sub.clone();
+                let requires = match sub.requires.get(&self.rpm.name) {
+                    Option::None => Constraints::One(pin),
+                    Option::Some(constraints) => constraints.and(pin),
+                };
+                sub.requires.insert(self.rpm.name.clone(), requires);
+                (name, desc, sub, err)
+            })
+            .collect()
+    }
+
+    fn build_package(
+        &self,
+        name: &str,
+        desc: &str,
+        pkg: &SubPackage,
+        warnings: &mut Vec<String>,
+        err: &Err,
+    ) -> Result<Package, Err> {
+        let mut builder = self.rpm.build_as(name, desc, err)?;
+        if let Option::Some(ref description) = pkg.description {
+            builder = builder.description(description.as_str());
+        }
+        builder = (self.reproducible())(builder, err)?;
+
+        // package the contents, dependencies, and scripts
+        builder = (pkg.build(self.rpm.auto_deps == Option::Some(true), warnings))(builder, err)?;
 
         // package the change log
         builder = self
             .changelog
             .iter()
-            .map(|(time, entry)| entry.build(time))
-            .fold(builder, |b, e| (e)(b));
+            .try_fold(builder, |b, (time, entry)| (entry.build(time))(b, err))?;
 
-        // package database interactions
-        builder = (add_rpm_db_interaction(self.requires.iter(), RPMBuilder::requires))(builder);
-        builder = (add_rpm_db_interaction(self.obsoletes.iter(), RPMBuilder::obsoletes))(builder);
-        builder = (add_rpm_db_interaction(self.conflicts.iter(), RPMBuilder::conflicts))(builder);
-        builder = (add_rpm_db_interaction(self.provides.iter(), RPMBuilder::provides))(builder);
-
-        // load scripts if we need to
-        builder = (Scripts::build(&self.scripts))(builder, &err)?;
+        // link binary packages to the source rpm they are built from
+        if self.srpm.is_some() {
+            builder = builder.source_rpm(self.srpm_file_name());
+        }
 
         // signing occurs last
         let finalizer = Sign::build(&self.signature);
-        finalizer(builder, &err).map_err(|e| e.note("sucess", false))
+        finalizer(builder, err).map_err(|e| e.note("sucess", false))
+    }
+
+    /// constructs a lambda which, in reproducible mode, clamps the build time
+    /// and every file mtime to `SOURCE_DATE_EPOCH` and fixes the build host.
+    ///
+    /// When `SOURCE_DATE_EPOCH` is unset the newest changelog entry is used.
+    pub fn reproducible<'a>(
+        &'a self,
+    ) -> impl FnOnce(PackageBuilder, &Err) -> Result<PackageBuilder, Err> + 'a {
+        move |builder: PackageBuilder, err: &Err| -> Result<PackageBuilder, Err> {
+            match self.source_date_epoch(err)? {
+                Option::None => Ok(builder),
+                Option::Some(epoch) => Ok(builder.source_date(epoch).build_host("localhost")),
+            }
+        }
+    }
+
+    /// the time every timestamp is clamped to, `None` outside of
+    /// reproducible mode.
+    pub fn source_date_epoch(&self, err: &Err) -> Result<Option<u32>, Err> {
+        use std::env::var;
+
+        if self.rpm.reproducible != Option::Some(true) {
+            return Ok(Option::None);
+        }
+        let epoch = match var("SOURCE_DATE_EPOCH") {
+            Ok(value) => value.trim().parse::<u32>().map_err(|e| {
+                err.clone()
+                    .note("invalid SOURCE_DATE_EPOCH", &value)
+                    .note("error", e)
+            })?,
+            Err(_) => match self.changelog.keys().next_back() {
+                Option::Some(when) => timestamp(when, err)?,
+                Option::None => {
+                    return Err(err.clone().note(
+                        "reproducible build",
+                        "requires SOURCE_DATE_EPOCH or a changelog entry",
+                    ))
+                }
+            },
+        };
+        Ok(Option::Some(epoch))
+    }
+
+    /// builds the source rpm, `config_path` is where this config was read from
+    pub fn build_srpm(&self, config_path: &str) -> Result<StreamPackage<'_>, Err> {
+        Srpm::build(self, config_path)
+    }
+
+    /// the top level tables describe the main package
+    pub fn main_package(&self) -> SubPackage {
+        SubPackage {
+            desc: Option::None,
+            description: self.rpm.description.clone(),
+            rich_requires: self.rich_requires.clone(),
+            rich_conflicts: self.rich_conflicts.clone(),
+            contents: self.contents.clone(),
+            requires: self.requires.clone(),
+            requires_pre: self.requires_pre.clone(),
+            requires_post: self.requires_post.clone(),
+            requires_preun: self.requires_preun.clone(),
+            requires_postun: self.requires_postun.clone(),
+            obsoletes: self.obsoletes.clone(),
+            conflicts: self.conflicts.clone(),
+            provides: self.provides.clone(),
+            recommends: self.recommends.clone(),
+            suggests: self.suggests.clone(),
+            supplements: self.supplements.clone(),
+            enhances: self.enhances.clone(),
+            scripts: self.scripts.clone(),
+        }
+    }
+
+    /// the inverse of `main_package`, replaces the top level tables
+    pub fn set_main_package(&mut self, pkg: SubPackage) {
+        self.rpm.description = pkg.description;
+        self.rich_requires = pkg.rich_requires;
+        self.rich_conflicts = pkg.rich_conflicts;
+        self.contents = pkg.contents;
+        self.requires = pkg.requires;
+        self.requires_pre = pkg.requires_pre;
+        self.requires_post = pkg.requires_post;
+        self.requires_preun = pkg.requires_preun;
+        self.requires_postun = pkg.requires_postun;
+        self.obsoletes = pkg.obsoletes;
+        self.conflicts = pkg.conflicts;
+        self.provides = pkg.provides;
+        self.recommends = pkg.recommends;
+        self.suggests = pkg.suggests;
+        self.supplements = pkg.supplements;
+        self.enhances = pkg.enhances;
+        self.scripts = pkg.scripts;
+    }
+
+    /// the `[epoch:]version[-release]` of the packages being built
+    pub fn evr(&self) -> Evr {
+        Evr {
+            epoch: self.rpm.epoch,
+            version: self.rpm.version.clone(),
+            release: self.rpm.release.map(|release| release.to_string()),
+        }
+    }
+
+    /// the conventional `name-version-release.arch.rpm` file name
+    pub fn file_name(&self, name: &str) -> String {
+        self.file_name_for(name, &self.rpm.arch)
+    }
+
+    /// the conventional `name-version-release.src.rpm` file name
+    pub fn srpm_file_name(&self) -> String {
+        self.file_name_for(&self.rpm.name, "src")
+    }
+
+    /// the conventional `name_version-release_arch.deb` file name, Debian
+    /// leaves the epoch out
+    pub fn deb_file_name(&self, name: &str) -> String {
+        let arch = deb_arch(&self.rpm.arch);
+        match self.rpm.release {
+            Option::None => format!("{}_{}_{}.deb", name, self.rpm.version, arch),
+            Option::Some(release) => {
+                format!("{}_{}-{}_{}.deb", name, self.rpm.version, release, arch)
+            }
+        }
+    }
+
+    fn file_name_for(&self, name: &str, arch: &str) -> String {
+        match self.rpm.release {
+            Option::None => format!("{}-{}.{}.rpm", name, self.rpm.version, arch),
+            Option::Some(release) => {
+                format!("{}-{}-{}.{}.rpm", name, self.rpm.version, release, arch)
+            }
+        }
     }
-}
 
-fn add_rpm_db_interaction<'a, I, F>(
-    iter: I,
-    lambda: F,
-) -> impl FnOnce(RPMBuilder) -> RPMBuilder + 'a
-where
-    I: Iterator<Item = (&'a String, &'a String)> + 'a,
-    F: Fn(RPMBuilder, Dependency) -> RPMBuilder + 'static,
-{
-    move |builder| -> RPMBuilder {
-        iter.map(|(name, version)| into_dependency(name, version))
-            .fold(builder, |builder, dep| lambda(builder, dep))
+    /// ensures the `obsoletes` and `conflicts` of the package `name` do not
+    /// match the version being built, as the package would obsolete (or
+    /// conflict with) itself.
+    fn check_self_references(&self, name: &str, pkg: &SubPackage, err: &Err) -> Result<(), Err> {
+        let own = self.evr();
+        let tables = [("obsoletes", &pkg.obsoletes), ("conflicts", &pkg.conflicts)];
+        for &(table, entries) in tables.iter() {
+            let constraints = match entries.get(name) {
+                Option::None => continue,
+                Option::Some(constraints) => constraints,
+            };
+            for version in constraints.each() {
+                let includes_self = match Constraint::parse(version) {
+                    Ok(Option::None) => true,
+                    Ok(Option::Some(constraint)) => satisfies(&own, &constraint),
+                    // reported when the dependency is built
+                    Err(_) => false,
+                };
+                if includes_self {
+                    return Err(err
+                        .clone()
+                        .note("table", table)
+                        .note("constraint", version)
+                        .note("error", "range includes the version of this package"));
+                }
+            }
+        }
+        Ok(())
     }
 }
@@ -0,0 +1,75 @@
+use std::io::{Result as IOResult, Write};
+
+const NEWC_MAGIC: &str = "070701";
+const STRIPPED_MAGIC: &str = "07070X";
+const TRAILER: &str = "TRAILER!!!";
+
+/// Entry is the header of a single file within a `newc` cpio archive, the
+/// format rpm uses for its payload.
+#[derive(Clone, Debug, Default)]
+pub struct Entry {
+    pub name: String,
+    pub ino: u32,
+    pub mode: u32,
+    pub uid: u32,
+    pub gid: u32,
+    pub nlink: u32,
+    pub mtime: u32,
+    pub size: u32,
+}
+impl Entry {
+    /// writes the header and name, the caller writes `size` bytes of data
+    /// followed by `padding(size)`.
+    pub fn write<W: Write>(&self, w: &mut W) -> IOResult<()> {
+        let fields = [
+            self.ino,
+            self.mode,
+            self.uid,
+            self.gid,
+            self.nlink,
+            self.mtime,
+            self.size,
+            0,
+            0,
+            0,
+            0,
+            self.name.len() as u32 + 1,
+            0,
+        ];
+        let mut header = String::with_capacity(110);
+        header.push_str(NEWC_MAGIC);
+        for field in fields.iter() {
+            header.push_str(&format!("{:08X}", field));
+        }
+        w.write_all(header.as_bytes())?;
+        w.write_all(self.name.as_bytes())?;
+        w.write_all(&[0])?;
+        w.write_all(padding(110 + self.name.len() as u64 + 1))
+    }
+}
+
+/// writes rpm's stripped header, used by packages with files of 4GiB or
+/// more. It records only the index of the file within the rpm header,
+/// which holds the rest of its metadata. As with `Entry::write` the
+/// caller writes the data followed by `padding(size)`.
+pub fn write_stripped<W: Write>(w: &mut W, index: u32) -> IOResult<()> {
+    let header = format!("{}{:08X}", STRIPPED_MAGIC, index);
+    w.write_all(header.as_bytes())?;
+    w.write_all(padding(header.len() as u64))
+}
+
+/// the zeros which align `len` bytes to the next 4 byte boundary
+pub fn padding(len: u64) -> &'static [u8] {
+    const ZEROS: [u8; 3] = [0; 3];
+    &ZEROS[..((4 - (len % 4)) % 4) as usize]
+}
+
+/// writes the entry which ends every archive
+pub fn write_trailer<W: Write>(w: &mut W) -> IOResult<()> {
+    let trailer = Entry {
+        name: TRAILER.to_string(),
+        nlink: 1,
+        ..Entry::default()
+    };
+    trailer.write(w)
+}
@@ -0,0 +1,901 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs::{metadata, read_to_string, File};
+use std::io::{copy, BufWriter, Error as IOError, Read, Result as IOResult, Seek, SeekFrom, Write};
+use std::os::unix::fs::MetadataExt;
+use std::path::{Path, PathBuf};
+
+use super::errors::Err;
+use super::flate2::write::GzEncoder;
+use super::flate2::Compression as GzLevel;
+use super::tempfile::tempfile;
+
+use super::ar::{self, Member};
+use super::core::ConfigFile;
+use super::fileopts::ComplexFileOptions;
+use super::rpm_meta::Algorithm;
+use super::stream::{now, Encoder};
+use super::subpackage::SubPackage;
+use super::tar::{self, Entry, Kind};
+use super::versions::{Constraint, Constraints, Operator, RichDependency};
+
+/// the format version recorded within `debian-binary`
+const FORMAT_VERSION: &[u8] = b"2.0\n";
+
+/// DebPackage builds a Debian binary package from the same config as an
+/// RPM, writing the `ar` container and its `tar` members directly.
+///
+/// Dependencies are translated from their rpm form, those Debian cannot
+/// express (file paths, sonames, most boolean dependencies) are dropped
+/// and reported as warnings.
+pub struct DebPackage<'a> {
+    config: &'a ConfigFile,
+    name: String,
+    desc: String,
+    pkg: SubPackage,
+    err: Err,
+}
+impl<'a> DebPackage<'a> {
+    pub fn new(
+        config: &'a ConfigFile,
+        name: &str,
+        desc: &str,
+        pkg: SubPackage,
+        err: Err,
+    ) -> DebPackage<'a> {
+        DebPackage {
+            config,
+            name: name.to_string(),
+            desc: desc.to_string(),
+            pkg,
+            err,
+        }
+    }
+
+    /// builds the package, writing it to `output`. Returns a warning for
+    /// everything which was not translated.
+    pub fn write<W: Write>(&self, output: &mut W) -> Result<Vec<String>, Err> {
+        let err = &self.err;
+        if !valid_name(&self.name) {
+            return Err(err.clone().note(
+                "error",
+                "debian package names are lowercase letters, digits, `+`, `-` and `.`",
+            ));
+        }
+        let epoch = self.config.source_date_epoch(err)?;
+        let mtime = epoch.unwrap_or_else(now) as u64;
+        let compression = self.config.rpm.compression(err)?;
+        let (algo, level) = (compression.algo, compression.level(err)?);
+
+        let mut warnings = Vec::new();
+        let sources = self.pkg.sources(err)?;
+        let (mut data, installed_size) = self.data(&sources, algo, level, epoch, mtime)?;
+        let control = self.control(&sources, installed_size, mtime, &mut warnings)?;
+
+        let write_err = |e: IOError| err.clone().note("failed to write deb", e);
+        let data_size = data.metadata().map_err(write_err)?.len();
+        ar::write_global_header(output).map_err(write_err)?;
+        let version_size = FORMAT_VERSION.len() as u64;
+        write_member(
+            output,
+            "debian-binary",
+            mtime,
+            version_size,
+            &mut &FORMAT_VERSION[..],
+        )
+        .map_err(write_err)?;
+        let control_size = control.len() as u64;
+        write_member(
+            output,
+            "control.tar.gz",
+            mtime,
+            control_size,
+            &mut &control[..],
+        )
+        .map_err(write_err)?;
+        write_member(output, data_member(algo), mtime, data_size, &mut data).map_err(write_err)?;
+        Ok(warnings)
+    }
+
+    /// writes the compressed `data` member to a temporary file, returning
+    /// it rewound along with the installed size in KiB.
+    fn data(
+        &self,
+        sources: &BTreeMap<String, (PathBuf, ComplexFileOptions)>,
+        algo: Algorithm,
+        level: i32,
+        epoch: Option<u32>,
+        mtime: u64,
+    ) -> Result<(File, u64), Err> {
+        let err = &self.err;
+        let write_err = |e: IOError| err.clone().note("failed to write data", e);
+        let file = BufWriter::new(tempfile().map_err(write_err)?);
+        let mut archive = Encoder::new(algo, level, 1, file).map_err(write_err)?;
+
+        // the changelog is generated unless the config packages its own
+        let changelog_path = format!("/usr/share/doc/{}/changelog.Debian.gz", self.name);
+        let changelog = if sources.contains_key(&changelog_path) {
+            Option::None
+        } else {
+            self.changelog()?
+        };
+
+        // every parent directory is listed before the files within it
+        let mut dirs = BTreeSet::new();
+        let paths = sources
+            .keys()
+            .chain(changelog.iter().map(|_| &changelog_path));
+        for path in paths {
+            let mut parent = Path::new(path).parent();
+            while let Option::Some(dir) = parent {
+                dirs.insert(dir.to_string_lossy().into_owned());
+                parent = dir.parent();
+            }
+        }
+        let mut installed_size = dirs.len() as u64;
+        for dir in dirs.iter() {
+            let entry = Entry {
+                name: format!(".{}/", dir.trim_end_matches('/')),
+                mode: 0o755,
+                mtime,
+                kind: Kind::Dir,
+                ..root_entry()
+            };
+            entry.write(&mut archive).map_err(write_err)?;
+        }
+
+        if let Option::Some(ref changelog) = changelog {
+            let entry = Entry {
+                name: format!(".{}", changelog_path),
+                mode: 0o644,
+                size: changelog.len() as u64,
+                mtime,
+                ..root_entry()
+            };
+            entry.write(&mut archive).map_err(write_err)?;
+            archive.write_all(changelog).map_err(write_err)?;
+            archive
+                .write_all(tar::padding(entry.size))
+                .map_err(write_err)?;
+            installed_size += kib(entry.size);
+        }
+
+        for (dst, (src, opts)) in sources.iter() {
+            let err = err.clone().note("src", src.display()).note("dst", dst);
+            let write_err = |e: IOError| err.clone().note("failed to write data", e);
+            let mut entry = Entry {
+                name: format!(".{}", dst),
+                uname: opts.user.clone().unwrap_or_else(|| "root".to_string()),
+                gname: opts.group.clone().unwrap_or_else(|| "root".to_string()),
+                mtime,
+                ..Entry::default()
+            };
+
+            if let Option::Some(ref target) = opts.symlink {
+                entry.mode = 0o777;
+                entry.kind = Kind::Symlink;
+                entry.linkname = target.clone();
+                entry.write(&mut archive).map_err(write_err)?;
+                installed_size += 1;
+                continue;
+            }
+
+            let meta = metadata(src).map_err(|e| err.clone().note("failed to stat file", e))?;
+            entry.mtime = match epoch {
+                Option::Some(epoch) if meta.mtime() > epoch as i64 => epoch as u64,
+                _ => meta.mtime().max(0) as u64,
+            };
+            entry.mode = match opts.mode {
+                Option::Some(mode) => mode as u32 & 0o7777,
+                Option::None => meta.mode() & 0o7777,
+            };
+            entry.size = meta.len();
+            entry.write(&mut archive).map_err(write_err)?;
+            let mut file =
+                File::open(src).map_err(|e| err.clone().note("failed to read file", e))?;
+            let copied = copy(&mut file, &mut archive).map_err(write_err)?;
+            if copied != entry.size {
+                return Err(err
+                    .clone()
+                    .note("expected size", entry.size)
+                    .note("read size", copied)
+                    .note("error", "file changed while it was being packaged"));
+            }
+            archive.write_all(tar::padding(copied)).map_err(write_err)?;
+            installed_size += kib(copied);
+        }
+
+        tar::write_trailer(&mut archive).map_err(write_err)?;
+        let mut file = archive
+            .finish()
+            .map_err(write_err)?
+            .into_inner()
+            .map_err(|e| write_err(e.into()))?;
+        file.seek(SeekFrom::Start(0)).map_err(write_err)?;
+        Ok((file, installed_size))
+    }
+
+    /// the gzipped `control` member: the control file, conffiles and the
+    /// maintainer scripts
+    fn control(
+        &self,
+        sources: &BTreeMap<String, (PathBuf, ComplexFileOptions)>,
+        installed_size: u64,
+        mtime: u64,
+        warnings: &mut Vec<String>,
+    ) -> Result<Vec<u8>, Err> {
+        let err = &self.err;
+        let mut files: Vec<(&str, u32, Vec<u8>)> = vec![(
+            "control",
+            0o644,
+            self.control_file(installed_size, warnings)?.into_bytes(),
+        )];
+
+        // conffiles must be regular files
+        let conffiles: String = sources
+            .iter()
+            .filter(|(_, (_, opts))| opts.config == Option::Some(true) && opts.symlink.is_none())
+            .map(|(dst, _)| format!("{}\n", dst))
+            .collect();
+        if !conffiles.is_empty() {
+            files.push(("conffiles", 0o644, conffiles.into_bytes()));
+        }
+
+        if let Option::Some(ref scripts) = self.pkg.scripts {
+            let slots = [
+                ("preinst", &scripts.pre_install),
+                ("postinst", &scripts.post_install),
+                ("prerm", &scripts.pre_uninstall),
+                ("postrm", &scripts.post_uninstall),
+            ];
+            for &(slot, path) in slots.iter() {
+                let path = match path {
+                    &Option::None => continue,
+                    &Option::Some(ref path) => path,
+                };
+                let mut script = read_to_string(path).map_err(|e| {
+                    err.clone()
+                        .note("failed to load script", e)
+                        .note("failed on script", slot)
+                        .note("error on path", path)
+                })?;
+                // dpkg executes scripts directly, rpm passes them to sh
+                if !script.starts_with("#!") {
+                    script = format!("#!/bin/sh\n{}", script);
+                }
+                let split = script.find('\n').unwrap_or(script.len());
+                let (interpreter, body) = script.split_at(split);
+                let shell = interpreter
+                    .trim_start_matches("#!")
+                    .split_whitespace()
+                    .next()
+                    .map(|path| path.ends_with("sh"))
+                    .unwrap_or(false);
+                if shell {
+                    script = format!("{}\n{}{}", interpreter, shim(slot), body);
+                } else {
+                    warnings.push(format!(
+                        "{} is not a shell script, it receives dpkg's arguments rather than rpm's instance count",
+                        slot
+                    ));
+                }
+                files.push((slot, 0o755, script.into_bytes()));
+            }
+        }
+
+        let write_err = |e: IOError| err.clone().note("failed to write control", e);
+        let mut archive = GzEncoder::new(Vec::new(), GzLevel::best());
+        let dir = Entry {
+            name: "./".to_string(),
+            mode: 0o755,
+            mtime,
+            kind: Kind::Dir,
+            ..root_entry()
+        };
+        dir.write(&mut archive).map_err(write_err)?;
+        for (name, mode, data) in files.iter() {
+            let entry = Entry {
+                name: format!("./{}", name),
+                mode: *mode,
+                size: data.len() as u64,
+                mtime,
+                ..root_entry()
+            };
+            entry.write(&mut archive).map_err(write_err)?;
+            archive.write_all(data).map_err(write_err)?;
+            archive
+                .write_all(tar::padding(entry.size))
+                .map_err(write_err)?;
+        }
+        tar::write_trailer(&mut archive).map_err(write_err)?;
+        archive.finish().map_err(write_err)
+    }
+
+    /// the `control` file, describing the package to dpkg
+    fn control_file(&self, installed_size: u64, warnings: &mut Vec<String>) -> Result<String, Err> {
+        let err = &self.err;
+        let pkg = &self.pkg;
+        let mut fields = vec![
+            ("Package", self.name.clone()),
+            ("Version", self.config.evr().to_string()),
+            ("Architecture", deb_arch(&self.config.rpm.arch).to_string()),
+        ];
+        let maintainer = match self.config.rpm.maintainer {
+            Option::Some(ref maintainer) => maintainer.trim(),
+            Option::None => match self.config.changelog.iter().next_back() {
+                Option::Some((_, entry)) => split_author(&entry.author).0,
+                Option::None => "",
+            },
+        };
+        if maintainer.is_empty() {
+            return Err(err.clone().note(
+                "error",
+                "debs require a Maintainer, set `maintainer` within [rpm] or add a changelog entry",
+            ));
+        }
+        fields.push(("Maintainer", maintainer.to_string()));
+        fields.push(("Installed-Size", installed_size.to_string()));
+
+        // rpm's ordering requirements for the scripts which run before the
+        // install are the closest match to Pre-Depends
+        let mut relations = Relations { warnings, err };
+        let pre_depends = relations.table("Pre-Depends", &pkg.requires_pre)?;
+        let mut depends = Vec::new();
+        for table in [
+            &pkg.requires,
+            &pkg.requires_post,
+            &pkg.requires_preun,
+            &pkg.requires_postun,
+        ]
+        .iter()
+        {
+            for relation in relations.table("Depends", table)? {
+                if !depends.contains(&relation) && !pre_depends.contains(&relation) {
+                    depends.push(relation);
+                }
+            }
+        }
+        depends.extend(relations.rich("Depends", &pkg.rich_requires, false)?);
+        let recommends = relations.table("Recommends", &pkg.recommends)?;
+        let suggests = relations.table("Suggests", &pkg.suggests)?;
+        let enhances = relations.table("Enhances", &pkg.enhances)?;
+
+        // a package rpm obsoletes is removed, Debian spells that as
+        // conflicting with, and replacing, it
+        let replaces = relations.table("Replaces", &pkg.obsoletes)?;
+        let mut conflicts = relations.table("Conflicts", &pkg.conflicts)?;
+        conflicts.extend(relations.rich("Conflicts", &pkg.rich_conflicts, true)?);
+        for relation in replaces.iter() {
+            if !conflicts.contains(relation) {
+                conflicts.push(relation.clone());
+            }
+        }
+
+        // only exact versions may be provided
+        let mut provides = Vec::new();
+        for relation in relations.table("Provides", &pkg.provides)? {
+            if relation.contains(" (") && !relation.contains(" (= ") {
+                relations.warnings.push(format!(
+                    "Provides: {} is not an exact version, it was dropped",
+                    relation
+                ));
+            } else {
+                provides.push(relation);
+            }
+        }
+        if self.config.rpm.auto_deps == Option::Some(true) {
+            relations.warnings.push(
+                "shared library dependencies are not derived for debs, auto_deps was ignored"
+                    .to_string(),
+            );
+        }
+        if !pkg.supplements.is_empty() {
+            relations
+                .warnings
+                .push("debian has no equivalent of supplements, they were dropped".to_string());
+        }
+
+        let lists = [
+            ("Pre-Depends", pre_depends),
+            ("Depends", depends),
+            ("Recommends", recommends),
+            ("Suggests", suggests),
+            ("Enhances", enhances),
+            ("Conflicts", conflicts),
+            ("Replaces", replaces),
+            ("Provides", provides),
+        ];
+        for (field, list) in lists.iter() {
+            if !list.is_empty() {
+                fields.push((*field, list.join(", ")));
+            }
+        }
+        // the synopsis is the first line of `desc`
+        let text = match self.pkg.description {
+            Option::None => self.desc.clone(),
+            Option::Some(ref extended) => {
+                let synopsis = self.desc.trim().lines().next().unwrap_or_default();
+                format!("{}\n{}", synopsis, extended)
+            }
+        };
+        fields.push(("Description", description(&text)));
+
+        Ok(fields
+            .iter()
+            .map(|(field, value)| format!("{}: {}\n", field, value))
+            .collect())
+    }
+
+    /// the changelog in Debian's format, gzipped, or `None` when the config
+    /// has no changelog
+    fn changelog(&self) -> Result<Option<Vec<u8>>, Err> {
+        if self.config.changelog.is_empty() {
+            return Ok(Option::None);
+        }
+        let mut text = String::new();
+        for (when, entry) in self.config.changelog.iter().rev() {
+            let (author, version) = split_author(&entry.author);
+            let version = match version {
+                Option::Some(version) => version.to_string(),
+                Option::None => self.config.evr().to_string(),
+            };
+            text.push_str(&format!(
+                "{} ({}) unstable; urgency=medium\n\n",
+                self.config.rpm.name, version
+            ));
+            let mut bullet = false;
+            for line in entry.entry.lines().map(|line| line.trim()) {
+                if line.is_empty() {
+                    continue;
+                }
+                if line.starts_with("- ") || line.starts_with("* ") {
+                    text.push_str(&format!("  * {}\n", line[2..].trim_start()));
+                } else if bullet {
+                    text.push_str(&format!("    {}\n", line));
+                } else {
+                    text.push_str(&format!("  * {}\n", line));
+                }
+                bullet = true;
+            }
+            text.push_str(&format!(
+                "\n -- {}  {}\n\n",
+                author,
+                when.format("%a, %d %b %Y %H:%M:%S +0000")
+            ));
+        }
+
+        let write_err = |e: IOError| self.err.clone().note("failed to write changelog", e);
+        let mut gz = GzEncoder::new(Vec::new(), GzLevel::best());
+        gz.write_all(text.as_bytes()).map_err(write_err)?;
+        gz.finish().map(Option::Some).map_err(write_err)
+    }
+}
+
+/// Relations translates dependency tables into Debian's relation fields
+struct Relations<'a, 'b> {
+    warnings: &'a mut Vec<String>,
+    err: &'b Err,
+}
+impl<'a, 'b> Relations<'a, 'b> {
+    /// every constraint is its own relation, as Debian ands relations
+    /// within a field
+    fn table(
+        &mut self,
+        field: &str,
+        table: &BTreeMap<String, Constraints>,
+    ) -> Result<Vec<String>, Err> {
+        let mut output = Vec::new();
+        for (name, constraints) in table.iter() {
+            if !valid_name(name) {
+                self.warnings.push(format!(
+                    "{}: {} is not a debian package name, it was dropped",
+                    field, name
+                ));
+                continue;
+            }
+            for version in constraints.each() {
+                let constraint = Constraint::parse(version).map_err(|e| {
+                    self.err
+                        .clone()
+                        .note("field", field)
+                        .note("name", name)
+                        .note("invalid constraint", e)
+                })?;
+                let relation = relation(name, constraint.as_ref());
+                if !output.contains(&relation) {
+                    output.push(relation);
+                }
+            }
+        }
+        Ok(output)
+    }
+
+    /// boolean dependencies which are ands of ors, or for conflicts ors,
+    /// of package names
+    fn rich(&mut self, field: &str, exprs: &[String], conflicts: bool) -> Result<Vec<String>, Err> {
+        let mut output = Vec::new();
+        for expr in exprs.iter() {
+            let rich = RichDependency::parse(expr).map_err(|e| {
+                self.err
+                    .clone()
+                    .note("field", field)
+                    .note("invalid rich dependency", e)
+            })?;
+            // a conflict with any alternative is a conflict with each
+            let translated = if conflicts {
+                alternatives(&rich).map(|list| list.into_iter().map(|atom| vec![atom]).collect())
+            } else {
+                conjunction(&rich)
+            };
+            match translated {
+                Option::Some(relations) => {
+                    for alternatives in relations {
+                        output.push(alternatives.join(" | "));
+                    }
+                }
+                Option::None => self.warnings.push(format!(
+                    "{}: {} has no debian equivalent, it was dropped",
+                    field, expr
+                )),
+            };
+        }
+        Ok(output)
+    }
+}
+
+/// the relations an `and` of `or`s translates into
+fn conjunction(rich: &RichDependency) -> Option<Vec<Vec<String>>> {
+    match rich {
+        &RichDependency::And(ref terms) => {
+            let mut output = Vec::new();
+            for term in terms.iter() {
+                output.extend(conjunction(term)?);
+            }
+            Option::Some(output)
+        }
+        _ => Option::Some(vec![alternatives(rich)?]),
+    }
+}
+
+/// the alternatives an `or` of package names translates into
+fn alternatives(rich: &RichDependency) -> Option<Vec<String>> {
+    match rich {
+        &RichDependency::Atom(ref name, ref constraint) if valid_name(name) => {
+            Option::Some(vec![relation(name, constraint.as_ref())])
+        }
+        &RichDependency::Or(ref terms) => {
+            let mut output = Vec::new();
+            for term in terms.iter() {
+                output.extend(alternatives(term)?);
+            }
+            Option::Some(output)
+        }
+        _ => Option::None,
+    }
+}
+
+fn relation(name: &str, constraint: Option<&Constraint>) -> String {
+    match constraint {
+        Option::None => name.to_string(),
+        Option::Some(constraint) => {
+            let op = match constraint.op {
+                Operator::Eq => "=",
+                Operator::Less => "<<",
+                Operator::LessEq => "<=",
+                Operator::Greater => ">>",
+                Operator::GreaterEq => ">=",
+            };
+            format!("{} ({} {})", name, op, constraint.evr)
+        }
+    }
+}
+
+/// whether `name` is a valid Debian package name, rpm also depends upon
+/// paths, sonames and other capabilities which are not
+fn valid_name(name: &str) -> bool {
+    name.len() >= 2
+        && name.starts_with(|c: char| c.is_ascii_lowercase() || c.is_ascii_digit())
+        && name
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || "+-.".contains(c))
+}
+
+/// the Debian architecture an rpm architecture is called
+pub fn deb_arch(arch: &str) -> &str {
+    match arch {
+        "noarch" => "all",
+        "x86_64" => "amd64",
+        "aarch64" => "arm64",
+        "i386" | "i486" | "i586" | "i686" => "i386",
+        "armv7hl" | "armv7l" => "armhf",
+        "ppc64le" => "ppc64el",
+        "riscv64" => "riscv64",
+        "s390x" => "s390x",
+        other => other,
+    }
+}
+
+/// the name of the `data` member, which names its compressor
+fn data_member(algo: Algorithm) -> &'static str {
+    match algo {
+        Algorithm::None => "data.tar",
+        Algorithm::Gzip => "data.tar.gz",
+        Algorithm::Xz => "data.tar.xz",
+        Algorithm::Zstd => "data.tar.zst",
+    }
+}
+
+/// a synopsis line followed by the extended description, blank lines
+/// become ` .`
+fn description(desc: &str) -> String {
+    let mut lines = desc.trim().lines();
+    let mut output = lines.next().unwrap_or_default().trim().to_string();
+    for line in lines {
+        if line.trim().is_empty() {
+            output.push_str("\n .");
+        } else {
+            output.push_str(&format!("\n {}", line));
+        }
+    }
+    output
+}
+
+/// splits rpm's `Name <email> - version` changelog author convention into
+/// the author and version
+fn split_author(author: &str) -> (&str, Option<&str>) {
+    let end = match author.rfind('>') {
+        Option::None => return (author.trim(), Option::None),
+        Option::Some(end) => end + 1,
+    };
+    let version = author[end..].trim().trim_start_matches('-').trim();
+    if version.is_empty() {
+        (author[..end].trim(), Option::None)
+    } else {
+        (author[..end].trim(), Option::Some(version))
+    }
+}
+
+/// the prelude of a maintainer script, replacing the action dpkg passes
+/// with the instance count rpm would. Actions rpm has no scriptlet for,
+/// such as unwinding a failed upgrade, exit without running it.
+fn shim(slot: &str) -> &'static str {
+    match slot {
+        "preinst" => {
+            "case \"$1\" in\n  install) set -- 1 ;;\n  upgrade) set -- 2 ;;\n  *) exit 0 ;;\nesac\n"
+        }
+        "postinst" => {
+            "case \"$1\" in\n  configure) if [ -z \"$2\" ]; then set -- 1; else set -- 2; fi ;;\n  *) exit 0 ;;\nesac\n"
+        }
+        // prerm and postrm
+        _ => "case \"$1\" in\n  remove) set -- 0 ;;\n  upgrade) set -- 1 ;;\n  *) exit 0 ;;\nesac\n",
+    }
+}
+
+/// writes a member of the `ar` container holding `size` bytes of `data`
+fn write_member<W: Write, R: Read>(
+    w: &mut W,
+    name: &str,
+    mtime: u64,
+    size: u64,
+    data: &mut R,
+) -> IOResult<()> {
+    let member = Member {
+        name: name.to_string(),
+        mtime,
+        mode: 0o100644,
+        size,
+    };
+    member.write(w)?;
+    copy(data, w)?;
+    w.write_all(ar::padding(size))
+}
+
+fn kib(size: u64) -> u64 {
+    (size + 1023) / 1024
+}
+
+fn root_entry() -> Entry {
+    Entry {
+        uname: "root".to_string(),
+        gname: "root".to_string(),
+        ..Entry::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::flate2::read::GzDecoder;
+    use super::super::toml::de::from_str;
+    use super::*;
+    use std::fs::write;
+
+    /// the members of an `ar` archive, by name
+    fn members(archive: &[u8]) -> Vec<(String, Vec<u8>)> {
+        assert_eq!(&archive[..8], b"!<arch>\n");
+        let mut output = Vec::new();
+        let mut rest = &archive[8..];
+        while !rest.is_empty() {
+            let header = std::str::from_utf8(&rest[..60]).unwrap();
+            assert_eq!(&header[58..], "`\n");
+            let size: usize = header[48..58].trim().parse().unwrap();
+            output.push((
+                header[..16].trim().to_string(),
+                rest[60..60 + size].to_vec(),
+            ));
+            rest = &rest[60 + size + size % 2..];
+        }
+        output
+    }
+
+    /// the entries of a `tar` archive, with their type flag and data,
+    /// checking every checksum along the way
+    fn entries(archive: &[u8]) -> Vec<(String, u8, Vec<u8>)> {
+        let text = |field: &[u8]| {
+            let end = field.iter().position(|b| *b == 0).unwrap_or(field.len());
+            String::from_utf8(field[..end].to_vec()).unwrap()
+        };
+        let mut output = Vec::new();
+        let mut rest = archive;
+        while rest[..512].iter().any(|b| *b != 0) {
+            let header = &rest[..512];
+            let sum: u64 = header
+                .iter()
+                .enumerate()
+                .map(|(idx, b)| {
+                    if (148..156).contains(&idx) {
+                        32
+                    } else {
+                        *b as u64
+                    }
+                })
+                .sum();
+            assert_eq!(u64::from_str_radix(&text(&header[148..154]), 8), Ok(sum));
+            let size = usize::from_str_radix(&text(&header[124..135]), 8).unwrap();
+            let name = match text(&header[345..500]) {
+                ref prefix if prefix.is_empty() => text(&header[..100]),
+                prefix => format!("{}/{}", prefix, text(&header[..100])),
+            };
+            output.push((name, header[156], rest[512..512 + size].to_vec()));
+            rest = &rest[512 + size + tar::padding(size as u64).len()..];
+        }
+        assert_eq!(rest, &[0u8; 1024][..]);
+        output
+    }
+
+    fn gunzip(data: &[u8]) -> Vec<u8> {
+        let mut output = Vec::new();
+        GzDecoder::new(data).read_to_end(&mut output).unwrap();
+        output
+    }
+
+    #[test]
+    fn debs_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        write(root.join("demo.conf"), b"answer = 42\n").unwrap();
+        write(root.join("post.sh"), b"echo installed $1\n").unwrap();
+        let config: ConfigFile = from_str(&format!(
+            "[rpm]\n\
+             name = 'demo'\n\
+             version = '1.0'\n\
+             release = 2\n\
+             license = 'MIT'\n\
+             arch = 'x86_64'\n\
+             desc = 'a demo'\n\
+             description = \"first paragraph\\n\\nsecond paragraph\"\n\
+             \n\
+             [contents]\n\
+             '{root}/demo.conf' = {{ dst = '/etc/demo.conf', config = true, mode = 0o640 }}\n\
+             'link' = {{ dst = '/usr/bin/demo', symlink = '/etc/demo.conf' }}\n\
+             \n\
+             [requires]\n\
+             'bash' = '>= 4.0'\n\
+             \n\
+             [scripts]\n\
+             post_install = '{root}/post.sh'\n\
+             \n\
+             [changelog]\n\
+             '2020-01-02T03:04:05' = {{ author = 'Dev <dev@example.com> - 1.0-1', entry = '- first' }}\n",
+            root = root.display()
+        ))
+        .unwrap();
+
+        let mut deb = Vec::new();
+        let (_, pkg) = config.deb_all().unwrap().remove(0);
+        let warnings = pkg.write(&mut deb).unwrap();
+        assert!(warnings.is_empty(), "{:?}", warnings);
+
+        let members = members(&deb);
+        let names: Vec<&str> = members.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(names, vec!["debian-binary", "control.tar.gz", "data.tar"]);
+        assert_eq!(members[0].1, b"2.0\n");
+
+        let control = entries(&gunzip(&members[1].1));
+        let names: Vec<&str> = control.iter().map(|(name, _, _)| name.as_str()).collect();
+        assert_eq!(names, vec!["./", "./control", "./conffiles", "./postinst"]);
+        assert_eq!(
+            String::from_utf8(control[1].2.clone()).unwrap(),
+            "Package: demo\n\
+             Version: 1.0-2\n\
+             Architecture: amd64\n\
+             Maintainer: Dev <dev@example.com>\n\
+             Installed-Size: 10\n\
+             Depends: bash (>= 4.0)\n\
+             Description: a demo\n \
+             first paragraph\n \
+             .\n \
+             second paragraph\n"
+        );
+        assert_eq!(control[2].2, b"/etc/demo.conf\n");
+        let postinst = String::from_utf8(control[3].2.clone()).unwrap();
+        assert!(postinst.starts_with("#!/bin/sh\ncase \"$1\" in\n"));
+        assert!(postinst.ends_with("esac\n\necho installed $1\n"));
+
+        let data = entries(&members[2].1);
+        let names: Vec<(&str, u8)> = data
+            .iter()
+            .map(|(name, kind, _)| (name.as_str(), *kind))
+            .collect();
+        assert_eq!(
+            names,
+            vec![
+                ("./", b'5'),
+                ("./etc/", b'5'),
+                ("./usr/", b'5'),
+                ("./usr/bin/", b'5'),
+                ("./usr/share/", b'5'),
+                ("./usr/share/doc/", b'5'),
+                ("./usr/share/doc/demo/", b'5'),
+                ("./usr/share/doc/demo/changelog.Debian.gz", b'0'),
+                ("./etc/demo.conf", b'0'),
+                ("./usr/bin/demo", b'2'),
+            ]
+        );
+        assert_eq!(
+            String::from_utf8(gunzip(&data[7].2)).unwrap(),
+            "demo (1.0-1) unstable; urgency=medium\n\n  \
+             * first\n\n \
+             -- Dev <dev@example.com>  Thu, 02 Jan 2020 03:04:05 +0000\n\n"
+        );
+        assert_eq!(data[8].2, b"answer = 42\n");
+    }
+
+    #[test]
+    fn relations_use_debian_operators() {
+        let relation = |version: &str| {
+            let constraint = Constraint::parse(version).unwrap();
+            super::relation("demo", constraint.as_ref())
+        };
+        assert_eq!(relation(""), "demo");
+        assert_eq!(relation("= 1.0-1"), "demo (= 1.0-1)");
+        assert_eq!(relation("< 2"), "demo (<< 2)");
+        assert_eq!(relation("<= 2"), "demo (<= 2)");
+        assert_eq!(relation("> 1:2"), "demo (>> 1:2)");
+        assert_eq!(relation(">= 2"), "demo (>= 2)");
+    }
+
+    #[test]
+    fn authors_split_from_versions() {
+        assert_eq!(
+            split_author("Dev <dev@example.com> - 1.0-1"),
+            ("Dev <dev@example.com>", Option::Some("1.0-1"))
+        );
+        assert_eq!(
+            split_author("Dev <dev@example.com> 2:1.0"),
+            ("Dev <dev@example.com>", Option::Some("2:1.0"))
+        );
+        assert_eq!(
+            split_author(" Dev <dev@example.com> "),
+            ("Dev <dev@example.com>", Option::None)
+        );
+        assert_eq!(split_author("Dev - 1.0"), ("Dev - 1.0", Option::None));
+    }
+
+    #[test]
+    fn descriptions_mark_blank_lines() {
+        assert_eq!(description("synopsis"), "synopsis");
+        assert_eq!(
+            description("  synopsis  \nfirst\n\n  indented\n   \nlast\n"),
+            "synopsis\n first\n .\n   indented\n .\n last"
+        );
+    }
+}
@@ -0,0 +1,371 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::fs::read_to_string;
+
+use super::errors::Err;
+use super::toml::de::from_str;
+
+use super::core::ConfigFile;
+use super::inspect::{date, mode_string, Change, Dep, File, Inspection, Script};
+
+/// Difference is a single entry which differs between the old and the new
+/// package
+#[derive(Clone, Debug)]
+pub enum Difference<T> {
+    Added(T),
+    Removed(T),
+    Changed(T, T),
+}
+
+/// Diff is everything which differs between two packages. Build times,
+/// build hosts and file mtimes always differ, so they are not compared.
+#[derive(Clone, Debug, Default)]
+pub struct Diff {
+    pub metadata: Vec<(&'static str, String, String)>,
+    pub files: Vec<Difference<File>>,
+    pub dependencies: Vec<(&'static str, Difference<Dep>)>,
+    pub scripts: Vec<Difference<Script>>,
+    pub changelog: Vec<Difference<Change>>,
+}
+impl Diff {
+    pub fn new(old: &Inspection, new: &Inspection) -> Diff {
+        Diff {
+            metadata: metadata(old, new),
+            files: files(old, new),
+            dependencies: dependencies(old, new),
+            scripts: scripts(old, new),
+            changelog: changelog(old, new),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.metadata.is_empty()
+            && self.files.is_empty()
+            && self.dependencies.is_empty()
+            && self.scripts.is_empty()
+            && self.changelog.is_empty()
+    }
+}
+
+/// inspects the old and new packages, either of which may be an rpm or a
+/// config. A config is built in memory, as the package sharing the name
+/// of the other side when it declares one.
+pub fn load(old: &str, new: &str) -> Result<(Inspection, Inspection), Err> {
+    match (is_config(old), is_config(new)) {
+        (true, false) => {
+            let new = Inspection::open(new)?;
+            Ok((load_config(old, Option::Some(&new.name))?, new))
+        }
+        (false, true) => {
+            let old = Inspection::open(old)?;
+            let new = load_config(new, Option::Some(&old.name))?;
+            Ok((old, new))
+        }
+        (true, true) => Ok((
+            load_config(old, Option::None)?,
+            load_config(new, Option::None)?,
+        )),
+        (false, false) => Ok((Inspection::open(old)?, Inspection::open(new)?)),
+    }
+}
+
+fn is_config(path: &str) -> bool {
+    path.ends_with(".toml")
+}
+
+fn load_config(path: &str, name: Option<&str>) -> Result<Inspection, Err> {
+    let err = Err::default().note("config", path);
+    let data = read_to_string(path).map_err(|e| err.clone().note("failed to read config", e))?;
+    let config = from_str::<ConfigFile>(&data).map_err(|e| {
+        err.clone()
+            .note("failed to parse config", format_args!("{}", e))
+    })?;
+
+    // skipped elf files are reported by `pkg`, they are not a difference
+    let mut warnings = Vec::new();
+    let mut packages = Vec::new();
+    for (_, pkg) in config.build_all(&mut warnings)? {
+        packages.push(Inspection::from_package(&pkg, &err)?);
+    }
+    let idx = packages
+        .iter()
+        .position(|pkg| Option::Some(pkg.name.as_str()) == name)
+        .unwrap_or(0);
+    Ok(packages.swap_remove(idx))
+}
+
+fn metadata(old: &Inspection, new: &Inspection) -> Vec<(&'static str, String, String)> {
+    let epoch = |i: &Inspection| i.epoch.map(|e| e.to_string()).unwrap_or_default();
+    let compressor = |i: &Inspection| i.payload_compressor.clone().unwrap_or_default();
+    let fields = vec![
+        ("name", old.name.clone(), new.name.clone()),
+        ("epoch", epoch(old), epoch(new)),
+        ("version", old.version.clone(), new.version.clone()),
+        ("release", old.release.clone(), new.release.clone()),
+        ("arch", old.arch.clone(), new.arch.clone()),
+        ("license", old.license.clone(), new.license.clone()),
+        ("summary", old.summary.clone(), new.summary.clone()),
+        (
+            "description",
+            old.description.clone(),
+            new.description.clone(),
+        ),
+        ("compression", compressor(old), compressor(new)),
+    ];
+    fields.into_iter().filter(|(_, a, b)| a != b).collect()
+}
+
+fn files(old: &Inspection, new: &Inspection) -> Vec<Difference<File>> {
+    let old_files: BTreeMap<&str, &File> = old.files.iter().map(|f| (f.path.as_str(), f)).collect();
+    let new_files: BTreeMap<&str, &File> = new.files.iter().map(|f| (f.path.as_str(), f)).collect();
+    let paths: BTreeSet<&str> = old_files
+        .keys()
+        .chain(new_files.keys())
+        .map(|p| *p)
+        .collect();
+
+    let mut output = Vec::new();
+    for path in paths {
+        match (old_files.get(path), new_files.get(path)) {
+            (Option::Some(a), Option::None) => output.push(Difference::Removed((*a).clone())),
+            (Option::None, Option::Some(b)) => output.push(Difference::Added((*b).clone())),
+            (Option::Some(a), Option::Some(b)) if !file_changes(a, b).is_empty() => {
+                output.push(Difference::Changed((*a).clone(), (*b).clone()))
+            }
+            _ => {}
+        };
+    }
+    output
+}
+
+/// describes every attribute, other than the mtime, which differs
+fn file_changes(old: &File, new: &File) -> Vec<String> {
+    let none = "-".to_string();
+    let mut output = Vec::new();
+    if old.mode != new.mode {
+        output.push(format!(
+            "mode: {} -> {}",
+            mode_string(old.mode),
+            mode_string(new.mode)
+        ));
+    }
+    if (&old.user, &old.group) != (&new.user, &new.group) {
+        output.push(format!(
+            "owner: {}:{} -> {}:{}",
+            old.user, old.group, new.user, new.group
+        ));
+    }
+    if old.digest != new.digest {
+        output.push(format!(
+            "digest: {} -> {}",
+            old.digest.as_ref().unwrap_or(&none),
+            new.digest.as_ref().unwrap_or(&none)
+        ));
+    }
+    if old.size != new.size {
+        output.push(format!("size: {} -> {}", old.size, new.size));
+    }
+    if old.linkto != new.linkto {
+        output.push(format!(
+            "link: {} -> {}",
+            old.linkto.as_ref().unwrap_or(&none),
+            new.linkto.as_ref().unwrap_or(&none)
+        ));
+    }
+    if old.config != new.config {
+        output.push(format!("config: {} -> {}", old.config, new.config));
+    }
+    if old.doc != new.doc {
+        output.push(format!("doc: {} -> {}", old.doc, new.doc));
+    }
+    output
+}
+
+/// a dependency removed and added under the same name is reported as
+/// changed, such as a bumped version
+fn dependencies(old: &Inspection, new: &Inspection) -> Vec<(&'static str, Difference<Dep>)> {
+    let mut output = Vec::new();
+    for ((table, old_deps), (_, new_deps)) in old
+        .dependency_tables()
+        .into_iter()
+        .zip(new.dependency_tables().into_iter())
+    {
+        let old_deps: BTreeSet<&Dep> = old_deps.iter().collect();
+        let new_deps: BTreeSet<&Dep> = new_deps.iter().collect();
+        let removed: Vec<&Dep> = old_deps.difference(&new_deps).map(|d| *d).collect();
+        let added: Vec<&Dep> = new_deps.difference(&old_deps).map(|d| *d).collect();
+        let count = |deps: &[&Dep], name: &str| deps.iter().filter(|d| d.name == name).count();
+
+        for dep in removed.iter() {
+            let replacement = added.iter().find(|d| d.name == dep.name);
+            match replacement {
+                Option::Some(b)
+                    if count(&removed, &dep.name) == 1 && count(&added, &dep.name) == 1 =>
+                {
+                    output.push((table, Difference::Changed((*dep).clone(), (*b).clone())))
+                }
+                _ => output.push((table, Difference::Removed((*dep).clone()))),
+            };
+        }
+        for dep in added.iter() {
+            if count(&removed, &dep.name) != 1 || count(&added, &dep.name) != 1 {
+                output.push((table, Difference::Added((*dep).clone())));
+            }
+        }
+    }
+    output
+}
+
+fn scripts(old: &Inspection, new: &Inspection) -> Vec<Difference<Script>> {
+    let mut output = Vec::new();
+    for a in old.scripts.iter() {
+        match new.scripts.iter().find(|b| b.name == a.name) {
+            Option::None => output.push(Difference::Removed(a.clone())),
+            Option::Some(b) if a != b => output.push(Difference::Changed(a.clone(), b.clone())),
+            _ => {}
+        };
+    }
+    for b in new.scripts.iter() {
+        if !old.scripts.iter().any(|a| a.name == b.name) {
+            output.push(Difference::Added(b.clone()));
+        }
+    }
+    output
+}
+
+fn changelog(old: &Inspection, new: &Inspection) -> Vec<Difference<Change>> {
+    let mut output: Vec<Difference<Change>> = new
+        .changelog
+        .iter()
+        .filter(|c| !old.changelog.contains(c))
+        .map(|c| Difference::Added(c.clone()))
+        .collect();
+    output.extend(
+        old.changelog
+            .iter()
+            .filter(|c| !new.changelog.contains(c))
+            .map(|c| Difference::Removed(c.clone())),
+    );
+    output
+}
+
+/// the lines of both texts, prefixed by `-` or `+` where they differ, as
+/// given by their longest common subsequence
+fn line_diff(old: &str, new: &str) -> Vec<String> {
+    let a: Vec<&str> = old.lines().collect();
+    let b: Vec<&str> = new.lines().collect();
+    let mut lengths = vec![vec![0usize; b.len() + 1]; a.len() + 1];
+    for i in (0..a.len()).rev() {
+        for j in (0..b.len()).rev() {
+            lengths[i][j] = if a[i] == b[j] {
+                lengths[i + 1][j + 1] + 1
+            } else {
+                lengths[i + 1][j].max(lengths[i][j + 1])
+            };
+        }
+    }
+
+    let mut output = Vec::with_capacity(a.len().max(b.len()));
+    let (mut i, mut j) = (0, 0);
+    while i < a.len() || j < b.len() {
+        if i < a.len() && j < b.len() && a[i] == b[j] {
+            output.push(format!("  {}", a[i]));
+            i += 1;
+            j += 1;
+        } else if j == b.len() || (i < a.len() && lengths[i + 1][j] >= lengths[i][j + 1]) {
+            output.push(format!("- {}", a[i]));
+            i += 1;
+        } else {
+            output.push(format!("+ {}", b[j]));
+            j += 1;
+        }
+    }
+    output
+}
+
+impl fmt::Display for Diff {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.is_empty() {
+            return writeln!(f, "no differences");
+        }
+
+        if !self.metadata.is_empty() {
+            writeln!(f, "metadata:")?;
+            for (field, a, b) in self.metadata.iter() {
+                if a.contains('\n') || b.contains('\n') {
+                    writeln!(f, "  ~ {}", field)?;
+                    for line in line_diff(a, b) {
+                        writeln!(f, "      {}", line)?;
+                    }
+                } else {
+                    writeln!(f, "  ~ {}: {} -> {}", field, a, b)?;
+                }
+            }
+        }
+
+        if !self.files.is_empty() {
+            writeln!(f, "files:")?;
+            for diff in self.files.iter() {
+                match diff {
+                    &Difference::Added(ref file) => writeln!(f, "  + {}", file)?,
+                    &Difference::Removed(ref file) => writeln!(f, "  - {}", file)?,
+                    &Difference::Changed(ref a, ref b) => {
+                        writeln!(f, "  ~ {}", b.path)?;
+                        for change in file_changes(a, b) {
+                            writeln!(f, "      {}", change)?;
+                        }
+                    }
+                };
+            }
+        }
+
+        let mut table = "";
+        for (name, diff) in self.dependencies.iter() {
+            if table != *name {
+                table = name;
+                writeln!(f, "{}:", table)?;
+            }
+            match diff {
+                &Difference::Added(ref dep) => writeln!(f, "  + {}", dep)?,
+                &Difference::Removed(ref dep) => writeln!(f, "  - {}", dep)?,
+                &Difference::Changed(ref a, ref b) => writeln!(f, "  ~ {} -> {}", a, b)?,
+            };
+        }
+
+        if !self.scripts.is_empty() {
+            writeln!(f, "scripts:")?;
+            for diff in self.scripts.iter() {
+                let (marker, a, b) = match diff {
+                    &Difference::Added(ref s) => ("+", Option::None, s),
+                    &Difference::Removed(ref s) => ("-", Option::None, s),
+                    &Difference::Changed(ref a, ref b) => ("~", Option::Some(a), b),
+                };
+                let program = b.program.as_ref().map(|p| p.as_str()).unwrap_or("(none)");
+                writeln!(f, "  {} {} ({})", marker, b.name, program)?;
+                let lines: Vec<String> = match a {
+                    Option::None => b.body.lines().map(|l| format!("  {}", l)).collect(),
+                    Option::Some(a) => line_diff(&a.body, &b.body),
+                };
+                if let Option::Some(a) = a.filter(|a| a.program != b.program) {
+                    let old = a.program.as_ref().map(|p| p.as_str()).unwrap_or("(none)");
+                    writeln!(f, "      program: {} -> {}", old, program)?;
+                }
+                for line in lines {
+                    writeln!(f, "      {}", line)?;
+                }
+            }
+        }
+
+        if !self.changelog.is_empty() {
+            writeln!(f, "changelog:")?;
+            for diff in self.changelog.iter() {
+                let (marker, change) = match diff {
+                    &Difference::Added(ref c) => ("+", c),
+                    &Difference::Removed(ref c) | &Difference::Changed(_, ref c) => ("-", c),
+                };
+                writeln!(f, "  {} * {} {}", marker, date(change.time), change.author)?;
+            }
+        }
+        Ok(())
+    }
+}
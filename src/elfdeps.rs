@@ -0,0 +1,141 @@
+use std::collections::BTreeSet;
+use std::fs::{read, File};
+use std::io::{Error, Read};
+use std::path::PathBuf;
+
+use super::errors::Err;
+use super::goblin::elf::symver::{VER_FLG_BASE, VER_FLG_WEAK};
+use super::goblin::elf::Elf;
+
+const ELF_MAGIC: &[u8] = b"\x7fELF";
+
+/// ElfDeps are the shared library capabilities discovered by reading the
+/// dynamic section of every ELF file within the package.
+///
+/// Names follow rpm's `elfdeps` conventions, `libc.so.6()(64bit)` for a
+/// soname and `libc.so.6(GLIBC_2.14)(64bit)` for a symbol version.
+#[derive(Clone, Debug, Default)]
+pub struct ElfDeps {
+    pub provides: BTreeSet<String>,
+    pub requires: BTreeSet<String>,
+}
+impl ElfDeps {
+    /// reads every file, non-ELF files are ignored. Like rpm's `elfdeps`,
+    /// a file with ELF magic which fails to parse is skipped, and a warning
+    /// is added to `warnings`.
+    pub fn scan(files: &[PathBuf], warnings: &mut Vec<String>, err: &Err) -> Result<ElfDeps, Err> {
+        let mut deps = ElfDeps::default();
+        for path in files {
+            let read_err = |e: Error| {
+                err.clone()
+                    .note("failed to read file for elf dependencies", e)
+                    .note("path", path.display())
+            };
+
+            // only ELF files are read in full
+            let mut magic = Vec::with_capacity(ELF_MAGIC.len());
+            File::open(path)
+                .and_then(|f| f.take(ELF_MAGIC.len() as u64).read_to_end(&mut magic))
+                .map_err(read_err)?;
+            if magic != ELF_MAGIC {
+                continue;
+            }
+            let data = read(path).map_err(read_err)?;
+            match Elf::parse(&data) {
+                Ok(elf) => deps.add(&elf),
+                Err(e) => warnings.push(format!(
+                    "{} is not a valid elf file, its dependencies were skipped: {}",
+                    path.display(),
+                    e
+                )),
+            };
+        }
+        Ok(deps)
+    }
+
+    fn add(&mut self, elf: &Elf) {
+        let marker = if elf.is_64 { "(64bit)" } else { "" };
+
+        if let Option::Some(soname) = elf.soname {
+            self.provides.insert(format!("{}(){}", soname, marker));
+            if let Option::Some(ref verdef) = elf.verdef {
+                for def in verdef.iter() {
+                    if def.vd_flags & VER_FLG_BASE != 0 {
+                        continue;
+                    }
+                    let name = def
+                        .iter()
+                        .next()
+                        .and_then(|aux| elf.dynstrtab.get_at(aux.vda_name));
+                    if let Option::Some(name) = name {
+                        self.provides
+                            .insert(format!("{}({}){}", soname, name, marker));
+                    }
+                }
+            }
+        }
+
+        for library in elf.libraries.iter() {
+            self.requires.insert(format!("{}(){}", library, marker));
+        }
+        if let Option::Some(ref verneed) = elf.verneed {
+            for need in verneed.iter() {
+                let library = match elf.dynstrtab.get_at(need.vn_file) {
+                    Option::None => continue,
+                    Option::Some(library) => library,
+                };
+                for aux in need.iter() {
+                    if aux.vna_flags & VER_FLG_WEAK != 0 {
+                        continue;
+                    }
+                    if let Option::Some(version) = elf.dynstrtab.get_at(aux.vna_name) {
+                        self.requires
+                            .insert(format!("{}({}){}", library, version, marker));
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::write;
+    use std::path::Path;
+
+    const FIXTURE: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/elf");
+
+    #[test]
+    fn sonames_and_versions_are_read() {
+        let files = vec![
+            Path::new(FIXTURE).join("libdemo.so.1"),
+            Path::new(FIXTURE).join("demo.c"),
+        ];
+        let mut warnings = Vec::new();
+        let deps = ElfDeps::scan(&files, &mut warnings, &Err::default()).unwrap();
+        assert!(warnings.is_empty(), "{:?}", warnings);
+        let provides: Vec<&str> = deps.provides.iter().map(|p| p.as_str()).collect();
+        assert_eq!(
+            provides,
+            vec!["libdemo.so.1()(64bit)", "libdemo.so.1(DEMO_1.0)(64bit)"]
+        );
+        let requires: Vec<&str> = deps.requires.iter().map(|r| r.as_str()).collect();
+        assert_eq!(
+            requires,
+            vec!["libc.so.6()(64bit)", "libc.so.6(GLIBC_2.2.5)(64bit)"]
+        );
+    }
+
+    #[test]
+    fn invalid_elf_files_are_warnings() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("truncated");
+        write(&path, b"\x7fELF\x02\x01").unwrap();
+        let mut warnings = Vec::new();
+        let deps = ElfDeps::scan(&[path], &mut warnings, &Err::default()).unwrap();
+        assert!(deps.provides.is_empty() && deps.requires.is_empty());
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].contains("is not a valid elf file"));
+    }
+}
@@ -0,0 +1,211 @@
+use std::collections::BTreeMap;
+use std::fs::{
+    create_dir, create_dir_all, read_to_string, remove_file, set_permissions, symlink_metadata,
+    OpenOptions, Permissions,
+};
+use std::io::{Error as IOError, Write};
+use std::os::unix::fs::{lchown, symlink, PermissionsExt};
+use std::path::{Component, Path, PathBuf};
+use std::time::{Duration, UNIX_EPOCH};
+
+use super::errors::Err;
+use super::rpm::Package;
+
+use super::inspect::File;
+
+/// extracts the payload of the RPM at `rpm_path` beneath `dir`, recreating
+/// its files, directories, symlinks, modes and mtimes.
+///
+/// When `owners` is set the ownership recorded in the package is applied
+/// as well, which requires root. Returns every entry extracted, and
+/// warnings for anything which was skipped.
+pub fn extract_rpm(
+    rpm_path: &str,
+    dir: &Path,
+    owners: bool,
+) -> Result<(Vec<File>, Vec<String>), Err> {
+    let err = Err::default().note("rpm", rpm_path);
+    let pkg = Package::open(rpm_path).map_err(|e| {
+        err.clone()
+            .note("failed to parse rpm", format_args!("{}", e))
+    })?;
+    let files = pkg.files().map_err(|e| {
+        err.clone()
+            .note("failed to read payload", format_args!("{}", e))
+    })?;
+    let ids = if owners {
+        Option::Some(Ids::load(&err)?)
+    } else {
+        Option::None
+    };
+
+    let mut warnings = Vec::new();
+    let mut extracted = Vec::new();
+    let mut dirs = Vec::new();
+    for file in files {
+        let file = file.map_err(|e| {
+            err.clone()
+                .note("failed to read payload", format_args!("{}", e))
+        })?;
+        let entry = File::from_entry(file.metadata);
+        let path = destination(dir, &entry.path, &err)?;
+        let write_err = |e: IOError| {
+            err.clone()
+                .note("failed to extract file", e)
+                .note("path", path.display())
+        };
+
+        let owner = ids.as_ref().map(|ids| ids.resolve(&entry, &mut warnings));
+        match entry.mode & 0o170000 {
+            // directories are restricted once their contents are written
+            0o040000 => {
+                remove_existing(&path).map_err(write_err)?;
+                create_dir_all(&path).map_err(write_err)?;
+                dirs.push((path.clone(), entry.mode, owner));
+            }
+            0o120000 => {
+                remove_existing(&path).map_err(write_err)?;
+                let target = entry.linkto.clone().unwrap_or_default();
+                symlink(&target, &path).map_err(write_err)?;
+                if let Option::Some((uid, gid)) = owner {
+                    lchown(&path, Option::Some(uid), Option::Some(gid)).map_err(write_err)?;
+                }
+            }
+            0o100000 | 0 => {
+                remove_existing(&path).map_err(write_err)?;
+                // `create_new` never follows a symlink left in the way
+                let mut output = OpenOptions::new()
+                    .write(true)
+                    .create_new(true)
+                    .open(&path)
+                    .map_err(write_err)?;
+                output.write_all(&file.content).map_err(write_err)?;
+                output
+                    .set_modified(UNIX_EPOCH + Duration::from_secs(entry.mtime as u64))
+                    .map_err(write_err)?;
+                // ownership first, as chown clears the setuid and setgid bits
+                if let Option::Some((uid, gid)) = owner {
+                    lchown(&path, Option::Some(uid), Option::Some(gid)).map_err(write_err)?;
+                }
+                set_permissions(&path, Permissions::from_mode((entry.mode & 0o7777) as u32))
+                    .map_err(write_err)?;
+            }
+            _ => {
+                warnings.push(format!(
+                    "{} is a device, fifo or socket, it was skipped",
+                    entry.path
+                ));
+                continue;
+            }
+        };
+        extracted.push(entry);
+    }
+
+    // the deepest directories are restricted first
+    for (path, mode, owner) in dirs.into_iter().rev() {
+        let write_err = |e: IOError| {
+            err.clone()
+                .note("failed to extract directory", e)
+                .note("path", path.display())
+        };
+        if let Option::Some((uid, gid)) = owner {
+            lchown(&path, Option::Some(uid), Option::Some(gid)).map_err(write_err)?;
+        }
+        set_permissions(&path, Permissions::from_mode((mode & 0o7777) as u32))
+            .map_err(write_err)?;
+    }
+    Ok((extracted, warnings))
+}
+
+/// the path beneath `dir` an entry is extracted to, creating its parent
+/// directories. Entries which would escape `dir` are rejected, including
+/// those beneath a symlink, which an earlier entry could have pointed
+/// anywhere.
+pub fn destination(dir: &Path, path: &str, err: &Err) -> Result<PathBuf, Err> {
+    let rel = Path::new(path.trim_start_matches('/'));
+    let escapes = rel
+        .components()
+        .any(|c| !matches!(c, Component::Normal(_) | Component::CurDir));
+    if escapes || rel.as_os_str().is_empty() {
+        return Err(err.clone().note("unsafe path within payload", path));
+    }
+
+    let mut parent = dir.to_path_buf();
+    for component in rel.parent().into_iter().flat_map(|p| p.components()) {
+        if component == Component::CurDir {
+            continue;
+        }
+        parent.push(component);
+        match symlink_metadata(&parent) {
+            Ok(ref meta) if meta.file_type().is_symlink() => {
+                return Err(err
+                    .clone()
+                    .note("unsafe path within payload", path)
+                    .note("symlink in place of a directory", parent.display()))
+            }
+            Ok(_) => {}
+            Err(_) => create_dir(&parent).map_err(|e| {
+                err.clone()
+                    .note("failed to create directory", e)
+                    .note("path", parent.display())
+            })?,
+        };
+    }
+    Ok(dir.join(rel))
+}
+
+/// removes whatever, other than a directory, already exists at `path`
+pub fn remove_existing(path: &Path) -> Result<(), IOError> {
+    match symlink_metadata(path) {
+        Ok(ref meta) if !meta.is_dir() => remove_file(path),
+        _ => Ok(()),
+    }
+}
+
+/// Ids maps the user and group names of the host to their ids, names the
+/// host lacks are owned by root as rpm would.
+struct Ids {
+    users: BTreeMap<String, u32>,
+    groups: BTreeMap<String, u32>,
+}
+impl Ids {
+    fn load(err: &Err) -> Result<Ids, Err> {
+        Ok(Ids {
+            users: id_table("/etc/passwd", err)?,
+            groups: id_table("/etc/group", err)?,
+        })
+    }
+
+    fn resolve(&self, file: &File, warnings: &mut Vec<String>) -> (u32, u32) {
+        let mut lookup = |table: &BTreeMap<String, u32>, kind: &str, name: &str| -> u32 {
+            match table.get(name) {
+                Option::Some(id) => *id,
+                Option::None => {
+                    let warning = format!("{} {} does not exist, root was used", kind, name);
+                    if !warnings.contains(&warning) {
+                        warnings.push(warning);
+                    }
+                    0
+                }
+            }
+        };
+        let uid = lookup(&self.users, "user", &file.user);
+        let gid = lookup(&self.groups, "group", &file.group);
+        (uid, gid)
+    }
+}
+
+/// reads the name and id, the first and third fields, of every line of
+/// `/etc/passwd` or `/etc/group`
+fn id_table(path: &str, err: &Err) -> Result<BTreeMap<String, u32>, Err> {
+    let data = read_to_string(path)
+        .map_err(|e| err.clone().note("failed to read ids", e).note("path", path))?;
+    Ok(data
+        .lines()
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split(':').collect();
+            let id = fields.get(2)?.parse::<u32>().ok()?;
+            Option::Some((fields[0].to_string(), id))
+        })
+        .collect())
+}
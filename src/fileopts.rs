@@ -2,11 +2,19 @@ use std::env::current_dir;
 use std::path::{Path, PathBuf};
 
 use super::errors::Err;
-use super::rpm::{RPMBuilder, RPMError, RPMFileOptions, RPMFileOptionsBuilder};
+use super::glob::{glob, MatchOptions, Pattern};
+use super::rpm::{
+    FileOptions as RPMFileOptions, FileOptionsBuilder as RPMFileOptionsBuilder, PackageBuilder,
+};
 use super::serde::{Deserialize, Serialize};
+use super::walkdir::WalkDir;
 
 /// FileOptions provides either a simplified or complex view of where
 /// files will end up when installed.
+///
+/// The source path may be a literal file, a directory (which is walked
+/// recursively) or a glob. For directories and globs the `dst` is treated
+/// as a prefix and every matched file is installed beneath it.
 #[derive(Clone, Hash, Debug, Deserialize, Serialize)]
 #[serde(untagged)]
 pub enum FileOptions {
@@ -19,27 +27,113 @@ impl FileOptions {
     pub fn build<'a>(
         &'a self,
         source: &'a str,
-    ) -> impl FnOnce(Result<RPMBuilder, Err>, &Err) -> Result<RPMBuilder, Err> + 'a {
-        move |builder_result, err| -> Result<RPMBuilder, Err> {
-            builder_result?
-                .with_file(source, self.make_opts())
-                .map_err(|e| {
-                    err.clone()
-                        .note("failed to load src", format_args!("{:?}", e))
-                        .note("src", source.to_string())
+    ) -> impl FnOnce(Result<PackageBuilder, Err>, &Err) -> Result<PackageBuilder, Err> + 'a {
+        move |builder_result, err| -> Result<PackageBuilder, Err> {
+            let builder = builder_result?;
+            self.expand(source, err)?
+                .into_iter()
+                .try_fold(builder, |builder, (src, dst)| {
+                    builder.with_file(&src, self.make_opts(&dst)).map_err(|e| {
+                        err.clone()
+                            .note("failed to load src", format_args!("{:?}", e))
+                            .note("src", src.display())
+                    })
                 })
         }
     }
 
-    fn make_opts(&self) -> RPMFileOptionsBuilder {
+    /// expands the source into every `(source file, install path)` pair it
+    /// describes.
+    pub fn expand(&self, source: &str, err: &Err) -> Result<Vec<(PathBuf, String)>, Err> {
+        let err = err.clone().note("src", source);
+        let excludes = self.excludes(&err)?;
+
+        let (base, matches) = match literal(source) {
+            Option::Some(path) => (PathBuf::from(&path), vec![PathBuf::from(path)]),
+            Option::None => (glob_base(source), expand_glob(source, &err)?),
+        };
+
+        let mut files = Vec::new();
+        for path in matches {
+            if path.is_dir() {
+                files.extend(walk_dir(&path, &err)?);
+            } else {
+                files.push(path);
+            }
+        }
+
+        // a literal file installs exactly at `dst`
+        if files.len() == 1 && files[0] == base {
+            return Ok(vec![(base, self.dst().to_string())]);
+        }
+
+        let mut output = Vec::with_capacity(files.len());
+        for path in files {
+            let rel = relative(&base, &path);
+            if excludes
+                .iter()
+                .any(|pat| pat.matches_with(&rel, MatchOptions::new()))
+            {
+                continue;
+            }
+            let dst = format!("{}/{}", self.dst().trim_end_matches('/'), rel);
+            output.push((path, dst));
+        }
+        if output.is_empty() {
+            return Err(err.note("error", "source matched no files"));
+        }
+        Ok(output)
+    }
+
+    /// the options shared by every file this entry expands into, `None`
+    /// for the simple form.
+    pub fn complex(&self) -> Option<&ComplexFileOptions> {
+        match self {
+            &FileOptions::Simple(_) => Option::None,
+            &FileOptions::Complex(ref cmp) => Option::Some(cmp),
+        }
+    }
+
+    /// where the source is installed, or the prefix of its files
+    pub fn dst(&self) -> &str {
         match self {
-            &FileOptions::Simple(ref dst) => RPMFileOptions::new(dst.to_string()),
-            &FileOptions::Complex(ref cmp) => cmp.build(),
+            &FileOptions::Simple(ref dst) => dst,
+            &FileOptions::Complex(ref cmp) => &cmp.dst,
+        }
+    }
+
+    fn excludes(&self, err: &Err) -> Result<Vec<Pattern>, Err> {
+        let patterns = match self {
+            &FileOptions::Complex(ComplexFileOptions {
+                exclude: Option::Some(ref patterns),
+                ..
+            }) => patterns,
+            _ => return Ok(Vec::new()),
+        };
+        patterns
+            .iter()
+            .map(|pat| {
+                Pattern::new(pat).map_err(|e| {
+                    err.clone()
+                        .note("invalid exclude pattern", pat)
+                        .note("error", e)
+                })
+            })
+            .collect()
+    }
+
+    fn make_opts(&self, dst: &str) -> RPMFileOptionsBuilder {
+        match self {
+            &FileOptions::Simple(_) => RPMFileOptions::new(dst.to_string()),
+            &FileOptions::Complex(ref cmp) => cmp.build(dst),
         }
     }
 }
 
 /// ComplexFileOptions encodes RPM specific options for an individual file.
+///
+/// When the source is a directory or glob these options are shared by
+/// every file it expands into.
 #[derive(Clone, Hash, Debug, Deserialize, Serialize, Default)]
 pub struct ComplexFileOptions {
     pub dst: String,
@@ -55,14 +149,18 @@ pub struct ComplexFileOptions {
     pub doc: Option<bool>,
     #[serde(default)]
     pub config: Option<bool>,
+    #[serde(default)]
+    pub exclude: Option<Vec<String>>,
 }
 impl ComplexFileOptions {
-    fn build(&self) -> RPMFileOptionsBuilder {
-        let mut opts = RPMFileOptions::new(&self.dst.clone());
+    fn build(&self, dst: &str) -> RPMFileOptionsBuilder {
+        let mut opts = RPMFileOptions::new(dst.to_string());
         opts = (Self::add_optional(&self.user, RPMFileOptionsBuilder::user))(opts);
         opts = (Self::add_optional(&self.group, RPMFileOptionsBuilder::group))(opts);
         opts = (Self::add_optional(&self.symlink, RPMFileOptionsBuilder::symlink))(opts);
-        opts = (Self::add_optional(&self.mode, RPMFileOptionsBuilder::mode))(opts);
+        opts = (Self::add_optional(&self.mode, |opts: RPMFileOptionsBuilder, mode: i32| {
+            opts.mode(mode as u16)
+        }))(opts);
         if self.doc == Option::Some(true) {
             opts = opts.is_doc();
         }
@@ -88,3 +186,73 @@ impl ComplexFileOptions {
         }
     }
 }
+
+fn is_glob(source: &str) -> bool {
+    source.contains(|c| c == '*' || c == '?' || c == '[')
+}
+
+/// the path a source names when it has no wild cards. Wild card
+/// characters escaped as a single character class, e.g. `[*]` (as
+/// `Pattern::escape` produces), are literal.
+fn literal(source: &str) -> Option<String> {
+    if !is_glob(source) {
+        return Option::Some(source.to_string());
+    }
+    let mut output = String::with_capacity(source.len());
+    let mut chars = source.chars();
+    while let Option::Some(c) = chars.next() {
+        match c {
+            '*' | '?' => return Option::None,
+            '[' => match (chars.next(), chars.next()) {
+                (Option::Some(escaped), Option::Some(']')) if escaped != '!' => {
+                    output.push(escaped)
+                }
+                _ => return Option::None,
+            },
+            c => output.push(c),
+        }
+    }
+    Option::Some(output)
+}
+
+/// the leading components of a glob which contain no wild cards, matched
+/// paths are installed relative to this.
+fn glob_base(source: &str) -> PathBuf {
+    Path::new(source)
+        .components()
+        .take_while(|c| !is_glob(&c.as_os_str().to_string_lossy()))
+        .collect()
+}
+
+fn expand_glob(source: &str, err: &Err) -> Result<Vec<PathBuf>, Err> {
+    let paths = glob(source).map_err(|e| err.clone().note("invalid glob", e))?;
+    paths
+        .map(|path| path.map_err(|e| err.clone().note("failed to read glob match", e)))
+        .collect()
+}
+
+fn walk_dir(dir: &Path, err: &Err) -> Result<Vec<PathBuf>, Err> {
+    WalkDir::new(dir)
+        .sort_by(|a, b| a.file_name().cmp(b.file_name()))
+        .into_iter()
+        .filter(|entry| match entry {
+            &Ok(ref entry) => !entry.file_type().is_dir(),
+            &Err(_) => true,
+        })
+        .map(|entry| {
+            entry
+                .map(|entry| entry.path().to_path_buf())
+                .map_err(|e| err.clone().note("failed to walk directory", e))
+        })
+        .collect()
+}
+
+/// relative path from `base` to `path` using `/` separators
+pub fn relative(base: &Path, path: &Path) -> String {
+    path.strip_prefix(base)
+        .unwrap_or(path)
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy().into_owned())
+        .collect::<Vec<String>>()
+        .join("/")
+}
@@ -0,0 +1,261 @@
+use std::collections::BTreeMap;
+
+/*
+ * tags, only those which are written by this crate are listed
+ *
+ */
+pub const HEADERSIGNATURES: u32 = 62;
+pub const HEADERIMMUTABLE: u32 = 63;
+pub const HEADERI18NTABLE: u32 = 100;
+
+pub const SIGTAG_SHA256: u32 = 273;
+pub const SIGTAG_LONGSIZE: u32 = 270;
+pub const SIGTAG_LONGARCHIVESIZE: u32 = 271;
+pub const SIGTAG_SIZE: u32 = 1000;
+pub const SIGTAG_PAYLOADSIZE: u32 = 1007;
+
+pub const NAME: u32 = 1000;
+pub const VERSION: u32 = 1001;
+pub const RELEASE: u32 = 1002;
+pub const EPOCH: u32 = 1003;
+pub const SUMMARY: u32 = 1004;
+pub const DESCRIPTION: u32 = 1005;
+pub const BUILDTIME: u32 = 1006;
+pub const BUILDHOST: u32 = 1007;
+pub const SIZE: u32 = 1009;
+pub const LICENSE: u32 = 1014;
+pub const GROUP: u32 = 1016;
+pub const OS: u32 = 1021;
+pub const ARCH: u32 = 1022;
+pub const PREIN: u32 = 1023;
+pub const POSTIN: u32 = 1024;
+pub const PREUN: u32 = 1025;
+pub const POSTUN: u32 = 1026;
+pub const FILESIZES: u32 = 1028;
+pub const FILEMODES: u32 = 1030;
+pub const FILERDEVS: u32 = 1033;
+pub const FILEMTIMES: u32 = 1034;
+pub const FILEDIGESTS: u32 = 1035;
+pub const FILELINKTOS: u32 = 1036;
+pub const FILEFLAGS: u32 = 1037;
+pub const FILEUSERNAME: u32 = 1039;
+pub const FILEGROUPNAME: u32 = 1040;
+pub const SOURCERPM: u32 = 1044;
+pub const FILEVERIFYFLAGS: u32 = 1045;
+pub const PROVIDENAME: u32 = 1047;
+pub const REQUIREFLAGS: u32 = 1048;
+pub const REQUIRENAME: u32 = 1049;
+pub const REQUIREVERSION: u32 = 1050;
+pub const CONFLICTFLAGS: u32 = 1053;
+pub const CONFLICTNAME: u32 = 1054;
+pub const CONFLICTVERSION: u32 = 1055;
+pub const RPMVERSION: u32 = 1064;
+pub const CHANGELOGTIME: u32 = 1080;
+pub const CHANGELOGNAME: u32 = 1081;
+pub const CHANGELOGTEXT: u32 = 1082;
+pub const PREINPROG: u32 = 1085;
+pub const POSTINPROG: u32 = 1086;
+pub const PREUNPROG: u32 = 1087;
+pub const POSTUNPROG: u32 = 1088;
+pub const OBSOLETENAME: u32 = 1090;
+pub const FILEDEVICES: u32 = 1095;
+pub const FILEINODES: u32 = 1096;
+pub const FILELANGS: u32 = 1097;
+pub const SOURCEPACKAGE: u32 = 1106;
+pub const PROVIDEFLAGS: u32 = 1112;
+pub const PROVIDEVERSION: u32 = 1113;
+pub const OBSOLETEFLAGS: u32 = 1114;
+pub const OBSOLETEVERSION: u32 = 1115;
+pub const DIRINDEXES: u32 = 1116;
+pub const BASENAMES: u32 = 1117;
+pub const DIRNAMES: u32 = 1118;
+pub const PAYLOADFORMAT: u32 = 1124;
+pub const PAYLOADCOMPRESSOR: u32 = 1125;
+pub const PAYLOADFLAGS: u32 = 1126;
+pub const LONGFILESIZES: u32 = 5008;
+pub const LONGSIZE: u32 = 5009;
+pub const FILEDIGESTALGO: u32 = 5011;
+pub const RECOMMENDNAME: u32 = 5046;
+pub const RECOMMENDVERSION: u32 = 5047;
+pub const RECOMMENDFLAGS: u32 = 5048;
+pub const SUGGESTNAME: u32 = 5049;
+pub const SUGGESTVERSION: u32 = 5050;
+pub const SUGGESTFLAGS: u32 = 5051;
+pub const SUPPLEMENTNAME: u32 = 5052;
+pub const SUPPLEMENTVERSION: u32 = 5053;
+pub const SUPPLEMENTFLAGS: u32 = 5054;
+pub const ENHANCENAME: u32 = 5055;
+pub const ENHANCEVERSION: u32 = 5056;
+pub const ENHANCEFLAGS: u32 = 5057;
+pub const ENCODING: u32 = 5062;
+pub const PAYLOADDIGEST: u32 = 5092;
+pub const PAYLOADDIGESTALGO: u32 = 5093;
+
+/// the `PGPHASHALGO` value for sha256
+pub const DIGEST_SHA256: u32 = 8;
+
+const MAGIC: [u8; 4] = [0x8e, 0xad, 0xe8, 0x01];
+const LEAD_MAGIC: [u8; 4] = [0xed, 0xab, 0xee, 0xdb];
+const LEAD_LEN: usize = 96;
+
+/// Value is the data of a single header entry
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value {
+    Int16(Vec<u16>),
+    Int32(Vec<u32>),
+    Int64(Vec<u64>),
+    Str(String),
+    Bin(Vec<u8>),
+    StrArray(Vec<String>),
+    I18n(String),
+}
+impl Value {
+    fn kind(&self) -> u32 {
+        match self {
+            &Value::Int16(_) => 3,
+            &Value::Int32(_) => 4,
+            &Value::Int64(_) => 5,
+            &Value::Str(_) => 6,
+            &Value::Bin(_) => 7,
+            &Value::StrArray(_) => 8,
+            &Value::I18n(_) => 9,
+        }
+    }
+
+    fn alignment(&self) -> usize {
+        match self {
+            &Value::Int16(_) => 2,
+            &Value::Int32(_) => 4,
+            &Value::Int64(_) => 8,
+            _ => 1,
+        }
+    }
+
+    fn count(&self) -> usize {
+        match self {
+            &Value::Int16(ref v) => v.len(),
+            &Value::Int32(ref v) => v.len(),
+            &Value::Int64(ref v) => v.len(),
+            &Value::Str(_) => 1,
+            &Value::Bin(ref v) => v.len(),
+            &Value::StrArray(ref v) => v.len(),
+            &Value::I18n(_) => 1,
+        }
+    }
+
+    fn encode(&self, store: &mut Vec<u8>) {
+        match self {
+            &Value::Int16(ref v) => v.iter().for_each(|x| store.extend(&x.to_be_bytes())),
+            &Value::Int32(ref v) => v.iter().for_each(|x| store.extend(&x.to_be_bytes())),
+            &Value::Int64(ref v) => v.iter().for_each(|x| store.extend(&x.to_be_bytes())),
+            &Value::Bin(ref v) => store.extend(v),
+            &Value::Str(ref s) | &Value::I18n(ref s) => {
+                store.extend(s.as_bytes());
+                store.push(0);
+            }
+            &Value::StrArray(ref v) => v.iter().for_each(|s| {
+                store.extend(s.as_bytes());
+                store.push(0);
+            }),
+        }
+    }
+}
+
+/// Header is an rpm header under construction, entries are kept sorted by
+/// tag as rpm searches them with a binary search.
+#[derive(Clone, Debug, Default)]
+pub struct Header {
+    entries: BTreeMap<u32, Value>,
+}
+impl Header {
+    /// adds an entry, empty arrays are skipped as rpm rejects them
+    pub fn insert(&mut self, tag: u32, value: Value) {
+        if value.count() > 0 {
+            self.entries.insert(tag, value);
+        }
+    }
+
+    /// encodes the header, with every entry in the `region` (either
+    /// `HEADERIMMUTABLE` or `HEADERSIGNATURES`).
+    pub fn encode(&self, region: u32) -> Vec<u8> {
+        let count = self.entries.len() + 1;
+        let mut index = Vec::with_capacity(count * 16);
+        let mut store = Vec::new();
+
+        for (tag, value) in self.entries.iter() {
+            while store.len() % value.alignment() != 0 {
+                store.push(0);
+            }
+            index.push((*tag, value.kind(), store.len() as u32, value.count() as u32));
+            value.encode(&mut store);
+        }
+
+        // the region trailer closes the store, pointing back at the index
+        let trailer_offset = store.len() as u32;
+        let back = -((count * 16) as i32);
+        for x in &[region, 7, back as u32, 16] {
+            store.extend(&x.to_be_bytes());
+        }
+        index.insert(0, (region, 7, trailer_offset, 16));
+
+        let mut output = Vec::with_capacity(16 + index.len() * 16 + store.len());
+        output.extend(&MAGIC);
+        output.extend(&[0u8; 4]);
+        output.extend(&(count as u32).to_be_bytes());
+        output.extend(&(store.len() as u32).to_be_bytes());
+        for (tag, kind, offset, count) in index {
+            for x in &[tag, kind, offset, count] {
+                output.extend(&x.to_be_bytes());
+            }
+        }
+        output.extend(store);
+        output
+    }
+
+    /// encodes the header as the signature header, which is padded to a
+    /// multiple of 8 bytes.
+    pub fn encode_signature(&self) -> Vec<u8> {
+        let mut output = self.encode(HEADERSIGNATURES);
+        while output.len() % 8 != 0 {
+            output.push(0);
+        }
+        output
+    }
+}
+
+/// the offsets, within an rpm, of the start of the main header and of the
+/// payload which follows it
+pub fn header_range(rpm: &[u8]) -> Option<(u64, u64)> {
+    let signature = encoded_len(rpm.get(LEAD_LEN..)?)?;
+    let start = (LEAD_LEN + signature + 7) / 8 * 8;
+    let end = start + encoded_len(rpm.get(start..)?)?;
+    Option::Some((start as u64, end as u64))
+}
+
+/// the length of the encoded header `data` starts with
+fn encoded_len(data: &[u8]) -> Option<usize> {
+    if data.len() < 16 || data[..4] != MAGIC {
+        return Option::None;
+    }
+    let word = |at: usize| u32::from_be_bytes([data[at], data[at + 1], data[at + 2], data[at + 3]]);
+    Option::Some(16 + word(8) as usize * 16 + word(12) as usize)
+}
+
+/// the 96 byte lead which starts every rpm, only its magic and type are
+/// still read by rpm.
+pub fn lead(name: &str, source: bool) -> Vec<u8> {
+    let mut output = Vec::with_capacity(96);
+    output.extend(&LEAD_MAGIC);
+    output.extend(&[3, 0]);
+    output.extend(&(if source { 1u16 } else { 0u16 }).to_be_bytes());
+    output.extend(&0u16.to_be_bytes());
+    let mut field = [0u8; 66];
+    for (dst, src) in field.iter_mut().zip(name.as_bytes().iter().take(65)) {
+        *dst = *src;
+    }
+    output.extend(&field[..]);
+    output.extend(&1u16.to_be_bytes());
+    output.extend(&5u16.to_be_bytes());
+    output.extend(&[0u8; 16]);
+    output
+}
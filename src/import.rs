@@ -0,0 +1,307 @@
+use std::collections::BTreeMap;
+use std::fs::{create_dir_all, set_permissions, write, Permissions};
+use std::io::Error as IOError;
+use std::os::unix::fs::PermissionsExt;
+use std::path::Path;
+
+use super::chrono::DateTime;
+use super::errors::Err;
+use super::glob::Pattern;
+use super::rpm::Package;
+
+use super::changelog::{insert_entry, ChangeLogEntry};
+use super::core::ConfigFile;
+use super::extract::{destination, remove_existing};
+use super::fileopts::{ComplexFileOptions, FileOptions};
+use super::inspect::{Dep, File, Inspection};
+use super::rpm_meta::{Algorithm, Compression};
+use super::scripts::Scripts;
+use super::versions::Constraints;
+
+/// the directory, within the import directory, the payload is extracted to
+pub const PAYLOAD_DIR: &str = "root";
+
+/// the directory, within the import directory, scripts are written to
+pub const SCRIPTS_DIR: &str = "scripts";
+
+/// imports the RPM at `rpm_path` into `dir`, extracting its payload and
+/// scriptlets and creating a config which reproduces it.
+///
+/// Every source path within the config is relative to `dir`. Anything
+/// which a config can not express is returned as a warning.
+pub fn import_rpm(rpm_path: &str, dir: &Path) -> Result<(ConfigFile, Vec<String>), Err> {
+    let err = Err::default().note("rpm", rpm_path);
+    let pkg = Package::open(rpm_path).map_err(|e| {
+        err.clone()
+            .note("failed to parse rpm", format_args!("{}", e))
+    })?;
+    let inspection = Inspection::from_package(&pkg, &err)?;
+
+    let mut warnings = Vec::new();
+    let mut config = ConfigFile::default();
+    metadata(&inspection, &mut config, &mut warnings);
+    dependencies(&inspection, &mut config, &mut warnings);
+    extract(&pkg, dir, &mut config, &mut warnings, &err)?;
+    scripts(&inspection, dir, &mut config, &mut warnings, &err)?;
+    changelog(&inspection, &mut config);
+    Ok((config, warnings))
+}
+
+fn metadata(inspection: &Inspection, config: &mut ConfigFile, warnings: &mut Vec<String>) {
+    config.rpm.name = inspection.name.clone();
+    config.rpm.version = inspection.version.clone();
+    config.rpm.license = inspection.license.clone();
+    config.rpm.arch = inspection.arch.clone();
+    config.rpm.desc = inspection.summary.clone();
+    config.rpm.description = Option::Some(inspection.description.clone())
+        .filter(|description| !description.is_empty() && *description != inspection.summary);
+    config.rpm.epoch = inspection.epoch;
+    config.rpm.release = match inspection.release.parse::<u16>() {
+        Ok(release) => Option::Some(release),
+        Err(_) => {
+            warnings.push(format!(
+                "release '{}' is not a number, it was dropped",
+                inspection.release
+            ));
+            Option::None
+        }
+    };
+    config.rpm.compression = match inspection.payload_compressor.as_ref().map(|c| c.as_str()) {
+        Option::Some("gzip") => Option::Some(Algorithm::Gzip),
+        Option::Some("xz") => Option::Some(Algorithm::Xz),
+        Option::Some("zstd") => Option::Some(Algorithm::Zstd),
+        _ => Option::None,
+    }
+    .map(|algo| Compression {
+        algo,
+        level: Option::None,
+    });
+}
+
+/// dependencies which rpm, or the builder, adds on its own are skipped
+fn dependencies(inspection: &Inspection, config: &mut ConfigFile, warnings: &mut Vec<String>) {
+    let evr = inspection.evr();
+    let own = [
+        inspection.name.clone(),
+        format!("{}({})", inspection.name, inspection.arch),
+    ];
+
+    let mut tables: BTreeMap<&'static str, BTreeMap<String, Vec<String>>> = BTreeMap::new();
+    for (table, deps) in inspection.dependency_tables() {
+        for dep in deps {
+            if dep.flags.iter().any(|f| f == "rpmlib") {
+                continue;
+            }
+            if table == "provides"
+                && own.contains(&dep.name)
+                && dep.op.as_ref().map(|op| op.as_str()) == Option::Some("=")
+                && dep.version == evr
+            {
+                continue;
+            }
+            if dep.name.starts_with('(') {
+                match table {
+                    "requires" => config.rich_requires.push(dep.name.clone()),
+                    "conflicts" => config.rich_conflicts.push(dep.name.clone()),
+                    _ => warnings.push(format!(
+                        "rich dependency '{}' in {} is not supported, it was dropped",
+                        dep.name, table
+                    )),
+                };
+                continue;
+            }
+            let table = match (table, scriptlet(dep)) {
+                ("requires", Option::Some(when)) => when,
+                (table, _) => table,
+            };
+            let constraint = match dep.op {
+                Option::None => String::new(),
+                Option::Some(ref op) => format!("{} {}", op, dep.version),
+            };
+            tables
+                .entry(table)
+                .or_insert_with(BTreeMap::new)
+                .entry(dep.name.clone())
+                .or_insert_with(Vec::new)
+                .push(constraint);
+        }
+    }
+
+    for (table, entries) in tables {
+        let target = match table {
+            "requires" => &mut config.requires,
+            "requires_pre" => &mut config.requires_pre,
+            "requires_post" => &mut config.requires_post,
+            "requires_preun" => &mut config.requires_preun,
+            "requires_postun" => &mut config.requires_postun,
+            "provides" => &mut config.provides,
+            "conflicts" => &mut config.conflicts,
+            "obsoletes" => &mut config.obsoletes,
+            "recommends" => &mut config.recommends,
+            "suggests" => &mut config.suggests,
+            "supplements" => &mut config.supplements,
+            _ => &mut config.enhances,
+        };
+        for (name, mut constraints) in entries {
+            let constraints = if constraints.len() == 1 {
+                Constraints::One(constraints.remove(0))
+            } else {
+                Constraints::Many(constraints)
+            };
+            target.insert(name, constraints);
+        }
+    }
+}
+
+/// the `requires_*` table of a dependency ordered against a scriptlet
+fn scriptlet(dep: &Dep) -> Option<&'static str> {
+    let tables = [
+        ("pre", "requires_pre"),
+        ("post", "requires_post"),
+        ("preun", "requires_preun"),
+        ("postun", "requires_postun"),
+    ];
+    tables
+        .iter()
+        .find(|(flag, _)| dep.flags.iter().any(|f| f == *flag))
+        .map(|(_, table)| *table)
+}
+
+/// writes every file of the payload beneath `dir/root`
+fn extract(
+    pkg: &Package,
+    dir: &Path,
+    config: &mut ConfigFile,
+    warnings: &mut Vec<String>,
+    err: &Err,
+) -> Result<(), Err> {
+    let files = pkg.files().map_err(|e| {
+        err.clone()
+            .note("failed to read payload", format_args!("{}", e))
+    })?;
+    let payload = dir.join(PAYLOAD_DIR);
+    create_dir_all(&payload).map_err(|e| {
+        err.clone()
+            .note("failed to create directory", e)
+            .note("path", payload.display())
+    })?;
+
+    let mut dirs = 0;
+    for file in files {
+        let file = file.map_err(|e| {
+            err.clone()
+                .note("failed to read payload", format_args!("{}", e))
+        })?;
+        let entry = File::from_entry(file.metadata);
+        if entry.is_dir() {
+            dirs += 1;
+            continue;
+        }
+
+        // keys are read back as globs, so wild cards in names are escaped
+        let source = Pattern::escape(&format!(
+            "{}/{}",
+            PAYLOAD_DIR,
+            entry.path.trim_start_matches('/')
+        ));
+        let path = destination(&payload, &entry.path, err)?;
+        let write_err = |e: IOError| {
+            err.clone()
+                .note("failed to extract file", e)
+                .note("path", path.display())
+        };
+        remove_existing(&path).map_err(write_err)?;
+
+        // symlinks are stored as their target, as they are within the payload
+        match entry.linkto {
+            Option::Some(ref target) => write(&path, target.as_bytes()).map_err(write_err)?,
+            Option::None => {
+                write(&path, &file.content).map_err(write_err)?;
+                set_permissions(&path, Permissions::from_mode((entry.mode & 0o7777) as u32))
+                    .map_err(write_err)?;
+            }
+        };
+
+        let opts = ComplexFileOptions {
+            dst: entry.path.clone(),
+            user: Option::Some(entry.user.clone()),
+            group: Option::Some(entry.group.clone()),
+            symlink: entry.linkto.clone(),
+            mode: Option::Some((entry.mode & 0o7777) as i32),
+            doc: Option::Some(true).filter(|_| entry.doc),
+            config: Option::Some(true).filter(|_| entry.config),
+            exclude: Option::None,
+        };
+        config.contents.insert(source, FileOptions::Complex(opts));
+    }
+    if dirs > 0 {
+        warnings.push(format!(
+            "{} directory entries are not owned by the config, only their files",
+            dirs
+        ));
+    }
+    Ok(())
+}
+
+/// writes every scriptlet to `dir/scripts`
+fn scripts(
+    inspection: &Inspection,
+    dir: &Path,
+    config: &mut ConfigFile,
+    warnings: &mut Vec<String>,
+    err: &Err,
+) -> Result<(), Err> {
+    if inspection.scripts.is_empty() {
+        return Ok(());
+    }
+    let scripts_dir = dir.join(SCRIPTS_DIR);
+    create_dir_all(&scripts_dir).map_err(|e| {
+        err.clone()
+            .note("failed to create directory", e)
+            .note("path", scripts_dir.display())
+    })?;
+
+    let mut output = Scripts::default();
+    for script in inspection.scripts.iter() {
+        let source = format!("{}/{}.sh", SCRIPTS_DIR, script.name);
+        let path = dir.join(&source);
+        write(&path, script.body.as_bytes()).map_err(|e| {
+            err.clone()
+                .note("failed to write script", e)
+                .note("path", path.display())
+        })?;
+        match script.program.as_ref().map(|p| p.as_str()) {
+            Option::None | Option::Some("/bin/sh") => {}
+            Option::Some(program) => warnings.push(format!(
+                "{} runs with '{}', it will run with /bin/sh",
+                script.name, program
+            )),
+        };
+        let slot = match script.name.as_str() {
+            "pre_install" => &mut output.pre_install,
+            "post_install" => &mut output.post_install,
+            "pre_uninstall" => &mut output.pre_uninstall,
+            _ => &mut output.post_uninstall,
+        };
+        *slot = Option::Some(source);
+    }
+    config.scripts = Option::Some(output);
+    Ok(())
+}
+
+fn changelog(inspection: &Inspection, config: &mut ConfigFile) {
+    for change in inspection.changelog.iter() {
+        let when = match DateTime::from_timestamp(change.time as i64, 0).map(|d| d.naive_utc()) {
+            Option::None => continue,
+            Option::Some(when) => when,
+        };
+        insert_entry(
+            &mut config.changelog,
+            when,
+            ChangeLogEntry {
+                author: change.author.clone(),
+                entry: change.text.clone(),
+            },
+        );
+    }
+}
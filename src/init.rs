@@ -0,0 +1,85 @@
+use std::os::unix::fs::PermissionsExt;
+use std::path::Path;
+
+use super::core::ConfigFile;
+use super::errors::Err;
+use super::fileopts::{relative, ComplexFileOptions, FileOptions};
+use super::glob::Pattern;
+use super::walkdir::WalkDir;
+
+/// scans a staged install root (the output of `make install DESTDIR=`)
+/// and creates a config with one `contents` entry per file.
+///
+/// The real on-disk mode is preserved. Files under `share/doc` or
+/// `share/man` are marked as documentation, and files under `etc` are
+/// marked as configuration.
+pub fn scan_root(root: &Path) -> Result<ConfigFile, Err> {
+    let err = Err::default().note("root", root.display());
+
+    let mut config = ConfigFile::default();
+    config.rpm.name = root
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+
+    for entry in WalkDir::new(root).sort_by(|a, b| a.file_name().cmp(b.file_name())) {
+        let entry = entry.map_err(|e| err.clone().note("failed to walk directory", e))?;
+        if entry.file_type().is_dir() {
+            continue;
+        }
+        let path = entry.path();
+        let rel = relative(root, path);
+        let metadata = entry.metadata().map_err(|e| {
+            err.clone()
+                .note("failed to read metadata", e)
+                .note("path", path.display())
+        })?;
+        let symlink = if entry.path_is_symlink() {
+            let target = path.read_link().map_err(|e| {
+                err.clone()
+                    .note("failed to read symlink", e)
+                    .note("path", path.display())
+            })?;
+            Option::Some(target.to_string_lossy().into_owned())
+        } else {
+            Option::None
+        };
+
+        let opts = ComplexFileOptions {
+            dst: format!("/{}", rel),
+            user: Option::Some("root".to_string()),
+            group: Option::Some("root".to_string()),
+            symlink,
+            mode: Option::Some((metadata.permissions().mode() & 0o7777) as i32),
+            doc: flag(is_doc(&rel)),
+            config: flag(is_config(&rel)),
+            exclude: Option::None,
+        };
+        // keys are read back as globs, so wild cards in names are escaped
+        config.contents.insert(
+            Pattern::escape(&path.to_string_lossy()),
+            FileOptions::Complex(opts),
+        );
+    }
+    Ok(config)
+}
+
+/// only emit the flags which are set, this keeps the generated config terse
+fn flag(value: bool) -> Option<bool> {
+    if value {
+        Option::Some(true)
+    } else {
+        Option::None
+    }
+}
+
+fn is_doc(rel: &str) -> bool {
+    let components = rel.split('/').collect::<Vec<&str>>();
+    components
+        .windows(2)
+        .any(|pair| pair[0] == "share" && (pair[1] == "doc" || pair[1] == "man"))
+}
+
+fn is_config(rel: &str) -> bool {
+    rel.starts_with("etc/")
+}
@@ -0,0 +1,419 @@
+use std::fmt;
+
+use super::chrono::DateTime;
+use super::errors::Err;
+use super::rpm::{Dependency, FileEntry, FileFlags, Package, Scriptlet};
+use super::serde::{Serialize, Serializer};
+
+/// Inspection is everything rpmbuilder can tell about a built package,
+/// as printed by `inspect`.
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct Inspection {
+    pub name: String,
+    pub epoch: Option<u32>,
+    pub version: String,
+    pub release: String,
+    pub arch: String,
+    pub license: String,
+    pub summary: String,
+    pub description: String,
+    pub build_time: Option<u64>,
+    pub build_host: Option<String>,
+    pub source_rpm: Option<String>,
+    pub payload_compressor: Option<String>,
+    pub requires: Vec<Dep>,
+    pub provides: Vec<Dep>,
+    pub conflicts: Vec<Dep>,
+    pub obsoletes: Vec<Dep>,
+    pub recommends: Vec<Dep>,
+    pub suggests: Vec<Dep>,
+    pub supplements: Vec<Dep>,
+    pub enhances: Vec<Dep>,
+    pub scripts: Vec<Script>,
+    pub changelog: Vec<Change>,
+    pub files: Vec<File>,
+}
+
+/// Dep is a single dependency, with its sense flags decoded
+#[derive(Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+pub struct Dep {
+    pub name: String,
+    pub op: Option<String>,
+    pub version: String,
+    pub flags: Vec<String>,
+}
+
+/// Script is an install or uninstall scriptlet
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize)]
+pub struct Script {
+    pub name: String,
+    pub program: Option<String>,
+    pub body: String,
+}
+
+/// Change is a single changelog entry
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize)]
+pub struct Change {
+    pub time: u64,
+    pub author: String,
+    pub text: String,
+}
+
+/// File is a single entry in the package's file list
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize)]
+pub struct File {
+    pub path: String,
+    #[serde(serialize_with = "octal")]
+    pub mode: u16,
+    pub user: String,
+    pub group: String,
+    pub size: u64,
+    pub mtime: u32,
+    pub digest: Option<String>,
+    pub linkto: Option<String>,
+    pub config: bool,
+    pub doc: bool,
+}
+
+impl Inspection {
+    /// parses the RPM at `path`
+    pub fn open(path: &str) -> Result<Inspection, Err> {
+        let err = Err::default().note("rpm", path);
+        let pkg = Package::open(path).map_err(|e| {
+            err.clone()
+                .note("failed to parse rpm", format_args!("{}", e))
+        })?;
+        Inspection::from_package(&pkg, &err)
+    }
+
+    /// reads the header of a parsed package
+    pub fn from_package(pkg: &Package, err: &Err) -> Result<Inspection, Err> {
+        let meta = &pkg.metadata;
+        let fail = |tag: &str, e: super::rpm::Error| {
+            err.clone()
+                .note("failed to read tag", tag)
+                .note("error", format_args!("{}", e))
+        };
+
+        let mut output = Inspection {
+            name: meta.get_name().map_err(|e| fail("name", e))?.to_string(),
+            epoch: meta.get_epoch().ok(),
+            version: meta
+                .get_version()
+                .map_err(|e| fail("version", e))?
+                .to_string(),
+            release: meta
+                .get_release()
+                .map_err(|e| fail("release", e))?
+                .to_string(),
+            arch: meta.get_arch().map_err(|e| fail("arch", e))?.to_string(),
+            license: meta.get_license().unwrap_or_default().to_string(),
+            summary: meta.get_summary().unwrap_or_default().to_string(),
+            description: meta.get_description().unwrap_or_default().to_string(),
+            build_time: meta.get_build_time().ok(),
+            build_host: meta.get_build_host().ok().map(|s| s.to_string()),
+            source_rpm: meta.get_source_rpm().ok().map(|s| s.to_string()),
+            payload_compressor: meta
+                .get_payload_compressor()
+                .ok()
+                .map(|c| format!("{:?}", c).to_lowercase()),
+            requires: deps(meta.get_requires()).map_err(|e| fail("requires", e))?,
+            provides: deps(meta.get_provides()).map_err(|e| fail("provides", e))?,
+            conflicts: deps(meta.get_conflicts()).map_err(|e| fail("conflicts", e))?,
+            obsoletes: deps(meta.get_obsoletes()).map_err(|e| fail("obsoletes", e))?,
+            recommends: deps(meta.get_recommends()).map_err(|e| fail("recommends", e))?,
+            suggests: deps(meta.get_suggests()).map_err(|e| fail("suggests", e))?,
+            supplements: deps(meta.get_supplements()).map_err(|e| fail("supplements", e))?,
+            enhances: deps(meta.get_enhances()).map_err(|e| fail("enhances", e))?,
+            ..Inspection::default()
+        };
+
+        let scripts = [
+            ("pre_install", meta.get_pre_install_script()),
+            ("post_install", meta.get_post_install_script()),
+            ("pre_uninstall", meta.get_pre_uninstall_script()),
+            ("post_uninstall", meta.get_post_uninstall_script()),
+        ];
+        for (name, script) in scripts.iter() {
+            if let &Ok(ref script) = script {
+                output.scripts.push(Script::new(name, script));
+            }
+        }
+
+        output.changelog = meta
+            .get_changelog_entries()
+            .unwrap_or_default()
+            .into_iter()
+            .map(|entry| Change {
+                time: entry.timestamp,
+                author: entry.name,
+                text: entry.description,
+            })
+            .collect();
+
+        output.files = meta
+            .get_file_entries()
+            .map_err(|e| fail("files", e))?
+            .into_iter()
+            .map(File::from_entry)
+            .collect();
+
+        Ok(output)
+    }
+
+    /// every dependency table, with the name used by `ConfigFile`
+    pub fn dependency_tables(&self) -> Vec<(&'static str, &[Dep])> {
+        vec![
+            ("requires", &self.requires[..]),
+            ("provides", &self.provides[..]),
+            ("conflicts", &self.conflicts[..]),
+            ("obsoletes", &self.obsoletes[..]),
+            ("recommends", &self.recommends[..]),
+            ("suggests", &self.suggests[..]),
+            ("supplements", &self.supplements[..]),
+            ("enhances", &self.enhances[..]),
+        ]
+    }
+
+    /// the `[epoch:]version-release` of the package
+    pub fn evr(&self) -> String {
+        match self.epoch {
+            Option::None => format!("{}-{}", self.version, self.release),
+            Option::Some(epoch) => format!("{}:{}-{}", epoch, self.version, self.release),
+        }
+    }
+}
+
+impl fmt::Display for Inspection {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let none = "(none)".to_string();
+        writeln!(f, "Name        : {}", self.name)?;
+        match self.epoch {
+            Option::None => writeln!(f, "Epoch       : (none)")?,
+            Option::Some(epoch) => writeln!(f, "Epoch       : {}", epoch)?,
+        };
+        writeln!(f, "Version     : {}", self.version)?;
+        writeln!(f, "Release     : {}", self.release)?;
+        writeln!(f, "Architecture: {}", self.arch)?;
+        writeln!(f, "License     : {}", self.license)?;
+        match self.build_time {
+            Option::None => writeln!(f, "Build Date  : (none)")?,
+            Option::Some(time) => writeln!(f, "Build Date  : {}", date(time))?,
+        };
+        writeln!(
+            f,
+            "Build Host  : {}",
+            self.build_host.as_ref().unwrap_or(&none)
+        )?;
+        writeln!(
+            f,
+            "Source RPM  : {}",
+            self.source_rpm.as_ref().unwrap_or(&none)
+        )?;
+        writeln!(
+            f,
+            "Compression : {}",
+            self.payload_compressor.as_ref().unwrap_or(&none)
+        )?;
+        writeln!(f, "Summary     : {}", self.summary)?;
+        writeln!(f, "Description :\n{}", self.description)?;
+
+        for (table, deps) in self.dependency_tables() {
+            if deps.is_empty() {
+                continue;
+            }
+            writeln!(f, "\n{}:", table)?;
+            for dep in deps {
+                writeln!(f, "  {}", dep)?;
+            }
+        }
+
+        for script in self.scripts.iter() {
+            writeln!(
+                f,
+                "\n{} ({}):",
+                script.name,
+                script.program.as_ref().unwrap_or(&none)
+            )?;
+            for line in script.body.lines() {
+                writeln!(f, "  {}", line)?;
+            }
+        }
+
+        if !self.changelog.is_empty() {
+            writeln!(f, "\nchangelog:")?;
+            for change in self.changelog.iter() {
+                writeln!(f, "  * {} {}", date(change.time), change.author)?;
+                for line in change.text.lines() {
+                    writeln!(f, "    {}", line)?;
+                }
+            }
+        }
+
+        if !self.files.is_empty() {
+            writeln!(f, "\nfiles:")?;
+            for file in self.files.iter() {
+                writeln!(f, "  {}", file)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Dep {
+    pub fn from_dependency(dep: Dependency) -> Dep {
+        const LESS: u32 = 1 << 1;
+        const GREATER: u32 = 1 << 2;
+        const EQUAL: u32 = 1 << 3;
+        const QUALIFIERS: &[(u32, &str)] = &[
+            (1 << 6, "prereq"),
+            (1 << 8, "interp"),
+            (1 << 9, "pre"),
+            (1 << 10, "post"),
+            (1 << 11, "preun"),
+            (1 << 12, "postun"),
+            (1 << 13, "verify"),
+            (1 << 19, "missingok"),
+            (1 << 24, "rpmlib"),
+            (1 << 26, "keyring"),
+            (1 << 28, "config"),
+        ];
+
+        let bits = dep.flags.bits();
+        let op = match (bits & LESS != 0, bits & GREATER != 0, bits & EQUAL != 0) {
+            (true, false, false) => Option::Some("<"),
+            (true, false, true) => Option::Some("<="),
+            (false, true, false) => Option::Some(">"),
+            (false, true, true) => Option::Some(">="),
+            (false, false, true) => Option::Some("="),
+            _ => Option::None,
+        };
+        Dep {
+            name: dep.name,
+            op: op.map(|op| op.to_string()),
+            version: dep.version,
+            flags: QUALIFIERS
+                .iter()
+                .filter(|(bit, _)| bits & bit != 0)
+                .map(|(_, name)| name.to_string())
+                .collect(),
+        }
+    }
+}
+
+impl fmt::Display for Dep {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.name)?;
+        if let Option::Some(ref op) = self.op {
+            write!(f, " {} {}", op, self.version)?;
+        }
+        if !self.flags.is_empty() {
+            write!(f, " ({})", self.flags.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+impl Script {
+    fn new(name: &str, script: &Scriptlet) -> Script {
+        Script {
+            name: name.to_string(),
+            program: script.program.as_ref().map(|args| args.join(" ")),
+            body: script.script.clone(),
+        }
+    }
+}
+
+impl File {
+    pub fn from_entry(entry: FileEntry) -> File {
+        File {
+            path: entry.path.to_string_lossy().into_owned(),
+            mode: entry.mode.raw_mode(),
+            user: entry.ownership.user,
+            group: entry.ownership.group,
+            size: entry.size as u64,
+            mtime: u32::from(entry.modified_at),
+            digest: entry.digest.map(|d| d.digest),
+            linkto: Option::Some(entry.linkto).filter(|l| !l.is_empty()),
+            config: entry.flags.contains(FileFlags::CONFIGURATION),
+            doc: entry.flags.contains(FileFlags::DOC),
+        }
+    }
+
+    pub fn is_dir(&self) -> bool {
+        self.mode & 0o170000 == 0o040000
+    }
+}
+
+impl fmt::Display for File {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} {} {} {:>10} {} {}",
+            mode_string(self.mode),
+            self.user,
+            self.group,
+            self.size,
+            self.digest.as_ref().map(|d| d.as_str()).unwrap_or("-"),
+            self.path
+        )?;
+        if let Option::Some(ref linkto) = self.linkto {
+            write!(f, " -> {}", linkto)?;
+        }
+        if self.config {
+            write!(f, " (config)")?;
+        }
+        if self.doc {
+            write!(f, " (doc)")?;
+        }
+        Ok(())
+    }
+}
+
+fn deps(result: Result<Vec<Dependency>, super::rpm::Error>) -> Result<Vec<Dep>, super::rpm::Error> {
+    result.map(|deps| deps.into_iter().map(Dep::from_dependency).collect())
+}
+
+/// renders a mode as `ls -l` would, for example `-rwxr-xr-x`
+pub fn mode_string(mode: u16) -> String {
+    let kind = match mode & 0o170000 {
+        0o040000 => 'd',
+        0o120000 => 'l',
+        0o020000 => 'c',
+        0o060000 => 'b',
+        0o010000 => 'p',
+        0o140000 => 's',
+        _ => '-',
+    };
+    let mut output = String::with_capacity(10);
+    output.push(kind);
+    for shift in [6, 3, 0].iter() {
+        let bits = (mode >> shift) & 0o7;
+        output.push(if bits & 0o4 != 0 { 'r' } else { '-' });
+        output.push(if bits & 0o2 != 0 { 'w' } else { '-' });
+        output.push(if bits & 0o1 != 0 { 'x' } else { '-' });
+    }
+    let special = [(0o4000, 3, 's'), (0o2000, 6, 's'), (0o1000, 9, 't')];
+    let mut chars: Vec<char> = output.chars().collect();
+    for &(bit, index, c) in special.iter() {
+        if mode & bit != 0 {
+            chars[index] = if chars[index] == 'x' {
+                c
+            } else {
+                c.to_ascii_uppercase()
+            };
+        }
+    }
+    chars.into_iter().collect()
+}
+
+pub fn date(time: u64) -> String {
+    match DateTime::from_timestamp(time as i64, 0).map(|d| d.naive_utc()) {
+        Option::None => time.to_string(),
+        Option::Some(when) => when.format("%a %b %d %Y").to_string(),
+    }
+}
+
+fn octal<S: Serializer>(mode: &u16, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(&format!("{:o}", mode))
+}
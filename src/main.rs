@@ -5,17 +5,44 @@ extern crate serde;
 extern crate chrono;
 extern crate clap;
 extern crate errors;
-extern crate libflate;
+extern crate flate2;
+extern crate glob;
+extern crate goblin;
 extern crate rpm;
+extern crate serde_json;
+extern crate sha2;
+extern crate tempfile;
 extern crate toml;
+extern crate walkdir;
+extern crate xz2;
+extern crate zstd;
 
+mod ar;
 mod changelog;
+mod checkdeps;
 mod cli;
 mod core;
+mod cpio;
+mod deb;
+mod diff;
+mod elfdeps;
+mod extract;
 mod fileopts;
+mod header;
+mod import;
+mod init;
+mod inspect;
+mod repo;
 mod rpm_meta;
 mod scripts;
 mod sign;
+mod simulate;
+mod spec;
+mod srpm;
+mod stream;
+mod subpackage;
+mod tar;
+mod vercmp;
 mod versions;
 use self::cli::{cli_build, AppWork};
 
@@ -0,0 +1,745 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs::{create_dir_all, metadata, read, read_to_string, remove_file, write};
+use std::io::{Read, Write};
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use super::errors::Err;
+use super::flate2::read::GzDecoder;
+use super::flate2::write::GzEncoder;
+use super::flate2::Compression as GzLevel;
+use super::rpm::Package;
+use super::sha2::{Digest, Sha256};
+use super::walkdir::WalkDir;
+
+use super::fileopts::relative;
+use super::header::header_range;
+use super::inspect::{Dep, Inspection};
+use super::stream::hex;
+
+/// the directory, within a repository, its metadata is written to
+pub const REPODATA_DIR: &str = "repodata";
+
+const COMMON_NS: &str = "http://linux.duke.edu/metadata/common";
+const FILELISTS_NS: &str = "http://linux.duke.edu/metadata/filelists";
+const OTHER_NS: &str = "http://linux.duke.edu/metadata/other";
+const REPO_NS: &str = "http://linux.duke.edu/metadata/repo";
+const RPM_NS: &str = "http://linux.duke.edu/metadata/rpm";
+
+/// the metadata files, in the order `repomd.xml` lists them
+const KINDS: &[&str] = &["primary", "filelists", "other"];
+
+/// Entry is a single package of a repository, holding its `<package>`
+/// element from each metadata file.
+#[derive(Clone, Debug, Default)]
+pub struct Entry {
+    pub pkgid: String,
+    pub href: String,
+    pub size: u64,
+    pub mtime: u64,
+    pub primary: String,
+    pub filelists: String,
+    pub other: String,
+}
+
+/// scans `dir` for rpms and writes the `repodata` describing them, as
+/// `createrepo_c` would.
+///
+/// With `update` the entries of the existing repodata are reused for every
+/// package whose path, size and mtime are unchanged and whose sha256 is
+/// still the recorded pkgid, rather than parsing the package again.
+/// Returns the number of packages, and how many of them were reused.
+pub fn write_repo(dir: &Path, update: bool) -> Result<(usize, usize), Err> {
+    let err = Err::default().note("repo", dir.display());
+    let repodata = dir.join(REPODATA_DIR);
+
+    let old_files = match read_repomd(dir, &err)? {
+        Option::None => BTreeMap::new(),
+        Option::Some(files) => files,
+    };
+    let mut old_entries = BTreeMap::new();
+    if update && !old_files.is_empty() {
+        for entry in read_entries(dir, &old_files, &err)? {
+            old_entries.insert(entry.href.clone(), entry);
+        }
+    }
+
+    let mut entries = Vec::new();
+    let mut reused = 0;
+    for path in find_rpms(dir, &err)? {
+        let href = relative(dir, &path);
+        let (size, mtime) = size_and_mtime(&path, &err)?;
+        let old = old_entries
+            .remove(&href)
+            .filter(|entry| entry.size == size && entry.mtime == mtime);
+        match old {
+            Option::Some(entry) if entry.pkgid == checksum(&path, &err)? => {
+                reused += 1;
+                entries.push(entry);
+            }
+            _ => entries.push(read_entry(&path, href, size, mtime, &err)?),
+        };
+    }
+
+    create_dir_all(&repodata).map_err(|e| {
+        err.clone()
+            .note("failed to create directory", e)
+            .note("path", repodata.display())
+    })?;
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    let mut repomd = format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<repomd xmlns=\"{}\" xmlns:rpm=\"{}\">\n  <revision>{}</revision>\n",
+        REPO_NS, RPM_NS, timestamp
+    );
+    let mut written = BTreeSet::new();
+    for kind in KINDS.iter() {
+        let xml = document(kind, &entries);
+        let mut encoder = GzEncoder::new(Vec::new(), GzLevel::default());
+        encoder
+            .write_all(xml.as_bytes())
+            .map_err(|e| err.clone().note("failed to compress metadata", e))?;
+        let compressed = encoder
+            .finish()
+            .map_err(|e| err.clone().note("failed to compress metadata", e))?;
+
+        let checksum = hex(&Sha256::digest(&compressed));
+        let href = format!("{}/{}-{}.xml.gz", REPODATA_DIR, checksum, kind);
+        let path = dir.join(&href);
+        write(&path, &compressed).map_err(|e| {
+            err.clone()
+                .note("failed to write metadata", e)
+                .note("path", path.display())
+        })?;
+        repomd.push_str(&format!(
+            "  <data type=\"{}\">\n    <checksum type=\"sha256\">{}</checksum>\n    <open-checksum type=\"sha256\">{}</open-checksum>\n    <location href=\"{}\"/>\n    <timestamp>{}</timestamp>\n    <size>{}</size>\n    <open-size>{}</open-size>\n  </data>\n",
+            kind,
+            checksum,
+            hex(&Sha256::digest(xml.as_bytes())),
+            escape(&href),
+            timestamp,
+            compressed.len(),
+            xml.len()
+        ));
+        written.insert(href);
+    }
+    repomd.push_str("</repomd>\n");
+
+    let repomd_path = repodata.join("repomd.xml");
+    write(&repomd_path, repomd.as_bytes()).map_err(|e| {
+        err.clone()
+            .note("failed to write metadata", e)
+            .note("path", repomd_path.display())
+    })?;
+
+    // the metadata replaced by this run is no longer referenced
+    for href in old_files.values().filter(|href| !written.contains(*href)) {
+        let _ = remove_file(dir.join(href));
+    }
+    Ok((entries.len(), reused))
+}
+
+/// reads every entry of the existing repodata of `dir`, `None` when it has
+/// none
+pub fn read_repo(dir: &Path) -> Result<Option<Vec<Entry>>, Err> {
+    let err = Err::default().note("repo", dir.display());
+    match read_repomd(dir, &err)? {
+        Option::None => Ok(Option::None),
+        Option::Some(files) => read_entries(dir, &files, &err).map(Option::Some),
+    }
+}
+
+/// every rpm beneath `dir`, other than source rpms, in a stable order
+pub fn find_rpms(dir: &Path, err: &Err) -> Result<Vec<std::path::PathBuf>, Err> {
+    let mut output = Vec::new();
+    let walker = WalkDir::new(dir)
+        .sort_by(|a, b| a.file_name().cmp(b.file_name()))
+        .into_iter()
+        .filter_entry(|entry| entry.file_name() != REPODATA_DIR);
+    for entry in walker {
+        let entry = entry.map_err(|e| err.clone().note("failed to walk directory", e))?;
+        let name = entry.file_name().to_string_lossy();
+        if entry.file_type().is_file() && name.ends_with(".rpm") && !name.ends_with(".src.rpm") {
+            output.push(entry.into_path());
+        }
+    }
+    Ok(output)
+}
+
+fn size_and_mtime(path: &Path, err: &Err) -> Result<(u64, u64), Err> {
+    let meta = metadata(path).map_err(|e| {
+        err.clone()
+            .note("failed to stat rpm", e)
+            .note("path", path.display())
+    })?;
+    let mtime = meta
+        .modified()
+        .ok()
+        .and_then(|m| m.duration_since(UNIX_EPOCH).ok())
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    Ok((meta.len(), mtime))
+}
+
+/// the sha256 of the rpm at `path`, which `createrepo_c` records as its pkgid
+fn checksum(path: &Path, err: &Err) -> Result<String, Err> {
+    let data = read(path).map_err(|e| {
+        err.clone()
+            .note("failed to read rpm", e)
+            .note("path", path.display())
+    })?;
+    Ok(hex(&Sha256::digest(&data)))
+}
+
+fn read_entry(path: &Path, href: String, size: u64, mtime: u64, err: &Err) -> Result<Entry, Err> {
+    let err = err.clone().note("rpm", path.display());
+    let data = read(path).map_err(|e| err.clone().note("failed to read rpm", e))?;
+    let (start, end) =
+        header_range(&data).ok_or_else(|| err.clone().note("error", "not an rpm"))?;
+    let pkg = Package::parse(&mut &data[..]).map_err(|e| {
+        err.clone()
+            .note("failed to parse rpm", format_args!("{}", e))
+    })?;
+    let inspection = Inspection::from_package(&pkg, &err)?;
+
+    let pkgid = hex(&Sha256::digest(&data));
+    Ok(Entry {
+        primary: primary(&inspection, &pkgid, &href, size, mtime, (start, end)),
+        filelists: filelists(&inspection, &pkgid),
+        other: other(&inspection, &pkgid),
+        pkgid,
+        href,
+        size,
+        mtime,
+    })
+}
+
+/*
+ * writing
+ *
+ */
+
+fn document(kind: &str, entries: &[Entry]) -> String {
+    let mut output = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    match kind {
+        "primary" => output.push_str(&format!(
+            "<metadata xmlns=\"{}\" xmlns:rpm=\"{}\" packages=\"{}\">\n",
+            COMMON_NS,
+            RPM_NS,
+            entries.len()
+        )),
+        "filelists" => output.push_str(&format!(
+            "<filelists xmlns=\"{}\" packages=\"{}\">\n",
+            FILELISTS_NS,
+            entries.len()
+        )),
+        _ => output.push_str(&format!(
+            "<otherdata xmlns=\"{}\" packages=\"{}\">\n",
+            OTHER_NS,
+            entries.len()
+        )),
+    };
+    for entry in entries {
+        let fragment = match kind {
+            "primary" => &entry.primary,
+            "filelists" => &entry.filelists,
+            _ => &entry.other,
+        };
+        output.push_str(fragment);
+        output.push('\n');
+    }
+    output.push_str(match kind {
+        "primary" => "</metadata>\n",
+        "filelists" => "</filelists>\n",
+        _ => "</otherdata>\n",
+    });
+    output
+}
+
+fn primary(
+    pkg: &Inspection,
+    pkgid: &str,
+    href: &str,
+    size: u64,
+    mtime: u64,
+    (start, end): (u64, u64),
+) -> String {
+    let installed: u64 = pkg
+        .files
+        .iter()
+        .filter(|f| !f.is_dir())
+        .map(|f| f.size)
+        .sum();
+    let mut output = String::from("<package type=\"rpm\">\n");
+    output.push_str(&format!("  <name>{}</name>\n", escape(&pkg.name)));
+    output.push_str(&format!("  <arch>{}</arch>\n", escape(&pkg.arch)));
+    output.push_str(&format!("  {}\n", version(pkg)));
+    output.push_str(&format!(
+        "  <checksum type=\"sha256\" pkgid=\"YES\">{}</checksum>\n",
+        pkgid
+    ));
+    output.push_str(&format!("  <summary>{}</summary>\n", escape(&pkg.summary)));
+    output.push_str(&format!(
+        "  <description>{}</description>\n",
+        escape(&pkg.description)
+    ));
+    output.push_str("  <packager/>\n  <url/>\n");
+    output.push_str(&format!(
+        "  <time file=\"{}\" build=\"{}\"/>\n",
+        mtime,
+        pkg.build_time.unwrap_or(0)
+    ));
+    output.push_str(&format!(
+        "  <size package=\"{}\" installed=\"{}\"/>\n",
+        size, installed
+    ));
+    output.push_str(&format!("  <location href=\"{}\"/>\n", escape(href)));
+    output.push_str("  <format>\n");
+    output.push_str(&format!(
+        "    <rpm:license>{}</rpm:license>\n",
+        escape(&pkg.license)
+    ));
+    output.push_str("    <rpm:vendor/>\n    <rpm:group/>\n");
+    output.push_str(&format!(
+        "    <rpm:buildhost>{}</rpm:buildhost>\n",
+        escape(pkg.build_host.as_ref().map(|h| h.as_str()).unwrap_or(""))
+    ));
+    output.push_str(&format!(
+        "    <rpm:sourcerpm>{}</rpm:sourcerpm>\n",
+        escape(pkg.source_rpm.as_ref().map(|s| s.as_str()).unwrap_or(""))
+    ));
+    output.push_str(&format!(
+        "    <rpm:header-range start=\"{}\" end=\"{}\"/>\n",
+        start, end
+    ));
+    for (table, deps) in pkg.dependency_tables() {
+        let deps: Vec<&Dep> = deps
+            .iter()
+            .filter(|d| !d.flags.iter().any(|f| f == "rpmlib"))
+            .collect();
+        if deps.is_empty() {
+            continue;
+        }
+        output.push_str(&format!("    <rpm:{}>\n", table));
+        for dep in deps {
+            output.push_str(&format!("      {}\n", dependency(table, dep)));
+        }
+        output.push_str(&format!("    </rpm:{}>\n", table));
+    }
+    // only the files most often depended upon are listed within primary
+    for file in pkg.files.iter().filter(|f| primary_file(&f.path)) {
+        let kind = if file.is_dir() { " type=\"dir\"" } else { "" };
+        output.push_str(&format!(
+            "    <file{}>{}</file>\n",
+            kind,
+            escape(&file.path)
+        ));
+    }
+    output.push_str("  </format>\n</package>");
+    output
+}
+
+fn filelists(pkg: &Inspection, pkgid: &str) -> String {
+    let mut output = format!(
+        "<package pkgid=\"{}\" name=\"{}\" arch=\"{}\">\n  {}\n",
+        pkgid,
+        escape(&pkg.name),
+        escape(&pkg.arch),
+        version(pkg)
+    );
+    for file in pkg.files.iter() {
+        let kind = if file.is_dir() { " type=\"dir\"" } else { "" };
+        output.push_str(&format!("  <file{}>{}</file>\n", kind, escape(&file.path)));
+    }
+    output.push_str("</package>");
+    output
+}
+
+fn other(pkg: &Inspection, pkgid: &str) -> String {
+    let mut output = format!(
+        "<package pkgid=\"{}\" name=\"{}\" arch=\"{}\">\n  {}\n",
+        pkgid,
+        escape(&pkg.name),
+        escape(&pkg.arch),
+        version(pkg)
+    );
+    for change in pkg.changelog.iter() {
+        output.push_str(&format!(
+            "  <changelog author=\"{}\" date=\"{}\">{}</changelog>\n",
+            escape(&change.author),
+            change.time,
+            escape(&change.text)
+        ));
+    }
+    output.push_str("</package>");
+    output
+}
+
+fn version(pkg: &Inspection) -> String {
+    format!(
+        "<version epoch=\"{}\" ver=\"{}\" rel=\"{}\"/>",
+        pkg.epoch.unwrap_or(0),
+        escape(&pkg.version),
+        escape(&pkg.release)
+    )
+}
+
+fn dependency(table: &str, dep: &Dep) -> String {
+    let mut output = format!("<rpm:entry name=\"{}\"", escape(&dep.name));
+    if let Option::Some(ref op) = dep.op {
+        let flags = match op.as_str() {
+            "<" => "LT",
+            "<=" => "LE",
+            ">" => "GT",
+            ">=" => "GE",
+            _ => "EQ",
+        };
+        let (epoch, version, release) = split_evr(&dep.version);
+        output.push_str(&format!(
+            " flags=\"{}\" epoch=\"{}\" ver=\"{}\"",
+            flags,
+            escape(epoch.unwrap_or("0")),
+            escape(version)
+        ));
+        if let Option::Some(release) = release {
+            output.push_str(&format!(" rel=\"{}\"", escape(release)));
+        }
+    }
+    let pre = ["prereq", "pre", "post"];
+    if table == "requires" && dep.flags.iter().any(|f| pre.contains(&f.as_str())) {
+        output.push_str(" pre=\"1\"");
+    }
+    output.push_str("/>");
+    output
+}
+
+/// splits `[epoch:]version[-release]`
+pub fn split_evr(evr: &str) -> (Option<&str>, &str, Option<&str>) {
+    let (epoch, rest) = match evr.find(':') {
+        Option::Some(idx) => (Option::Some(&evr[..idx]), &evr[idx + 1..]),
+        Option::None => (Option::None, evr),
+    };
+    match rest.rfind('-') {
+        Option::Some(idx) => (epoch, &rest[..idx], Option::Some(&rest[idx + 1..])),
+        Option::None => (epoch, rest, Option::None),
+    }
+}
+
+/// the files `createrepo_c` lists within primary, rather than only within
+/// filelists
+fn primary_file(path: &str) -> bool {
+    path.starts_with("/etc/") || path.contains("bin/") || path == "/usr/lib/sendmail"
+}
+
+fn escape(text: &str) -> String {
+    let mut output = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => output.push_str("&amp;"),
+            '<' => output.push_str("&lt;"),
+            '>' => output.push_str("&gt;"),
+            '"' => output.push_str("&quot;"),
+            '\'' => output.push_str("&apos;"),
+            c => output.push(c),
+        };
+    }
+    output
+}
+
+/*
+ * reading
+ *
+ */
+
+/// the location of each metadata file listed by `repomd.xml`
+fn read_repomd(dir: &Path, err: &Err) -> Result<Option<BTreeMap<String, String>>, Err> {
+    let path = dir.join(REPODATA_DIR).join("repomd.xml");
+    if !path.exists() {
+        return Ok(Option::None);
+    }
+    let data = read_to_string(&path).map_err(|e| {
+        err.clone()
+            .note("failed to read metadata", e)
+            .note("path", path.display())
+    })?;
+    let mut files = BTreeMap::new();
+    for data in elements(&data, "data") {
+        if let (Option::Some(kind), Option::Some(href)) = (
+            attribute(data, "data", "type"),
+            attribute(data, "location", "href"),
+        ) {
+            files.insert(kind, href);
+        }
+    }
+    Ok(Option::Some(files))
+}
+
+/// splits the existing metadata back into its entries, the filelists and
+/// other elements are matched to primary by their pkgid
+fn read_entries(
+    dir: &Path,
+    files: &BTreeMap<String, String>,
+    err: &Err,
+) -> Result<Vec<Entry>, Err> {
+    let mut documents = BTreeMap::new();
+    for kind in KINDS.iter() {
+        let href = files
+            .get(*kind)
+            .ok_or_else(|| err.clone().note("repomd.xml does not list", kind))?;
+        documents.insert(*kind, read_gz(&dir.join(href), err)?);
+    }
+    let by_pkgid = |kind: &str| -> BTreeMap<String, String> {
+        elements(&documents[kind], "package")
+            .into_iter()
+            .filter_map(|p| Option::Some((attribute(p, "package", "pkgid")?, p.to_string())))
+            .collect()
+    };
+    let mut filelists = by_pkgid("filelists");
+    let mut other = by_pkgid("other");
+
+    let mut entries = Vec::new();
+    for package in elements(&documents["primary"], "package") {
+        let pkgid = match text(package, "checksum") {
+            Option::Some(pkgid) => pkgid,
+            Option::None => continue,
+        };
+        let number = |element: &str, name: &str| -> u64 {
+            attribute(package, element, name)
+                .and_then(|n| n.parse().ok())
+                .unwrap_or(0)
+        };
+        entries.push(Entry {
+            href: attribute(package, "location", "href").unwrap_or_default(),
+            size: number("size", "package"),
+            mtime: number("time", "file"),
+            primary: package.to_string(),
+            filelists: filelists.remove(&pkgid).unwrap_or_default(),
+            other: other.remove(&pkgid).unwrap_or_default(),
+            pkgid,
+        });
+    }
+    Ok(entries)
+}
+
+fn read_gz(path: &Path, err: &Err) -> Result<String, Err> {
+    let data = read(path).map_err(|e| {
+        err.clone()
+            .note("failed to read metadata", e)
+            .note("path", path.display())
+    })?;
+    let mut output = String::new();
+    GzDecoder::new(&data[..])
+        .read_to_string(&mut output)
+        .map_err(|e| {
+            err.clone()
+                .note("failed to decompress metadata", e)
+                .note("path", path.display())
+        })?;
+    Ok(output)
+}
+
+/// every element named `name` within `xml`, from its start tag up to, and
+/// including, its end tag, or only the tag when it closes itself
+pub fn elements<'a>(xml: &'a str, name: &str) -> Vec<&'a str> {
+    let close = format!("</{}>", name);
+    let mut output = Vec::new();
+    let mut rest = xml;
+    while let Option::Some((start, tag_end)) = start_tag(rest, name) {
+        let end = if rest[..tag_end].ends_with("/>") {
+            tag_end
+        } else {
+            match rest[tag_end..].find(&close) {
+                Option::Some(end) => tag_end + end + close.len(),
+                Option::None => break,
+            }
+        };
+        output.push(&rest[start..end]);
+        rest = &rest[end..];
+    }
+    output
+}
+
+/// the value of attribute `attr` on the first element named `name` within
+/// `xml`, whatever the order or quoting of its attributes
+pub fn attribute(xml: &str, name: &str, attr: &str) -> Option<String> {
+    let (start, end) = start_tag(xml, name)?;
+    let tag = xml[start + 1 + name.len()..end]
+        .trim_end_matches('>')
+        .trim_end_matches('/');
+    let mut rest = tag.trim_start();
+    while !rest.is_empty() {
+        let eq = rest.find('=')?;
+        let key = rest[..eq].trim();
+        let value = rest[eq + 1..].trim_start();
+        let quote = value.chars().next().filter(|q| *q == '"' || *q == '\'')?;
+        let value_end = value[1..].find(quote)? + 1;
+        if key == attr {
+            return Option::Some(unescape(&value[1..value_end]));
+        }
+        rest = value[value_end + 1..].trim_start();
+    }
+    Option::None
+}
+
+/// the text within the first element named `name` of `xml`, empty when it
+/// closes itself
+pub fn text(xml: &str, name: &str) -> Option<String> {
+    let (_, tag_end) = start_tag(xml, name)?;
+    if xml[..tag_end].ends_with("/>") {
+        return Option::Some(String::new());
+    }
+    let body_end = tag_end + xml[tag_end..].find(&format!("</{}>", name))?;
+    Option::Some(unescape(&xml[tag_end..body_end]))
+}
+
+/// the start, and the end past its `>`, of the first start tag named `name`
+/// within `xml`. Tags which only share a prefix with `name` are skipped and
+/// a `>` quoted within an attribute does not end the tag.
+fn start_tag(xml: &str, name: &str) -> Option<(usize, usize)> {
+    let open = format!("<{}", name);
+    let mut from = 0;
+    loop {
+        let start = from + xml[from..].find(&open)?;
+        let after = start + open.len();
+        from = after;
+        match xml[after..].chars().next() {
+            Option::Some(c) if c.is_whitespace() || c == '>' || c == '/' => {}
+            _ => continue,
+        };
+        let mut quote = Option::None;
+        for (i, c) in xml[after..].char_indices() {
+            match (quote, c) {
+                (Option::None, '"') | (Option::None, '\'') => quote = Option::Some(c),
+                (Option::None, '>') => return Option::Some((start, after + i + 1)),
+                (Option::Some(q), c) if q == c => quote = Option::None,
+                _ => {}
+            };
+        }
+        return Option::None;
+    }
+}
+
+fn unescape(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::OpenOptions;
+    use std::time::Duration;
+
+    const FIXTURE: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/createrepo_c");
+    const HELLO: &str = "3b7c6e1a0b5f1c2e9d8f7a6b5c4d3e2f1a0b9c8d7e6f5a4b3c2d1e0f9a8b7c6d";
+
+    /// copies the repodata `createrepo_c` wrote into `dir`, with `edit`
+    /// applied to primary
+    fn repodata(dir: &Path, edit: impl Fn(String) -> String) {
+        create_dir_all(dir.join(REPODATA_DIR)).unwrap();
+        let fixture = Path::new(FIXTURE);
+        let repomd = read(fixture.join("repomd.xml")).unwrap();
+        write(dir.join(REPODATA_DIR).join("repomd.xml"), repomd).unwrap();
+        for kind in KINDS.iter() {
+            let mut xml = read_to_string(fixture.join(format!("{}.xml", kind))).unwrap();
+            if *kind == "primary" {
+                xml = edit(xml);
+            }
+            let mut encoder = GzEncoder::new(Vec::new(), GzLevel::default());
+            encoder.write_all(xml.as_bytes()).unwrap();
+            let path = dir.join(REPODATA_DIR).join(format!("{}.xml.gz", kind));
+            write(path, encoder.finish().unwrap()).unwrap();
+        }
+    }
+
+    #[test]
+    fn reads_createrepo_c_metadata() {
+        let dir = tempfile::tempdir().unwrap();
+        repodata(dir.path(), |xml| xml);
+        let entries = read_repo(dir.path()).unwrap().unwrap();
+        assert_eq!(entries.len(), 2);
+
+        let hello = &entries[0];
+        assert_eq!(hello.pkgid, HELLO);
+        assert_eq!(hello.href, "Packages/h/hello-2.10-3.fc38.x86_64.rpm");
+        assert_eq!(hello.size, 77425);
+        assert_eq!(hello.mtime, 1690000000);
+        assert!(hello.primary.starts_with("<package type=\"rpm\">"));
+        assert!(hello.primary.ends_with("</package>"));
+        assert!(hello.filelists.contains("/usr/share/doc/hello/NEWS"));
+        assert!(hello.other.contains("Rebuilt for Fedora 38"));
+        assert_eq!(
+            text(&hello.primary, "description").unwrap(),
+            "The GNU Hello program produces a familiar, friendly greeting.\n\
+             It allows non-programmers to use a classic computer science tool & more."
+        );
+        assert_eq!(
+            attribute(&hello.primary, "version", "rel").unwrap(),
+            "3.fc38"
+        );
+
+        let docs = &entries[1];
+        assert_eq!(docs.href, "Packages/h/hello-docs-2.10-3.fc38.noarch.rpm");
+        assert_eq!(text(&docs.primary, "description").unwrap(), "");
+        assert!(docs.filelists.ends_with("</package>"));
+    }
+
+    #[test]
+    fn attributes_in_any_order() {
+        let xml = "<root>\n\
+                   <packager>someone</packager>\n\
+                   <package\n    arch='noarch' pkgid=\"a&amp;b\"\n    name=\"x&gt;y\"/>\n\
+                   <package name=\"empty\" pkgid=\"c\"></package>\n\
+                   <location xml:base=\"http://example.com/a>b\" href=\"x.rpm\" />\n\
+                   </root>";
+        let packages = elements(xml, "package");
+        assert_eq!(packages.len(), 2);
+        assert!(packages[0].ends_with("/>"));
+        assert_eq!(attribute(packages[0], "package", "pkgid").unwrap(), "a&b");
+        assert_eq!(attribute(packages[0], "package", "arch").unwrap(), "noarch");
+        assert_eq!(attribute(packages[0], "package", "name").unwrap(), "x>y");
+        assert_eq!(attribute(packages[1], "package", "pkgid").unwrap(), "c");
+        assert_eq!(text(packages[1], "package").unwrap(), "");
+        assert_eq!(attribute(xml, "location", "href").unwrap(), "x.rpm");
+        assert_eq!(attribute(xml, "location", "base"), Option::None);
+        assert_eq!(text(xml, "packager").unwrap(), "someone");
+    }
+
+    #[test]
+    fn update_compares_the_pkgid() {
+        let dir = tempfile::tempdir().unwrap();
+        let href = "Packages/h/hello-docs-2.10-3.fc38.noarch.rpm";
+        let path = dir.path().join(href);
+        create_dir_all(path.parent().unwrap()).unwrap();
+        let contents = b"not really an rpm, but the recorded one";
+        let pkgid = hex(&Sha256::digest(&contents[..]));
+        let place = |contents: &[u8]| {
+            write(&path, contents).unwrap();
+            let file = OpenOptions::new().write(true).open(&path).unwrap();
+            file.set_modified(UNIX_EPOCH + Duration::from_secs(1690000001))
+                .unwrap();
+        };
+        place(contents);
+        repodata(dir.path(), |xml| {
+            xml.replace(
+                "9f86d081884c7d659a2feaa0c55ad015a3bf4f1b2b0b822cd15d6c15b0f00a08",
+                &pkgid,
+            )
+            .replace(
+                "package=\"1024\"",
+                &format!("package=\"{}\"", contents.len()),
+            )
+        });
+        // the old hello entry's package is gone, so only hello-docs remains
+        assert_eq!(write_repo(dir.path(), true).unwrap(), (1, 1));
+
+        // the same size and mtime, but different contents, is read again
+        place(b"not really an rpm, and not the recorded");
+        assert!(write_repo(dir.path(), true).is_err());
+    }
+}
@@ -1,5 +1,5 @@
-use super::libflate::gzip::Encoder;
-use super::rpm::{Compressor, RPMBuilder};
+use super::errors::Err;
+use super::rpm::{CompressionWithLevel, PackageBuilder};
 use super::serde::{Deserialize, Serialize};
 
 /// RPM these are required fields for initializing an RPM package build
@@ -10,32 +10,129 @@ pub struct RPM {
     pub license: String,
     pub arch: String,
     pub desc: String,
+    /// the long description, `desc` is repeated when it is unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// the `Maintainer` of debs, the author of the newest changelog entry
+    /// when it is unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub maintainer: Option<String>,
     pub release: Option<u16>,
+    pub epoch: Option<u32>,
+    pub auto_deps: Option<bool>,
+    pub reproducible: Option<bool>,
+    /// deprecated, `gzip = true` is read as `compression = { algo = "gzip" }`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub gzip: Option<bool>,
+    pub compression: Option<Compression>,
 }
 impl RPM {
     /// initializes the construct of the RPM builder
-    pub fn build(&self) -> RPMBuilder {
-        let mut builder = RPMBuilder::new(
-            &self.name,
-            &self.version,
-            &self.license,
-            &self.arch,
-            &self.desc,
-        );
-
-        // check if we should gzip compress this
-        builder = match &self.gzip {
-            &Option::Some(true) => {
-                builder.compression(Compressor::Gzip(Encoder::new(Vec::with_capacity(4096)).unwrap()))
-            }
-            _ => builder,
+    pub fn build(&self, err: &Err) -> Result<PackageBuilder, Err> {
+        self.build_as(&self.name, &self.desc, err)
+    }
+
+    /// initializes the RPM builder for a package sharing this metadata
+    /// under a different name, such as a subpackage.
+    pub fn build_as(&self, name: &str, desc: &str, err: &Err) -> Result<PackageBuilder, Err> {
+        let mut builder = PackageBuilder::new(name, &self.version, &self.license, &self.arch, desc);
+
+        // payloads are stored uncompressed unless a compressor is selected
+        builder = builder.compression(self.compression(err)?.build(err)?);
+
+        // an epoch overrides upstream versioning, for example to downgrade
+        builder = match &self.epoch {
+            &Option::None => builder,
+            &Option::Some(ref epoch) => builder.epoch(*epoch),
         };
 
         // check if we're adding release information
-        match &self.release {
+        Ok(match &self.release {
             &Option::None => builder,
-            &Option::Some(ref release) => builder.release(*release),
+            &Option::Some(ref release) => builder.release(release.to_string()),
+        })
+    }
+
+    /// the payload compressor, which is `none` unless one is selected
+    pub fn compression(&self, err: &Err) -> Result<Compression, Err> {
+        match (&self.compression, self.gzip) {
+            (&Option::Some(_), Option::Some(true)) => Err(err.clone().note(
+                "error",
+                "gzip is deprecated and can not be combined with compression",
+            )),
+            (&Option::Some(ref compression), _) => Ok(compression.clone()),
+            (&Option::None, Option::Some(true)) => Ok(Compression {
+                algo: Algorithm::Gzip,
+                level: Option::None,
+            }),
+            (&Option::None, _) => Ok(Compression::default()),
+        }
+    }
+}
+
+/// Compression selects the payload compressor, for example
+/// `compression = { algo = "zstd", level = 19 }`. When the level is
+/// omitted the compressor's default is used.
+#[derive(Clone, Hash, Debug, Deserialize, Serialize, Default)]
+pub struct Compression {
+    pub algo: Algorithm,
+    #[serde(default)]
+    pub level: Option<i32>,
+}
+impl Compression {
+    /// validates the level, this also determines `PAYLOADCOMPRESSOR` and
+    /// `PAYLOADFLAGS` within the header.
+    pub fn build(&self, err: &Err) -> Result<CompressionWithLevel, Err> {
+        let level = self.level(err)?;
+        Ok(match self.algo {
+            Algorithm::None => CompressionWithLevel::None,
+            Algorithm::Gzip => CompressionWithLevel::Gzip(level as u32),
+            Algorithm::Xz => CompressionWithLevel::Xz(level as u32),
+            Algorithm::Zstd => CompressionWithLevel::Zstd(level),
+        })
+    }
+
+    /// the configured level, or the compressor's default when omitted
+    pub fn level(&self, err: &Err) -> Result<i32, Err> {
+        let (min, max, default) = match self.algo {
+            Algorithm::None => (0, 0, 0),
+            Algorithm::Gzip => (0, 9, 9),
+            Algorithm::Xz => (0, 9, 9),
+            Algorithm::Zstd => (1, 22, 19),
+        };
+        match self.level {
+            Option::None => Ok(default),
+            Option::Some(level) if level < min || level > max => Err(err
+                .clone()
+                .note("compression", format_args!("{:?}", self.algo))
+                .note("level", level)
+                .note(
+                    "error",
+                    format_args!("level must be within {} to {}", min, max),
+                )),
+            Option::Some(level) => Ok(level),
+        }
+    }
+}
+
+/// Algorithm is a payload compressor supported by rpm
+#[derive(Clone, Copy, Hash, Debug, Deserialize, Serialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum Algorithm {
+    #[default]
+    None,
+    Gzip,
+    Xz,
+    Zstd,
+}
+impl Algorithm {
+    /// the name recorded as `PAYLOADCOMPRESSOR`
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            &Algorithm::None => "none",
+            &Algorithm::Gzip => "gzip",
+            &Algorithm::Xz => "xz",
+            &Algorithm::Zstd => "zstd",
         }
     }
 }
@@ -1,5 +1,5 @@
 use super::errors::Err;
-use super::rpm::{RPMBuilder, RPMError};
+use super::rpm::PackageBuilder;
 use super::serde::{Deserialize, Serialize};
 
 /// Scripts defines information about installation/uninstall scripts.
@@ -11,67 +11,64 @@ pub struct Scripts {
     pub post_uninstall: Option<String>,
 }
 impl Scripts {
+    /// the path of every script which is declared
+    pub fn paths(&self) -> Vec<&str> {
+        vec![
+            &self.pre_install,
+            &self.post_install,
+            &self.pre_uninstall,
+            &self.post_uninstall,
+        ]
+        .into_iter()
+        .filter_map(|path| path.as_ref().map(|path| path.as_str()))
+        .collect()
+    }
+
     pub fn build<'a>(
         arg: &'a Option<Scripts>,
-    ) -> impl FnOnce(RPMBuilder, &Err) -> Result<RPMBuilder, Err> + 'a {
-        move |builder: RPMBuilder, err: &Err| -> Result<RPMBuilder, Err> {
+    ) -> impl FnOnce(PackageBuilder, &Err) -> Result<PackageBuilder, Err> + 'a {
+        move |builder: PackageBuilder, err: &Err| -> Result<PackageBuilder, Err> {
             let interior = match arg {
                 &Option::None => return Ok(builder),
                 &Option::Some(ref interior) => interior,
             };
-
-            let mut builder: RPMBuilder = builder;
-
-            builder = (Scripts::load_script(
-                &interior.post_install,
-                "post_install",
-                RPMBuilder::post_install_script,
-            ))(builder, err)?;
-            builder = (Scripts::load_script(
-                &interior.pre_install,
-                "pre_install",
-                RPMBuilder::pre_install_script,
-            ))(builder, err)?;
-            builder = (Scripts::load_script(
-                &interior.post_uninstall,
-                "post_uninstall",
-                RPMBuilder::post_uninstall_script,
-            ))(builder, err)?;
-            builder = (Scripts::load_script(
-                &interior.pre_uninstall,
-                "pre_uninstall",
-                RPMBuilder::pre_uninstall_script,
-            ))(builder, err)?;
-
-            Ok(builder)
+            Ok(interior
+                .read_all(err)?
+                .into_iter()
+                .fold(builder, |builder, (scriptlet, script)| {
+                    scriptlet.add(builder, script)
+                }))
         }
     }
 
-    fn load_script<'a, F>(
-        script: &'a Option<String>,
-        name: &'static str,
-        lambda: F,
-    ) -> impl FnOnce(RPMBuilder, &Err) -> Result<RPMBuilder, Err> + 'a
-    where
-        F: Fn(RPMBuilder, String) -> RPMBuilder + 'static,
-    {
+    /// reads every script which is declared
+    pub fn read_all(&self, err: &Err) -> Result<Vec<(Scriptlet, String)>, Err> {
         use std::fs::read_to_string;
 
-        move |builder: RPMBuilder, err: &Err| -> Result<RPMBuilder, Err> {
-            let path = match script {
-                &Option::None => return Ok(builder),
+        let all = [
+            (Scriptlet::PreInstall, &self.pre_install),
+            (Scriptlet::PostInstall, &self.post_install),
+            (Scriptlet::PreUninstall, &self.pre_uninstall),
+            (Scriptlet::PostUninstall, &self.post_uninstall),
+        ];
+        let mut output = Vec::with_capacity(all.len());
+        for &(scriptlet, path) in all.iter() {
+            let path = match path {
+                &Option::None => continue,
                 &Option::Some(ref path) => path,
             };
-
             match read_to_string(path) {
-                Ok(x) => Ok(lambda(builder, x)),
-                Err(e) => Err(err
-                    .clone()
-                    .note("failed to load script", e)
-                    .note("failed on script", name)
-                    .note("error on path", path)),
-            }
+                Ok(x) => output.push((scriptlet, x)),
+                Err(e) => {
+                    return Err(err
+                        .clone()
+                        .note("failed to load script", e)
+                        .note("failed on script", scriptlet.name())
+                        .note("error on path", path))
+                }
+            };
         }
+        Ok(output)
     }
 
     /*
@@ -123,3 +120,34 @@ impl Scripts {
     }
     */
 }
+
+/// Scriptlet is a point during an install, or uninstall, at which rpm
+/// runs a script
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Scriptlet {
+    PreInstall,
+    PostInstall,
+    PreUninstall,
+    PostUninstall,
+}
+impl Scriptlet {
+    /// the name used within `[scripts]`
+    pub fn name(&self) -> &'static str {
+        match self {
+            &Scriptlet::PreInstall => "pre_install",
+            &Scriptlet::PostInstall => "post_install",
+            &Scriptlet::PreUninstall => "pre_uninstall",
+            &Scriptlet::PostUninstall => "post_uninstall",
+        }
+    }
+
+    /// adds the script to the builder
+    pub fn add(self, builder: PackageBuilder, script: String) -> PackageBuilder {
+        match self {
+            Scriptlet::PreInstall => builder.pre_install_script(script),
+            Scriptlet::PostInstall => builder.post_install_script(script),
+            Scriptlet::PreUninstall => builder.pre_uninstall_script(script),
+            Scriptlet::PostUninstall => builder.post_uninstall_script(script),
+        }
+    }
+}
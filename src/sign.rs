@@ -1,6 +1,6 @@
 use super::errors::Err;
 use super::rpm::signature::pgp::Signer;
-use super::rpm::{RPMBuilder, RPMError, RPMPackage};
+use super::rpm::{Package, PackageBuilder};
 use super::serde::{Deserialize, Serialize};
 
 /// Sign expects the path to an ascii pgp asc secret key
@@ -12,8 +12,8 @@ pub struct Sign {
 impl Sign {
     pub fn build<'a>(
         arg: &'a Option<Self>,
-    ) -> impl FnOnce(RPMBuilder, &Err) -> Result<RPMPackage, Err> + 'a {
-        move |builder: RPMBuilder, err: &Err| -> Result<RPMPackage, Err> {
+    ) -> impl FnOnce(PackageBuilder, &Err) -> Result<Package, Err> + 'a {
+        move |builder: PackageBuilder, err: &Err| -> Result<Package, Err> {
             let result = match arg {
                 Option::None => builder.build(),
                 Option::Some(interior) => {
@@ -0,0 +1,214 @@
+use std::collections::BTreeSet;
+use std::fmt;
+use std::fs::create_dir_all;
+use std::path::Path;
+use std::process::Command;
+
+use super::errors::Err;
+use super::walkdir::WalkDir;
+
+use super::extract::extract_rpm;
+use super::inspect::{File, Inspection, Script};
+
+/// the `PATH` rpm gives scriptlets
+const SCRIPT_PATH: &str = "/sbin:/bin:/usr/sbin:/usr/bin";
+
+/// Simulation is the outcome of installing a package into a scratch root
+#[derive(Clone, Debug, Default)]
+pub struct Simulation {
+    pub nevra: String,
+    pub extracted: Vec<File>,
+    pub runs: Vec<Run>,
+    pub warnings: Vec<String>,
+}
+impl Simulation {
+    /// whether every scriptlet which ran exited successfully
+    pub fn is_ok(&self) -> bool {
+        self.runs.iter().all(|run| run.code == Option::Some(0))
+    }
+}
+
+/// Run is a single scriptlet invocation
+#[derive(Clone, Debug, Default)]
+pub struct Run {
+    pub name: String,
+    /// `None` when the scriptlet was killed by a signal
+    pub code: Option<i32>,
+    pub stdout: String,
+    pub stderr: String,
+    /// paths beneath the root which appeared while the scriptlet ran,
+    /// writes elsewhere on the host are not seen
+    pub created: Vec<String>,
+}
+
+/// installs the RPM at `rpm_path` into `root` the way rpm orders it: the
+/// `pre_install` scriptlet runs, the payload is extracted, and then the
+/// `post_install` scriptlet runs.
+///
+/// Scriptlets run under their shell from within `root`, rather than chrooted, so
+/// `RPM_INSTALL_PREFIX` points at `root` for scriptlets written to be
+/// relocatable. `$1` is the number of instances installed afterwards, 1
+/// for a fresh install or 2 when `upgrade` is set. As rpm does, a failing
+/// `pre_install` aborts the install.
+///
+/// As nothing confines them, scriptlets are only run when `unsafe_host` is
+/// set, otherwise a package with install scriptlets is rejected.
+pub fn simulate_rpm(
+    rpm_path: &str,
+    root: &Path,
+    upgrade: bool,
+    unsafe_host: bool,
+) -> Result<Simulation, Err> {
+    let err = Err::default()
+        .note("rpm", rpm_path)
+        .note("root", root.display());
+    let pkg = Inspection::open(rpm_path)?;
+    let script = |name: &str| pkg.scripts.iter().find(|s| s.name == name);
+    if !unsafe_host && (script("pre_install").is_some() || script("post_install").is_some()) {
+        return Err(err.clone().note(
+            "error",
+            "install scriptlets run directly on the host, pass --unsafe-host to run them",
+        ));
+    }
+    create_dir_all(root).map_err(|e| err.clone().note("failed to create root", e))?;
+    // scriptlets run from within root, so it must not be relative
+    let root = &root
+        .canonicalize()
+        .map_err(|e| err.clone().note("failed to resolve root", e))?;
+
+    let mut output = Simulation {
+        nevra: format!("{}-{}-{}.{}", pkg.name, pkg.version, pkg.release, pkg.arch),
+        ..Simulation::default()
+    };
+    let count = if upgrade { "2" } else { "1" };
+
+    if let Option::Some(pre) = script("pre_install") {
+        let run = run_script(&pkg, pre, root, count, &mut output.warnings, &err)?;
+        if let Option::Some(run) = run {
+            let failed = run.code != Option::Some(0);
+            output.runs.push(run);
+            if failed {
+                output
+                    .warnings
+                    .push("pre_install failed, the payload was not extracted".to_string());
+                return Ok(output);
+            }
+        }
+    }
+
+    let (extracted, warnings) = extract_rpm(rpm_path, root, false)?;
+    output.extracted = extracted;
+    output.warnings.extend(warnings);
+
+    if let Option::Some(post) = script("post_install") {
+        let run = run_script(&pkg, post, root, count, &mut output.warnings, &err)?;
+        if let Option::Some(run) = run {
+            output.runs.push(run);
+        }
+    }
+    Ok(output)
+}
+
+/// runs a scriptlet, or skips it with a warning when its program is not a
+/// shell
+fn run_script(
+    pkg: &Inspection,
+    script: &Script,
+    root: &Path,
+    count: &str,
+    warnings: &mut Vec<String>,
+    err: &Err,
+) -> Result<Option<Run>, Err> {
+    let err = err.clone().note("script", &script.name);
+    // rpm runs scriptlets without a program under `sh`
+    let program = match script.program.as_ref().map(|p| p.as_str()) {
+        Option::None => "sh",
+        Option::Some(program) if program.ends_with("sh") => program,
+        Option::Some(program) => {
+            warnings.push(format!(
+                "{} runs {} rather than a shell, it was skipped",
+                script.name, program
+            ));
+            return Ok(Option::None);
+        }
+    };
+
+    let before = paths(root, &err)?;
+    let root_str = root.to_string_lossy();
+    let result = Command::new(program)
+        .arg("-c")
+        .arg(&script.body)
+        // the arguments following the body are `$0` and `$1`
+        .arg(&script.name)
+        .arg(count)
+        .current_dir(root)
+        .env_clear()
+        .env("PATH", SCRIPT_PATH)
+        .env("RPM_INSTALL_PREFIX", root_str.as_ref())
+        .env("RPM_INSTALL_PREFIX0", root_str.as_ref())
+        .env("RPM_PACKAGE_NAME", &pkg.name)
+        .env("RPM_PACKAGE_VERSION", &pkg.version)
+        .env("RPM_PACKAGE_RELEASE", &pkg.release)
+        .env("RPM_ARCH", &pkg.arch)
+        .env("RPM_OS", "linux")
+        .output()
+        .map_err(|e| {
+            err.clone()
+                .note("program", program)
+                .note("failed to run scriptlet", e)
+        })?;
+    let after = paths(root, &err)?;
+
+    Ok(Option::Some(Run {
+        name: script.name.clone(),
+        code: result.status.code(),
+        stdout: String::from_utf8_lossy(&result.stdout).into_owned(),
+        stderr: String::from_utf8_lossy(&result.stderr).into_owned(),
+        created: after.difference(&before).cloned().collect(),
+    }))
+}
+
+/// every path beneath `root`, as it would be seen once installed
+fn paths(root: &Path, err: &Err) -> Result<BTreeSet<String>, Err> {
+    let mut output = BTreeSet::new();
+    for entry in WalkDir::new(root).min_depth(1) {
+        let entry = entry.map_err(|e| err.clone().note("failed to walk root", e))?;
+        if let Ok(rel) = entry.path().strip_prefix(root) {
+            output.insert(format!("/{}", rel.to_string_lossy()));
+        }
+    }
+    Ok(output)
+}
+
+impl fmt::Display for Simulation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(
+            f,
+            "{}: {} files extracted",
+            self.nevra,
+            self.extracted.len()
+        )?;
+        for run in self.runs.iter() {
+            match run.code {
+                Option::Some(code) => writeln!(f, "{}: exit {}", run.name, code)?,
+                Option::None => writeln!(f, "{}: killed by a signal", run.name)?,
+            };
+            for (label, text) in [("stdout", &run.stdout), ("stderr", &run.stderr)].iter() {
+                if text.is_empty() {
+                    continue;
+                }
+                writeln!(f, "  {}:", label)?;
+                for line in text.lines() {
+                    writeln!(f, "    {}", line)?;
+                }
+            }
+            if !run.created.is_empty() {
+                writeln!(f, "  created:")?;
+                for path in run.created.iter() {
+                    writeln!(f, "    {}", path)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,1614 @@
+use std::collections::BTreeMap;
+use std::env::consts::{ARCH, OS};
+use std::fs::{create_dir_all, read_to_string, write};
+use std::mem::replace;
+use std::path::Path;
+
+use super::chrono::NaiveDate;
+use super::errors::Err;
+use super::glob::Pattern;
+
+use super::changelog::{insert_entry, ChangeLogEntry};
+use super::core::ConfigFile;
+use super::fileopts::{ComplexFileOptions, FileOptions};
+use super::import::{PAYLOAD_DIR, SCRIPTS_DIR};
+use super::scripts::Scripts;
+use super::subpackage::SubPackage;
+use super::versions::Constraints;
+
+/// the macros rpm itself defines which a spec most often uses, anything
+/// else must be defined by the spec.
+const DEFAULT_MACROS: &[(&str, &str)] = &[
+    ("_prefix", "/usr"),
+    ("_exec_prefix", "/usr"),
+    ("_bindir", "/usr/bin"),
+    ("_sbindir", "/usr/sbin"),
+    ("_libdir", "/usr/lib64"),
+    ("_libexecdir", "/usr/libexec"),
+    ("_datadir", "/usr/share"),
+    ("_includedir", "/usr/include"),
+    ("_sysconfdir", "/etc"),
+    ("_localstatedir", "/var"),
+    ("_sharedstatedir", "/var/lib"),
+    ("_rundir", "/run"),
+    ("_mandir", "/usr/share/man"),
+    ("_infodir", "/usr/share/info"),
+    ("_docdir", "/usr/share/doc"),
+    ("_defaultdocdir", "/usr/share/doc"),
+    ("_licensedir", "/usr/share/licenses"),
+    ("_unitdir", "/usr/lib/systemd/system"),
+    ("_tmpfilesdir", "/usr/lib/tmpfiles.d"),
+    ("buildroot", ""),
+];
+
+/// sections rpmbuild runs to produce the files, rather than describe them
+const BUILD_SECTIONS: &[&str] = &[
+    "prep",
+    "conf",
+    "generate_buildrequires",
+    "build",
+    "install",
+    "check",
+    "clean",
+];
+
+/// scriptlets which have no counterpart within a config
+const OTHER_SCRIPTS: &[&str] = &[
+    "pretrans",
+    "posttrans",
+    "preuntrans",
+    "postuntrans",
+    "verifyscript",
+    "triggerprein",
+    "triggerin",
+    "triggerun",
+    "triggerpostun",
+    "filetriggerin",
+    "filetriggerun",
+    "filetriggerpostun",
+    "transfiletriggerin",
+    "transfiletriggerun",
+    "transfiletriggerpostun",
+];
+
+const SCRIPTS: &[(&str, &str)] = &[
+    ("pre", "pre_install"),
+    ("post", "post_install"),
+    ("preun", "pre_uninstall"),
+    ("postun", "post_uninstall"),
+];
+
+const CONDITIONALS: &[&str] = &[
+    "%if",
+    "%ifarch",
+    "%ifnarch",
+    "%ifos",
+    "%ifnos",
+    "%elif",
+    "%elifarch",
+    "%elifos",
+    "%else",
+    "%endif",
+];
+
+/// bounds the expansion of macros which refer to themselves
+const MAX_DEPTH: usize = 16;
+
+/// converts the spec at `spec_path` into a config, its scriptlets are
+/// written to `dir/scripts`.
+///
+/// Files listed in `%files` are expected beneath `dir/root`, which is
+/// where `%install` would have placed them, and relative `%doc` or
+/// `%license` files beneath `dir`. Anything which could not be translated
+/// is returned as a warning.
+pub fn from_spec(spec_path: &str, dir: &Path) -> Result<(ConfigFile, Vec<String>), Err> {
+    let err = Err::default().note("spec", spec_path);
+    let text = read_to_string(spec_path).map_err(|e| err.clone().note("failed to read spec", e))?;
+
+    let mut parser = Parser::default();
+    for (name, value) in DEFAULT_MACROS.iter() {
+        parser.macros.insert(name.to_string(), value.to_string());
+    }
+    parser.config.rpm.auto_deps = Option::Some(true);
+    parser.parse(&text);
+    parser.finish(dir, &err)
+}
+
+/// Section is the part of the spec the parser is within, the package is
+/// `None` for the main package or the suffix of a sub package.
+enum Section {
+    Preamble(Option<String>),
+    Description(Option<String>),
+    Files(Option<String>),
+    Script(Option<String>, &'static str, Option<String>),
+    Changelog,
+    Skipped,
+}
+
+/// Condition is an open `%if`, tracking which of its branches is translated
+struct Condition {
+    /// whether the current branch is translated
+    active: bool,
+    /// whether an earlier branch was translated, so later ones are not
+    taken: bool,
+    /// a condition could not be evaluated, so its first branch was assumed
+    /// and the lines of the others are dropped with a warning
+    guessed: bool,
+}
+
+/// Attr is the ownership and mode given by `%attr` or `%defattr`
+#[derive(Clone, Default)]
+struct Attr {
+    mode: Option<i32>,
+    user: Option<String>,
+    group: Option<String>,
+}
+
+#[derive(Default)]
+struct Parser {
+    macros: BTreeMap<String, String>,
+    warnings: Vec<String>,
+    config: ConfigFile,
+    packages: BTreeMap<Option<String>, SubPackage>,
+    scripts: Vec<(Option<String>, &'static str, String)>,
+    defattr: Attr,
+    conditions: Vec<Condition>,
+}
+impl Parser {
+    fn parse(&mut self, text: &str) {
+        let mut section = Section::Preamble(Option::None);
+        let mut body: Vec<&str> = Vec::new();
+        for (number, line) in text.lines().enumerate() {
+            let trimmed = line.trim();
+            if self.conditional(trimmed, number + 1) {
+                continue;
+            }
+            if !self.conditions.iter().all(|c| c.active) {
+                let guessed = self.conditions.iter().any(|c| c.guessed && !c.active);
+                if guessed && !trimmed.is_empty() {
+                    self.warn(format!(
+                        "line {} '{}' is within a branch which may have been taken, it was dropped",
+                        number + 1,
+                        trimmed
+                    ));
+                }
+                continue;
+            }
+            if let Option::Some(next) = self.section(trimmed) {
+                let done = replace(&mut section, next);
+                self.close(done, &body);
+                body.clear();
+                continue;
+            }
+            if self.directive(trimmed) {
+                continue;
+            }
+            match section {
+                Section::Preamble(ref pkg) => self.preamble(pkg, trimmed),
+                Section::Files(ref pkg) => self.files(pkg, trimmed),
+                Section::Skipped => {}
+                _ => body.push(line),
+            };
+        }
+        self.close(section, &body);
+    }
+
+    fn finish(mut self, dir: &Path, err: &Err) -> Result<(ConfigFile, Vec<String>), Err> {
+        if self.config.rpm.name.is_empty() || self.config.rpm.version.is_empty() {
+            return Err(err.clone().note("error", "spec has no Name or Version"));
+        }
+        if self.config.rpm.arch.is_empty() {
+            self.config.rpm.arch = std::env::consts::ARCH.to_string();
+        }
+
+        if !self.scripts.is_empty() {
+            let scripts_dir = dir.join(SCRIPTS_DIR);
+            create_dir_all(&scripts_dir).map_err(|e| {
+                err.clone()
+                    .note("failed to create directory", e)
+                    .note("path", scripts_dir.display())
+            })?;
+        }
+        for (pkg, slot, body) in replace(&mut self.scripts, Vec::new()) {
+            let source = match pkg {
+                Option::None => format!("{}/{}.sh", SCRIPTS_DIR, slot),
+                Option::Some(ref suffix) => format!("{}/{}-{}.sh", SCRIPTS_DIR, suffix, slot),
+            };
+            let path = dir.join(&source);
+            write(&path, body.as_bytes()).map_err(|e| {
+                err.clone()
+                    .note("failed to write script", e)
+                    .note("path", path.display())
+            })?;
+            let scripts = self
+                .package(&pkg)
+                .scripts
+                .get_or_insert_with(Scripts::default);
+            let slot = match slot {
+                "pre_install" => &mut scripts.pre_install,
+                "post_install" => &mut scripts.post_install,
+                "pre_uninstall" => &mut scripts.pre_uninstall,
+                _ => &mut scripts.post_uninstall,
+            };
+            *slot = Option::Some(source);
+        }
+
+        let mut config = self.config;
+        for (pkg, sub) in self.packages {
+            match pkg {
+                Option::None => config.set_main_package(sub),
+                Option::Some(suffix) => {
+                    config.packages.insert(suffix, sub);
+                }
+            };
+        }
+        Ok((config, self.warnings))
+    }
+
+    fn warn(&mut self, warning: String) {
+        if !self.warnings.contains(&warning) {
+            self.warnings.push(warning);
+        }
+    }
+
+    fn package(&mut self, pkg: &Option<String>) -> &mut SubPackage {
+        self.packages
+            .entry(pkg.clone())
+            .or_insert_with(SubPackage::default)
+    }
+
+    /// the full name of a package, as used for its documentation directory
+    fn package_name(&self, pkg: &Option<String>) -> String {
+        match pkg {
+            &Option::None => self.config.rpm.name.clone(),
+            &Option::Some(ref suffix) => format!("{}-{}", self.config.rpm.name, suffix),
+        }
+    }
+
+    /*
+     * sections
+     *
+     */
+
+    /// the section a line starts, if it starts one
+    fn section(&mut self, line: &str) -> Option<Section> {
+        let mut words = line.split_whitespace();
+        let name = words.next()?.strip_prefix('%')?;
+        let args: Vec<&str> = words.collect();
+
+        if BUILD_SECTIONS.contains(&name) {
+            self.warn(format!(
+                "%{} is run by rpmbuild and was not translated, installed files belong beneath {}/",
+                name, PAYLOAD_DIR
+            ));
+            return Option::Some(Section::Skipped);
+        }
+        if OTHER_SCRIPTS.contains(&name) {
+            self.warn(format!(
+                "%{} can not be expressed by a config, it was dropped",
+                name
+            ));
+            return Option::Some(Section::Skipped);
+        }
+        if name == "changelog" {
+            return Option::Some(Section::Changelog);
+        }
+        let script = SCRIPTS
+            .iter()
+            .find(|(s, _)| *s == name)
+            .map(|(_, slot)| *slot);
+        if !["package", "description", "files"].contains(&name) && script.is_none() {
+            return Option::None;
+        }
+
+        let (pkg, program) = match self.arguments(name, &args) {
+            Option::None => return Option::Some(Section::Skipped),
+            Option::Some(parsed) => parsed,
+        };
+        Option::Some(match (name, script) {
+            (_, Option::Some(slot)) => Section::Script(pkg, slot, program),
+            ("package", _) => {
+                if pkg.is_none() {
+                    self.warn("%package without a name was skipped".to_string());
+                    return Option::Some(Section::Skipped);
+                }
+                self.package(&pkg);
+                Section::Preamble(pkg)
+            }
+            ("description", _) => Section::Description(pkg),
+            _ => {
+                self.defattr = Attr::default();
+                Section::Files(pkg)
+            }
+        })
+    }
+
+    /// the package and program named by the arguments of a section, `None`
+    /// when the package can not be expressed as a sub package.
+    fn arguments(
+        &mut self,
+        section: &str,
+        args: &[&str],
+    ) -> Option<(Option<String>, Option<String>)> {
+        let mut pkg = Option::None;
+        let mut program = Option::None;
+        let mut args = args.iter();
+        while let Option::Some(arg) = args.next() {
+            match *arg {
+                "-n" => {
+                    let full = self.expand(args.next().map(|a| *a).unwrap_or(""));
+                    let prefix = format!("{}-", self.config.rpm.name);
+                    if full == self.config.rpm.name {
+                        pkg = Option::None;
+                    } else if full.starts_with(&prefix) {
+                        pkg = Option::Some(full[prefix.len()..].to_string());
+                    } else {
+                        self.warn(format!(
+                            "%{} -n {} does not share the name of the main package, it was skipped",
+                            section, full
+                        ));
+                        return Option::None;
+                    }
+                }
+                "-p" => program = args.next().map(|p| self.expand(p)),
+                "-f" => {
+                    let list = args.next().map(|a| *a).unwrap_or("");
+                    self.warn(format!("%{} -f {} was not translated", section, list));
+                }
+                flag if flag.starts_with('-') => {
+                    self.warn(format!("%{} {} was ignored", section, flag));
+                }
+                suffix => pkg = Option::Some(self.expand(suffix)),
+            };
+        }
+        Option::Some((pkg, program))
+    }
+
+    fn close(&mut self, section: Section, body: &[&str]) {
+        match section {
+            Section::Description(pkg) => self.description(&pkg, body),
+            Section::Script(pkg, slot, program) => self.script(pkg, slot, program, body),
+            Section::Changelog => self.changelog(body),
+            _ => {}
+        };
+    }
+
+    /// handles the macro definitions valid anywhere
+    fn directive(&mut self, line: &str) -> bool {
+        let mut words = line.splitn(2, char::is_whitespace);
+        let keyword = words.next().unwrap_or("");
+        let rest = words.next().unwrap_or("").trim();
+        match keyword {
+            "%define" | "%global" => {
+                let mut parts = rest.splitn(2, char::is_whitespace);
+                let name = parts.next().unwrap_or("");
+                let body = parts.next().unwrap_or("").trim();
+                if name.contains('(') {
+                    self.warn(format!("parametric macro {} is not supported", name));
+                    return true;
+                }
+                let body = if keyword == "%global" {
+                    self.expand(body)
+                } else {
+                    body.to_string()
+                };
+                self.macros.insert(name.to_string(), body);
+                true
+            }
+            "%undefine" => {
+                self.macros.remove(rest);
+                true
+            }
+            // build conditionals take their default, as no `--with` is given
+            "%bcond_with" => true,
+            "%bcond_without" => {
+                self.macros
+                    .insert(format!("with_{}", rest), "1".to_string());
+                true
+            }
+            "%bcond" => {
+                let mut parts = rest.splitn(2, char::is_whitespace);
+                let name = parts.next().unwrap_or("").to_string();
+                let default = self.expand(parts.next().unwrap_or("0"));
+                match evaluate(&default) {
+                    Option::Some(true) => {
+                        self.macros
+                            .insert(format!("with_{}", name), "1".to_string());
+                    }
+                    Option::Some(false) => {}
+                    Option::None => self.warn(format!(
+                        "the default of %bcond {} could not be evaluated, it is off",
+                        name
+                    )),
+                };
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// follows `%if` and its relatives, returning whether the line was one.
+    ///
+    /// Numeric and string comparisons, `%{defined}`, `%{with}`, and the
+    /// host's arch and os are evaluated. When a condition can not be, its
+    /// first branch is translated and the lines of the others are dropped.
+    fn conditional(&mut self, line: &str, number: usize) -> bool {
+        let mut words = line.splitn(2, char::is_whitespace);
+        let keyword = words.next().unwrap_or("");
+        let rest = words.next().unwrap_or("").trim();
+        if !CONDITIONALS.contains(&keyword) {
+            return false;
+        }
+
+        let depth = self.conditions.len();
+        match keyword {
+            "%endif" => {
+                if self.conditions.pop().is_none() {
+                    self.warn(format!("line {} %endif has no %if, it was ignored", number));
+                }
+            }
+            "%else" => match self.conditions.last_mut() {
+                Option::Some(condition) => {
+                    condition.active = !condition.taken;
+                    condition.taken = true;
+                }
+                Option::None => {
+                    self.warn(format!("line {} %else has no %if, it was ignored", number))
+                }
+            },
+            elif if elif.starts_with("%elif") => {
+                if depth == 0 {
+                    self.warn(format!(
+                        "line {} {} has no %if, it was ignored",
+                        number, elif
+                    ));
+                    return true;
+                }
+                let enclosing = self.conditions[..depth - 1].iter().all(|c| c.active);
+                let (active, guessed) = if enclosing && !self.conditions[depth - 1].taken {
+                    self.test(keyword, rest, number)
+                } else {
+                    (false, false)
+                };
+                let condition = &mut self.conditions[depth - 1];
+                condition.active = active;
+                condition.taken |= active;
+                condition.guessed |= guessed;
+            }
+            _ => {
+                // conditions within an untranslated branch are not evaluated
+                let (active, guessed) = if self.conditions.iter().all(|c| c.active) {
+                    self.test(keyword, rest, number)
+                } else {
+                    (false, false)
+                };
+                self.conditions.push(Condition {
+                    active,
+                    taken: active,
+                    guessed,
+                });
+            }
+        };
+        true
+    }
+
+    /// whether the branch of a conditional is taken, and whether that was
+    /// guessed as the condition could not be evaluated
+    fn test(&mut self, keyword: &str, condition: &str, number: usize) -> (bool, bool) {
+        let expanded = self.expand(condition);
+        let words = || expanded.split_whitespace();
+        let outcome = if expanded.contains('%') {
+            Option::None
+        } else {
+            match keyword {
+                "%if" | "%elif" => evaluate(&expanded),
+                "%ifarch" | "%elifarch" => Option::Some(words().any(|a| a == ARCH)),
+                "%ifnarch" => Option::Some(!words().any(|a| a == ARCH)),
+                "%ifos" | "%elifos" => Option::Some(words().any(|os| os == OS)),
+                _ => Option::Some(!words().any(|os| os == OS)),
+            }
+        };
+        match outcome {
+            Option::Some(active) => (active, false),
+            Option::None => {
+                self.warn(format!(
+                    "line {} '{} {}' could not be evaluated, its first branch was translated",
+                    number, keyword, condition
+                ));
+                (true, true)
+            }
+        }
+    }
+
+    /*
+     * preamble
+     *
+     */
+
+    fn preamble(&mut self, pkg: &Option<String>, line: &str) {
+        if line.is_empty() || line.starts_with('#') {
+            return;
+        }
+        let idx = match line.find(':') {
+            Option::Some(idx) => idx,
+            Option::None => {
+                self.warn(format!("preamble line '{}' was not understood", line));
+                return;
+            }
+        };
+        let (tag, qualifiers) = match line[..idx].trim().find('(') {
+            Option::None => (line[..idx].trim(), ""),
+            Option::Some(open) => {
+                let tag = line[..idx].trim();
+                (&tag[..open], tag[open + 1..].trim_end_matches(')'))
+            }
+        };
+        let value = self.expand(line[idx + 1..].trim());
+        let key = tag
+            .to_lowercase()
+            .trim_end_matches(|c: char| c.is_ascii_digit())
+            .to_string();
+
+        match (key.as_str(), pkg.is_none()) {
+            ("name", true) => {
+                self.config.rpm.name = value.clone();
+                self.macros.insert("name".to_string(), value);
+            }
+            ("version", true) => {
+                self.config.rpm.version = value.clone();
+                self.macros.insert("version".to_string(), value);
+            }
+            ("release", true) => {
+                self.config.rpm.release = match value.parse::<u16>() {
+                    Ok(release) => Option::Some(release),
+                    Err(_) => {
+                        self.warn(format!(
+                            "release '{}' is not a number, it was dropped",
+                            value
+                        ));
+                        Option::None
+                    }
+                };
+                self.macros.insert("release".to_string(), value);
+            }
+            ("epoch", true) => {
+                self.config.rpm.epoch = match value.parse::<u32>() {
+                    Ok(epoch) => Option::Some(epoch),
+                    Err(_) => {
+                        self.warn(format!("epoch '{}' is not a number, it was dropped", value));
+                        Option::None
+                    }
+                };
+                self.macros.insert("epoch".to_string(), value);
+            }
+            ("license", true) => self.config.rpm.license = value,
+            ("summary", true) => self.config.rpm.desc = value,
+            ("summary", false) => self.package(pkg).desc = Option::Some(value),
+            ("buildarch", true) => self.config.rpm.arch = value,
+            ("autoreqprov", true) | ("autoreq", true) => {
+                self.config.rpm.auto_deps = Option::Some(!["no", "0"].contains(&value.as_str()));
+            }
+            ("source", true) => self.srpm_file(false, &value),
+            ("patch", true) => self.srpm_file(true, &value),
+            ("requires", _) => {
+                let tables: Vec<&'static str> = if qualifiers.is_empty() {
+                    vec!["requires"]
+                } else {
+                    qualifiers
+                        .split(',')
+                        .map(|q| match q.trim() {
+                            "pre" => "requires_pre",
+                            "post" => "requires_post",
+                            "preun" => "requires_preun",
+                            "postun" => "requires_postun",
+                            other => {
+                                self.warn(format!(
+                                    "Requires({}) was translated as Requires",
+                                    other
+                                ));
+                                "requires"
+                            }
+                        })
+                        .collect()
+                };
+                for table in tables {
+                    self.dependencies(pkg, table, &value);
+                }
+            }
+            ("provides", _) => self.dependencies(pkg, "provides", &value),
+            ("conflicts", _) => self.dependencies(pkg, "conflicts", &value),
+            ("obsoletes", _) => self.dependencies(pkg, "obsoletes", &value),
+            ("recommends", _) => self.dependencies(pkg, "recommends", &value),
+            ("suggests", _) => self.dependencies(pkg, "suggests", &value),
+            ("supplements", _) => self.dependencies(pkg, "supplements", &value),
+            ("enhances", _) => self.dependencies(pkg, "enhances", &value),
+            _ => self.warn(format!("tag {} was not translated", tag)),
+        };
+    }
+
+    /// sources and patches are packaged by their file name, as rpmbuild
+    /// does for urls
+    fn srpm_file(&mut self, patch: bool, value: &str) {
+        let file = value.rsplit('/').next().unwrap_or(value).to_string();
+        let srpm = self.config.srpm.get_or_insert_with(Default::default);
+        if patch {
+            srpm.patches.push(file);
+        } else {
+            srpm.sources.push(file);
+        }
+    }
+
+    fn dependencies(&mut self, pkg: &Option<String>, table: &'static str, value: &str) {
+        let tokens = split_tokens(value, true);
+        let mut tokens = tokens.iter().peekable();
+        while let Option::Some(name) = tokens.next() {
+            if name.starts_with('(') {
+                match table {
+                    "requires" => self.package(pkg).rich_requires.push(name.clone()),
+                    "conflicts" => self.package(pkg).rich_conflicts.push(name.clone()),
+                    _ => self.warn(format!(
+                        "rich dependency '{}' in {} is not supported, it was dropped",
+                        name, table
+                    )),
+                };
+                continue;
+            }
+            let op = match tokens.peek().map(|t| t.as_str()) {
+                Option::Some("==") => Option::Some("="),
+                Option::Some(op) if ["<", "<=", "=", ">=", ">"].contains(&op) => Option::Some(op),
+                _ => Option::None,
+            };
+            let constraint = match op {
+                Option::None => String::new(),
+                Option::Some(op) => {
+                    let op = op.to_string();
+                    tokens.next();
+                    format!("{} {}", op, tokens.next().map(|v| v.as_str()).unwrap_or(""))
+                }
+            };
+            add_constraint(dependency_table(self.package(pkg), table), name, constraint);
+        }
+    }
+
+    /*
+     * files
+     *
+     */
+
+    fn files(&mut self, pkg: &Option<String>, line: &str) {
+        if line.is_empty() || line.starts_with('#') {
+            return;
+        }
+        let mut attr = self.defattr.clone();
+        let mut config = false;
+        let mut doc_dir = Option::None;
+        let mut dir = false;
+        let mut exclude = false;
+        let mut paths = Vec::new();
+        for token in split_tokens(line, false) {
+            let (name, args) = match token.find('(') {
+                Option::Some(open) if token.ends_with(')') => {
+                    (&token[..open], &token[open + 1..token.len() - 1])
+                }
+                _ => (token.as_str(), ""),
+            };
+            match name {
+                "%defattr" => self.defattr = Attr::parse(args, &Attr::default()),
+                "%attr" => attr = Attr::parse(args, &self.defattr),
+                "%config" => {
+                    if !args.is_empty() {
+                        self.warn(format!("%config({}) was translated as %config", args));
+                    }
+                    config = true;
+                }
+                "%doc" => doc_dir = Option::Some("/usr/share/doc"),
+                "%license" => doc_dir = Option::Some("/usr/share/licenses"),
+                "%dir" => dir = true,
+                "%exclude" => exclude = true,
+                "%ghost" | "%verify" | "%lang" | "%caps" | "%readme" | "%docdir" | "%artifact"
+                | "%missingok" => self.warn(format!("{} was not translated", name)),
+                _ => paths.push(self.expand(token.trim_matches('"'))),
+            };
+        }
+        for path in paths {
+            if exclude {
+                self.exclude(pkg, &path);
+            } else if dir {
+                self.warn(format!(
+                    "%dir {} was dropped, a config only owns the files within it",
+                    path
+                ));
+            } else {
+                self.add_file(pkg, &path, &attr, config, doc_dir);
+            }
+        }
+    }
+
+    fn add_file(
+        &mut self,
+        pkg: &Option<String>,
+        path: &str,
+        attr: &Attr,
+        config: bool,
+        doc_dir: Option<&str>,
+    ) {
+        // keys are read back as globs, so only rpm's wild cards remain
+        let (pattern, glob) = pattern(path);
+        let literal = path.replace('\\', "");
+        let (source, dst) = match (path.starts_with('/'), doc_dir) {
+            (true, _) => {
+                let dst = if glob { fixed_prefix(path) } else { literal };
+                (format!("{}{}", PAYLOAD_DIR, pattern), dst)
+            }
+            // relative documentation is copied from the build directory
+            (false, Option::Some(doc_dir)) => {
+                let base = format!("{}/{}", doc_dir, self.package_name(pkg));
+                let dst = if glob {
+                    base
+                } else {
+                    let name = literal.rsplit('/').next().unwrap_or(&literal);
+                    format!("{}/{}", base, name)
+                };
+                (pattern, dst)
+            }
+            (false, Option::None) => {
+                self.warn(format!("relative path '{}' in %files was dropped", path));
+                return;
+            }
+        };
+        let opts = ComplexFileOptions {
+            dst,
+            user: attr.user.clone(),
+            group: attr.group.clone(),
+            symlink: Option::None,
+            mode: attr.mode,
+            doc: Option::Some(true).filter(|_| doc_dir.is_some()),
+            config: Option::Some(true).filter(|_| config),
+            exclude: Option::None,
+        };
+        self.package(pkg)
+            .contents
+            .insert(source, FileOptions::Complex(opts));
+    }
+
+    /// excludes `path` from the entry which would otherwise include it
+    fn exclude(&mut self, pkg: &Option<String>, path: &str) {
+        let contents = &mut self.package(pkg).contents;
+        let entry = contents.values_mut().find_map(|opts| match opts {
+            &mut FileOptions::Complex(ref mut opts)
+                if path.starts_with(&format!("{}/", opts.dst.trim_end_matches('/'))) =>
+            {
+                Option::Some(opts)
+            }
+            _ => Option::None,
+        });
+        match entry {
+            Option::Some(opts) => {
+                let rel = path[opts.dst.trim_end_matches('/').len() + 1..].to_string();
+                opts.exclude.get_or_insert_with(Vec::new).push(rel);
+            }
+            Option::None => self.warn(format!(
+                "%exclude {} matched no entry, it was dropped",
+                path
+            )),
+        };
+    }
+
+    /*
+     * bodies
+     *
+     */
+
+    /// the text becomes `description`, unless a sub package has no
+    /// `Summary` in which case it becomes the sub package's `desc`
+    fn description(&mut self, pkg: &Option<String>, body: &[&str]) {
+        let text = join_body(body);
+        if text.is_empty() {
+            return;
+        }
+        let sub = self.package(pkg);
+        match (pkg, &sub.desc) {
+            (&Option::Some(_), &Option::None) => sub.desc = Option::Some(text),
+            _ => sub.description = Option::Some(text),
+        };
+    }
+
+    fn script(
+        &mut self,
+        pkg: Option<String>,
+        slot: &'static str,
+        program: Option<String>,
+        body: &[&str],
+    ) {
+        let text = join_body(body);
+        let text = match program.as_ref().map(|p| p.as_str()) {
+            Option::None | Option::Some("/bin/sh") => text,
+            Option::Some(program) if program.starts_with("<lua>") => {
+                self.warn(format!("{} is written in lua, it was dropped", slot));
+                return;
+            }
+            // a lone program is run as the scriptlet
+            Option::Some(program) if text.is_empty() => program.to_string(),
+            Option::Some(program) => {
+                self.warn(format!(
+                    "{} runs with '{}', it will run with /bin/sh",
+                    slot, program
+                ));
+                text
+            }
+        };
+        if text.is_empty() {
+            return;
+        }
+        let text = self.expand(&text);
+        self.scripts.push((pkg, slot, format!("{}\n", text)));
+    }
+
+    /// entries start with `* <day> <month> <date> <year> <author>`
+    fn changelog(&mut self, body: &[&str]) {
+        let mut entries: Vec<(&str, Vec<&str>)> = Vec::new();
+        for line in body {
+            if line.starts_with('*') {
+                entries.push((*line, Vec::new()));
+            } else if let Option::Some(entry) = entries.last_mut() {
+                entry.1.push(*line);
+            }
+        }
+
+        for (header, lines) in entries {
+            let words: Vec<&str> = header.trim_start_matches('*').split_whitespace().collect();
+            let date = words
+                .iter()
+                .take(4)
+                .map(|w| *w)
+                .collect::<Vec<&str>>()
+                .join(" ");
+            let when = NaiveDate::parse_from_str(&date, "%a %b %d %Y")
+                .ok()
+                .and_then(|date| date.and_hms_opt(0, 0, 0));
+            let when = match when {
+                Option::Some(when) => when,
+                Option::None => {
+                    self.warn(format!(
+                        "changelog entry '{}' has no readable date, it was dropped",
+                        header
+                    ));
+                    continue;
+                }
+            };
+            insert_entry(
+                &mut self.config.changelog,
+                when,
+                ChangeLogEntry {
+                    author: words[4..].join(" "),
+                    entry: join_body(&lines).replace("%%", "%"),
+                },
+            );
+        }
+    }
+
+    /*
+     * macros
+     *
+     */
+
+    fn expand(&mut self, text: &str) -> String {
+        self.expand_depth(text, 0)
+    }
+
+    fn expand_depth(&mut self, text: &str, depth: usize) -> String {
+        let mut output = String::with_capacity(text.len());
+        let mut rest = text;
+        while let Option::Some(idx) = rest.find('%') {
+            output.push_str(&rest[..idx]);
+            rest = &rest[idx + 1..];
+            if rest.starts_with('%') {
+                output.push('%');
+                rest = &rest[1..];
+            } else if rest.starts_with('{') {
+                match closing_brace(rest) {
+                    Option::None => output.push('%'),
+                    Option::Some(end) => {
+                        let value = self.braced(&rest[1..end], depth);
+                        output.push_str(&value);
+                        rest = &rest[end + 1..];
+                    }
+                };
+            } else if rest.starts_with('(') {
+                self.warn("shell expansions %(...) were not run".to_string());
+                output.push('%');
+            } else {
+                let len = rest
+                    .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                    .unwrap_or(rest.len());
+                let name = &rest[..len];
+                rest = &rest[len..];
+                match self.lookup(name, depth) {
+                    Option::Some(value) => output.push_str(&value),
+                    Option::None => {
+                        if !name.is_empty() {
+                            self.unknown(name);
+                        }
+                        output.push('%');
+                        output.push_str(name);
+                    }
+                };
+            }
+        }
+        output.push_str(rest);
+        output
+    }
+
+    /// expands `%{...}`, including the `%{?name}`, `%{?name:text}` and
+    /// `%{!?name:text}` conditional forms
+    fn braced(&mut self, inner: &str, depth: usize) -> String {
+        // `%{defined name}` and friends expand to 1 or 0
+        let mut words = inner.splitn(2, ' ');
+        let test = match (words.next(), words.next().map(|w| w.trim())) {
+            (Option::Some("defined"), Option::Some(name)) => Option::Some((name.to_string(), true)),
+            (Option::Some("undefined"), Option::Some(name)) => {
+                Option::Some((name.to_string(), false))
+            }
+            (Option::Some("with"), Option::Some(name)) => {
+                Option::Some((format!("with_{}", name), true))
+            }
+            (Option::Some("without"), Option::Some(name)) => {
+                Option::Some((format!("with_{}", name), false))
+            }
+            _ => Option::None,
+        };
+        if let Option::Some((name, defined)) = test {
+            let outcome = self.macros.contains_key(&name) == defined;
+            return if outcome { "1" } else { "0" }.to_string();
+        }
+
+        let (negate, conditional, body) = if let Option::Some(body) = inner.strip_prefix("!?") {
+            (true, true, body)
+        } else if let Option::Some(body) = inner.strip_prefix("?!") {
+            (true, true, body)
+        } else if let Option::Some(body) = inner.strip_prefix('?') {
+            (false, true, body)
+        } else {
+            (false, false, inner)
+        };
+        let (name, alternative) = match body.find(':') {
+            Option::Some(idx) => (&body[..idx], Option::Some(&body[idx + 1..])),
+            Option::None => (body, Option::None),
+        };
+
+        if conditional {
+            let active = self.macros.contains_key(name) != negate;
+            return match (active, alternative) {
+                (false, _) => String::new(),
+                (true, Option::Some(alternative)) => self.expand_depth(alternative, depth + 1),
+                (true, Option::None) if negate => String::new(),
+                (true, Option::None) => self.lookup(name, depth).unwrap_or_default(),
+            };
+        }
+        match (alternative, self.lookup(name, depth)) {
+            (Option::None, Option::Some(value)) => value,
+            _ => {
+                self.unknown(name);
+                format!("%{{{}}}", inner)
+            }
+        }
+    }
+
+    fn lookup(&mut self, name: &str, depth: usize) -> Option<String> {
+        if depth >= MAX_DEPTH {
+            self.warn(format!("macro %{{{}}} expands too deeply", name));
+            return Option::None;
+        }
+        let value = self.macros.get(name)?.clone();
+        Option::Some(self.expand_depth(&value, depth + 1))
+    }
+
+    fn unknown(&mut self, name: &str) {
+        self.warn(format!(
+            "macro %{{{}}} could not be expanded, it was kept as is",
+            name
+        ));
+    }
+}
+
+impl Attr {
+    /// parses `mode, user, group`, where `-` keeps the value of `defaults`
+    fn parse(args: &str, defaults: &Attr) -> Attr {
+        let fields: Vec<&str> = args.split(',').map(|f| f.trim()).collect();
+        let field = |idx: usize| {
+            fields
+                .get(idx)
+                .map(|f| *f)
+                .filter(|f| !f.is_empty() && *f != "-")
+        };
+        Attr {
+            mode: field(0)
+                .and_then(|mode| i32::from_str_radix(mode, 8).ok())
+                .or(defaults.mode),
+            user: field(1)
+                .map(|u| u.to_string())
+                .or_else(|| defaults.user.clone()),
+            group: field(2)
+                .map(|g| g.to_string())
+                .or_else(|| defaults.group.clone()),
+        }
+    }
+}
+
+fn dependency_table<'a>(
+    pkg: &'a mut SubPackage,
+    table: &str,
+) -> &'a mut BTreeMap<String, Constraints> {
+    match table {
+        "requires" => &mut pkg.requires,
+        "requires_pre" => &mut pkg.requires_pre,
+        "requires_post" => &mut pkg.requires_post,
+        "requires_preun" => &mut pkg.requires_preun,
+        "requires_postun" => &mut pkg.requires_postun,
+        "provides" => &mut pkg.provides,
+        "conflicts" => &mut pkg.conflicts,
+        "obsoletes" => &mut pkg.obsoletes,
+        "recommends" => &mut pkg.recommends,
+        "suggests" => &mut pkg.suggests,
+        "supplements" => &mut pkg.supplements,
+        _ => &mut pkg.enhances,
+    }
+}
+
+fn add_constraint(table: &mut BTreeMap<String, Constraints>, name: &str, constraint: String) {
+    let constraints = match table.remove(name) {
+        Option::None => Constraints::One(constraint),
+        Option::Some(Constraints::One(first)) => Constraints::Many(vec![first, constraint]),
+        Option::Some(Constraints::Many(mut all)) => {
+            all.push(constraint);
+            Constraints::Many(all)
+        }
+    };
+    table.insert(name.to_string(), constraints);
+}
+
+/// splits on whitespace, and commas when `commas` is set, outside of
+/// parentheses, braces and quotes
+fn split_tokens(line: &str, commas: bool) -> Vec<String> {
+    let mut tokens = Vec::new();
+    let mut current = String::new();
+    let mut depth = 0usize;
+    let mut quoted = false;
+    for c in line.chars() {
+        match c {
+            '(' | '{' if !quoted => depth += 1,
+            ')' | '}' if !quoted => depth = depth.saturating_sub(1),
+            '"' => quoted = !quoted,
+            c if depth == 0 && !quoted && (c.is_whitespace() || (commas && c == ',')) => {
+                if !current.is_empty() {
+                    tokens.push(replace(&mut current, String::new()));
+                }
+                continue;
+            }
+            _ => {}
+        };
+        current.push(c);
+    }
+    if !current.is_empty() {
+        tokens.push(current);
+    }
+    tokens
+}
+
+/// the index of the brace closing the one `text` starts with
+fn closing_brace(text: &str) -> Option<usize> {
+    let mut depth = 0;
+    for (idx, c) in text.char_indices() {
+        match c {
+            '{' => depth += 1,
+            '}' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Option::Some(idx);
+                }
+            }
+            _ => {}
+        };
+    }
+    Option::None
+}
+
+fn is_glob(path: &str) -> bool {
+    path.contains(|c| c == '*' || c == '?' || c == '[')
+}
+
+/// the glob a `%files` path is read as, and whether it has wild cards.
+/// rpm reads `*`, `?` and `[` as wild cards unless a `\` escapes them,
+/// escaped characters are matched literally.
+fn pattern(path: &str) -> (String, bool) {
+    let mut output = String::with_capacity(path.len());
+    let mut glob = false;
+    let mut chars = path.chars();
+    while let Option::Some(c) = chars.next() {
+        match c {
+            '\\' => {
+                if let Option::Some(escaped) = chars.next() {
+                    output.push_str(&Pattern::escape(&escaped.to_string()));
+                }
+            }
+            '*' | '?' | '[' => {
+                glob = true;
+                output.push(c);
+            }
+            // a `]` closes a class, or is literal without one
+            ']' if !glob => output.push_str("[]]"),
+            c => output.push(c),
+        };
+    }
+    (output, glob)
+}
+
+/// the leading components of a glob which contain no wild cards
+fn fixed_prefix(path: &str) -> String {
+    path.split('/')
+        .take_while(|c| !is_glob(c))
+        .collect::<Vec<&str>>()
+        .join("/")
+}
+
+/// Operand is a value within a conditional expression
+enum Operand {
+    Int(i64),
+    Str(String),
+}
+impl Operand {
+    fn truthy(&self) -> bool {
+        match self {
+            &Operand::Int(value) => value != 0,
+            &Operand::Str(ref value) => !value.is_empty(),
+        }
+    }
+}
+
+#[derive(PartialEq)]
+enum Token {
+    Int(i64),
+    Str(String),
+    Op(&'static str),
+}
+
+/// longer operators first, so `<=` is not read as `<`
+const OPERATORS: &[&str] = &[
+    "||", "&&", "==", "!=", "<=", ">=", "<", ">", "!", "-", "(", ")",
+];
+
+/// evaluates an expanded `%if` expression as rpm does: integers, quoted
+/// strings, comparisons, `!`, `&&`, `||` and parentheses. `None` when the
+/// expression uses anything else.
+fn evaluate(expr: &str) -> Option<bool> {
+    let mut tokens = Vec::new();
+    let mut rest = expr.trim_start();
+    while !rest.is_empty() {
+        if let Option::Some(op) = OPERATORS.iter().find(|op| rest.starts_with(**op)) {
+            tokens.push(Token::Op(op));
+            rest = &rest[op.len()..];
+        } else if let Option::Some(quoted) = rest.strip_prefix('"') {
+            let end = quoted.find('"')?;
+            tokens.push(Token::Str(quoted[..end].to_string()));
+            rest = &quoted[end + 1..];
+        } else {
+            let len = rest
+                .find(|c: char| !c.is_ascii_digit())
+                .unwrap_or(rest.len());
+            tokens.push(Token::Int(rest[..len].parse().ok()?));
+            rest = &rest[len..];
+        }
+        rest = rest.trim_start();
+    }
+
+    let mut pos = 0;
+    let value = or_expr(&tokens, &mut pos)?;
+    if pos == tokens.len() {
+        Option::Some(value.truthy())
+    } else {
+        Option::None
+    }
+}
+
+fn or_expr(tokens: &[Token], pos: &mut usize) -> Option<Operand> {
+    let mut left = and_expr(tokens, pos)?;
+    while tokens.get(*pos) == Option::Some(&Token::Op("||")) {
+        *pos += 1;
+        let right = and_expr(tokens, pos)?;
+        left = Operand::Int((left.truthy() || right.truthy()) as i64);
+    }
+    Option::Some(left)
+}
+
+fn and_expr(tokens: &[Token], pos: &mut usize) -> Option<Operand> {
+    let mut left = comparison(tokens, pos)?;
+    while tokens.get(*pos) == Option::Some(&Token::Op("&&")) {
+        *pos += 1;
+        let right = comparison(tokens, pos)?;
+        left = Operand::Int((left.truthy() && right.truthy()) as i64);
+    }
+    Option::Some(left)
+}
+
+fn comparison(tokens: &[Token], pos: &mut usize) -> Option<Operand> {
+    let left = unary(tokens, pos)?;
+    let op = match tokens.get(*pos) {
+        Option::Some(&Token::Op(op)) if ["==", "!=", "<", "<=", ">", ">="].contains(&op) => op,
+        _ => return Option::Some(left),
+    };
+    *pos += 1;
+    let right = unary(tokens, pos)?;
+    let ordering = match (&left, &right) {
+        (&Operand::Int(a), &Operand::Int(b)) => a.cmp(&b),
+        (&Operand::Str(ref a), &Operand::Str(ref b)) => a.cmp(b),
+        _ => return Option::None,
+    };
+    let outcome = match op {
+        "==" => ordering.is_eq(),
+        "!=" => ordering.is_ne(),
+        "<" => ordering.is_lt(),
+        "<=" => ordering.is_le(),
+        ">" => ordering.is_gt(),
+        _ => ordering.is_ge(),
+    };
+    Option::Some(Operand::Int(outcome as i64))
+}
+
+fn unary(tokens: &[Token], pos: &mut usize) -> Option<Operand> {
+    let token = tokens.get(*pos)?;
+    *pos += 1;
+    match token {
+        &Token::Int(value) => Option::Some(Operand::Int(value)),
+        &Token::Str(ref value) => Option::Some(Operand::Str(value.clone())),
+        &Token::Op("!") => Option::Some(Operand::Int(!unary(tokens, pos)?.truthy() as i64)),
+        &Token::Op("-") => match unary(tokens, pos)? {
+            Operand::Int(value) => Option::Some(Operand::Int(-value)),
+            Operand::Str(_) => Option::None,
+        },
+        &Token::Op("(") => {
+            let value = or_expr(tokens, pos)?;
+            if tokens.get(*pos) != Option::Some(&Token::Op(")")) {
+                return Option::None;
+            }
+            *pos += 1;
+            Option::Some(value)
+        }
+        _ => Option::None,
+    }
+}
+
+/// joins the lines of a section, dropping leading and trailing blank lines
+fn join_body(body: &[&str]) -> String {
+    body.join("\n").trim_matches('\n').trim_end().to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// translates `spec`, returning the config, the warnings and the
+    /// directory scripts were written to
+    fn translate(spec: &str) -> (ConfigFile, Vec<String>, tempfile::TempDir) {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("demo.spec");
+        write(&path, spec).unwrap();
+        let (config, warnings) = from_spec(&path.to_string_lossy(), dir.path()).unwrap();
+        (config, warnings, dir)
+    }
+
+    fn parser() -> Parser {
+        let mut parser = Parser::default();
+        for (name, value) in DEFAULT_MACROS.iter() {
+            parser.macros.insert(name.to_string(), value.to_string());
+        }
+        parser
+    }
+
+    fn constraints(table: &BTreeMap<String, Constraints>, name: &str) -> Vec<String> {
+        match table.get(name) {
+            Option::None => panic!("{} is missing from {:?}", name, table.keys()),
+            Option::Some(constraints) => constraints.each().iter().map(|c| c.to_string()).collect(),
+        }
+    }
+
+    fn file<'a>(contents: &'a BTreeMap<String, FileOptions>, key: &str) -> &'a ComplexFileOptions {
+        match contents.get(key).and_then(|opts| opts.complex()) {
+            Option::None => panic!("{} is missing from {:?}", key, contents.keys()),
+            Option::Some(opts) => opts,
+        }
+    }
+
+    const PREAMBLE: &str = "\
+%global upstream 2.4
+Name:           demo
+Version:        %{upstream}
+Release:        3%{?dist}
+Epoch:          1
+Summary:        A demo package
+License:        MIT
+BuildArch:      noarch
+Source0:        https://example.com/demo-%{version}.tar.gz
+Requires:       bash >= 4.0, coreutils
+Requires(pre):  shadow-utils
+Requires(post): systemd
+Requires(preun,postun): systemd
+Provides:       demo-tools = %{version}
+Obsoletes:      old-demo < 2
+Requires:       (foo or bar)
+
+%description
+The long description
+of the demo.
+
+%package devel
+Requires:       demo-libs
+
+%description devel
+Headers for demo.
+";
+
+    #[test]
+    fn preamble_is_translated() {
+        let (config, warnings, _dir) = translate(PREAMBLE);
+        assert_eq!(config.rpm.name, "demo");
+        assert_eq!(config.rpm.version, "2.4");
+        assert_eq!(config.rpm.release, Option::Some(3));
+        assert_eq!(config.rpm.epoch, Option::Some(1));
+        assert_eq!(config.rpm.desc, "A demo package");
+        assert_eq!(
+            config.rpm.description.as_deref(),
+            Option::Some("The long description\nof the demo.")
+        );
+        assert_eq!(config.rpm.license, "MIT");
+        assert_eq!(config.rpm.arch, "noarch");
+        assert_eq!(config.srpm.unwrap().sources, vec!["demo-2.4.tar.gz"]);
+
+        assert_eq!(constraints(&config.requires, "bash"), vec![">= 4.0"]);
+        assert_eq!(constraints(&config.requires, "coreutils"), vec![""]);
+        assert_eq!(constraints(&config.requires_pre, "shadow-utils"), vec![""]);
+        assert_eq!(constraints(&config.requires_post, "systemd"), vec![""]);
+        assert_eq!(constraints(&config.requires_preun, "systemd"), vec![""]);
+        assert_eq!(constraints(&config.requires_postun, "systemd"), vec![""]);
+        assert!(!config.requires.contains_key("shadow-utils"));
+        assert_eq!(constraints(&config.provides, "demo-tools"), vec!["= 2.4"]);
+        assert_eq!(constraints(&config.obsoletes, "old-demo"), vec!["< 2"]);
+        assert_eq!(config.rich_requires, vec!["(foo or bar)"]);
+
+        // a sub package without a Summary takes its %description
+        let devel = &config.packages["devel"];
+        assert_eq!(devel.desc.as_deref(), Option::Some("Headers for demo."));
+        assert_eq!(devel.description, Option::None);
+        assert_eq!(constraints(&devel.requires, "demo-libs"), vec![""]);
+        assert!(warnings.is_empty(), "{:?}", warnings);
+    }
+
+    #[test]
+    fn conditionals_are_evaluated() {
+        let spec = format!(
+            "\
+Name: demo
+Version: 1.0
+%define fedora 39
+%bcond_without docs
+%bcond_with tests
+%if 0%{{?fedora}} >= 38
+Requires: modern
+%else
+Requires: legacy
+%endif
+%if 0%{{?rhel}}
+Requires: rhel
+%elif %{{with docs}} && !%{{with tests}}
+Requires: docs
+%else
+Requires: neither
+%endif
+%if 1
+%if 0
+Requires: inner
+%endif
+%endif
+%ifarch {} other
+Requires: native
+%endif
+%ifnarch {}
+Requires: foreign
+%endif
+%if %{{undefined_condition}} == 1
+Requires: guessed
+%else
+Requires: dropped
+%endif
+%if \"%{{name}}\" == \"demo\"
+Requires: named
+%endif
+",
+            ARCH, ARCH
+        );
+        let (config, warnings, _dir) = translate(&spec);
+        let requires: Vec<&str> = config.requires.keys().map(|k| k.as_str()).collect();
+        assert_eq!(
+            requires,
+            vec!["docs", "guessed", "modern", "named", "native"]
+        );
+        assert!(warnings
+            .iter()
+            .any(|w| w.contains("could not be evaluated, its first branch was translated")));
+        assert!(warnings
+            .iter()
+            .any(|w| w.contains("'Requires: dropped' is within a branch")));
+        assert!(!warnings.iter().any(|w| w.contains("'Requires: legacy'")));
+    }
+
+    #[test]
+    fn conditional_expressions() {
+        let cases = [
+            ("1", true),
+            ("0", false),
+            ("2 > 1 && 3 <= 3", true),
+            ("1 == 2 || (4 != 4)", false),
+            ("!0", true),
+            ("\"a\" == \"a\"", true),
+            ("\"a\" != \"b\" && !(1 > 2)", true),
+        ];
+        for (expr, expected) in cases.iter() {
+            assert_eq!(evaluate(expr), Option::Some(*expected), "{}", expr);
+        }
+        assert_eq!(evaluate("1 >"), Option::None);
+        assert_eq!(evaluate("(1"), Option::None);
+    }
+
+    #[test]
+    fn conditional_macros_expand() {
+        let mut parser = parser();
+        parser.macros.insert("set".to_string(), "value".to_string());
+        parser
+            .macros
+            .insert("nested".to_string(), "%{set}-%{_bindir}".to_string());
+        parser
+            .macros
+            .insert("loop".to_string(), "%{loop}".to_string());
+        let cases = [
+            ("%{?set:yes}", "yes"),
+            ("%{?unset:yes}", ""),
+            ("%{!?set:no}", ""),
+            ("%{!?unset:no}", "no"),
+            ("%{?set}", "value"),
+            ("%{?unset}", ""),
+            ("%{?set:%{nested}}", "value-/usr/bin"),
+            ("%set and %{set}", "value and value"),
+            ("100%%", "100%"),
+            ("%{defined set} %{undefined set}", "1 0"),
+        ];
+        for (text, expected) in cases.iter() {
+            assert_eq!(parser.expand(text), *expected, "{}", text);
+        }
+        assert!(parser.warnings.is_empty(), "{:?}", parser.warnings);
+
+        assert_eq!(parser.expand("%{unset}"), "%{unset}");
+        assert!(parser.expand("%{loop}").contains("%{loop}"));
+        assert!(parser
+            .warnings
+            .iter()
+            .any(|w| w.contains("expands too deeply")));
+    }
+
+    #[test]
+    fn files_are_translated() {
+        let (config, warnings, _dir) = translate(
+            "\
+Name: demo
+Version: 1.0
+
+%files
+%defattr(-,root,root)
+%attr(0755,root,wheel) %{_bindir}/demo
+%config(noreplace) %{_sysconfdir}/demo.conf
+%doc README.md
+%license LICENSE
+%dir %{_localstatedir}/lib/demo
+%{_datadir}/demo/*
+%exclude %{_datadir}/demo/private
+%{_datadir}/demo-notes/notes\\[1\\].txt
+",
+        );
+        let bin = file(&config.contents, "root/usr/bin/demo");
+        assert_eq!(bin.dst, "/usr/bin/demo");
+        assert_eq!(bin.mode, Option::Some(0o755));
+        assert_eq!(bin.user.as_deref(), Option::Some("root"));
+        assert_eq!(bin.group.as_deref(), Option::Some("wheel"));
+
+        let conf = file(&config.contents, "root/etc/demo.conf");
+        assert_eq!(conf.config, Option::Some(true));
+        assert_eq!(conf.mode, Option::None);
+        assert_eq!(conf.user.as_deref(), Option::Some("root"));
+
+        let readme = file(&config.contents, "README.md");
+        assert_eq!(readme.dst, "/usr/share/doc/demo/README.md");
+        assert_eq!(readme.doc, Option::Some(true));
+        let license = file(&config.contents, "LICENSE");
+        assert_eq!(license.dst, "/usr/share/licenses/demo/LICENSE");
+
+        let data = file(&config.contents, "root/usr/share/demo/*");
+        assert_eq!(data.dst, "/usr/share/demo");
+        assert_eq!(data.exclude, Option::Some(vec!["private".to_string()]));
+
+        // escaped wild cards are literal, and stay literal as a key
+        let notes = file(
+            &config.contents,
+            "root/usr/share/demo-notes/notes[[]1[]].txt",
+        );
+        assert_eq!(notes.dst, "/usr/share/demo-notes/notes[1].txt");
+
+        assert!(!config.contents.keys().any(|k| k.contains("var/lib")));
+        assert!(warnings.iter().any(|w| w.contains("%dir /var/lib/demo")));
+        assert!(warnings.iter().any(|w| w.contains("%config(noreplace)")));
+    }
+
+    #[test]
+    fn changelog_is_translated() {
+        let (config, warnings, _dir) = translate(
+            "\
+Name: demo
+Version: 1.0
+
+%changelog
+* Tue Jan 03 2023 Dev <dev@example.com> - 1.0-2
+- fixed 100%% of the bugs
+- second line
+
+* Mon Jan 02 2023 Dev <dev@example.com> - 1.0-1
+- first
+* Someday Dev <dev@example.com>
+- unreadable
+",
+        );
+        let entries: Vec<(String, &str, &str)> = config
+            .changelog
+            .iter()
+            .map(|(when, e)| (when.to_string(), e.author.as_str(), e.entry.as_str()))
+            .collect();
+        assert_eq!(
+            entries,
+            vec![
+                (
+                    "2023-01-02 00:00:00".to_string(),
+                    "Dev <dev@example.com> - 1.0-1",
+                    "- first"
+                ),
+                (
+                    "2023-01-03 00:00:00".to_string(),
+                    "Dev <dev@example.com> - 1.0-2",
+                    "- fixed 100% of the bugs\n- second line"
+                ),
+            ]
+        );
+        assert!(warnings
+            .iter()
+            .any(|w| w.contains("'* Someday Dev <dev@example.com>' has no readable date")));
+    }
+
+    #[test]
+    fn scriptlets_are_written() {
+        let (config, _, dir) = translate(
+            "\
+Name: demo
+Version: 1.0
+
+%post
+echo %{name} $1
+
+%postun -p /sbin/ldconfig
+",
+        );
+        let scripts = config.scripts.unwrap();
+        assert_eq!(
+            scripts.post_install.as_deref(),
+            Option::Some("scripts/post_install.sh")
+        );
+        let post = read_to_string(dir.path().join("scripts/post_install.sh")).unwrap();
+        assert_eq!(post, "echo demo $1\n");
+        let postun = read_to_string(dir.path().join("scripts/post_uninstall.sh")).unwrap();
+        assert_eq!(postun, "/sbin/ldconfig\n");
+    }
+}
@@ -0,0 +1,79 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use super::errors::Err;
+use super::glob::Pattern;
+use super::serde::{Deserialize, Serialize};
+
+use super::core::ConfigFile;
+use super::fileopts::{ComplexFileOptions, FileOptions};
+use super::stream::StreamPackage;
+use super::subpackage::SubPackage;
+
+/// Srpm declares the sources which are packaged, alongside the config and
+/// its scripts, into a source RPM.
+#[derive(Clone, Hash, Debug, Deserialize, Serialize, Default)]
+pub struct Srpm {
+    #[serde(default)]
+    pub sources: Vec<String>,
+    #[serde(default)]
+    pub patches: Vec<String>,
+}
+impl Srpm {
+    /// builds the source RPM for a config read from `config_path`. Source
+    /// packages need a header the rpm crate can not write, `SOURCEPACKAGE`
+    /// and the `src` arch, so they are written by `StreamPackage`.
+    pub fn build<'a>(config: &'a ConfigFile, config_path: &str) -> Result<StreamPackage<'a>, Err> {
+        let err = Err::default()
+            .note("srpm", &config.rpm.name)
+            .note("version", &config.rpm.version);
+
+        let mut files: Vec<&str> = vec![config_path];
+        files.extend(config.scripts.iter().flat_map(|s| s.paths()));
+        for sub in config.packages.values() {
+            files.extend(sub.scripts.iter().flat_map(|s| s.paths()));
+        }
+        if let Option::Some(ref srpm) = config.srpm {
+            files.extend(srpm.sources.iter().map(|s| s.as_str()));
+            files.extend(srpm.patches.iter().map(|s| s.as_str()));
+        }
+
+        // source rpms are flat, every file is stored by its file name
+        let mut by_name: BTreeMap<String, &str> = BTreeMap::new();
+        for path in files {
+            let name = match Path::new(path).file_name() {
+                Option::Some(name) => name.to_string_lossy().into_owned(),
+                Option::None => {
+                    return Err(err.clone().note("source path has no file name", path));
+                }
+            };
+            match by_name.get(&name) {
+                Option::Some(existing) if *existing != path => {
+                    return Err(err
+                        .clone()
+                        .note("duplicate source file name", &name)
+                        .note("first", existing)
+                        .note("second", path));
+                }
+                _ => {}
+            };
+            by_name.insert(name, path);
+        }
+
+        // contents are read back as globs, so wild cards in paths are escaped
+        let mut pkg = SubPackage {
+            description: config.rpm.description.clone(),
+            ..SubPackage::default()
+        };
+        for (name, path) in by_name {
+            let opts = ComplexFileOptions {
+                dst: format!("/{}", name),
+                mode: Option::Some(0o644),
+                ..ComplexFileOptions::default()
+            };
+            pkg.contents
+                .insert(Pattern::escape(path), FileOptions::Complex(opts));
+        }
+        Ok(StreamPackage::source(config, pkg, err))
+    }
+}
@@ -0,0 +1,926 @@
+use std::collections::{BTreeMap, VecDeque};
+use std::convert::TryFrom;
+use std::fs::{metadata, read_to_string, File};
+use std::io::{copy, BufWriter, Error as IOError, Read, Result as IOResult, Seek, SeekFrom, Write};
+use std::os::unix::fs::MetadataExt;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{channel, sync_channel, Receiver, Sender, SyncSender};
+use std::sync::{Arc, Mutex};
+use std::thread::{scope, Scope};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use super::errors::Err;
+use super::flate2::write::GzEncoder;
+use super::flate2::Compression as GzLevel;
+use super::rpm::Dependency;
+use super::sha2::{Digest, Sha256};
+use super::tempfile::tempfile;
+use super::xz2::stream::{Check, MtStreamBuilder};
+use super::xz2::write::XzEncoder;
+use super::zstd::stream::write::Encoder as ZstdEncoder;
+
+use super::changelog::timestamp;
+use super::core::ConfigFile;
+use super::cpio::{padding, write_stripped, write_trailer, Entry};
+use super::fileopts::ComplexFileOptions;
+use super::header::{self, Header, Value};
+use super::rpm_meta::Algorithm;
+use super::scripts::Scriptlet;
+use super::subpackage::{Kind, SubPackage};
+
+const READ_BUFFER: usize = 64 * 1024;
+
+/// chunks buffered between a reader and the payload writer, per file
+const CHUNKS_IN_FLIGHT: usize = 4;
+
+/// files dispatched ahead of the payload writer, per job
+const FILES_IN_FLIGHT: usize = 2;
+
+/// StreamPackage writes an RPM without holding its payload in memory.
+///
+/// Every file is read once, digested, and compressed straight into an
+/// anonymous temporary file. Once the header (which records the digests)
+/// is known the lead, signature, header, and payload are copied to the
+/// output. Memory use is bounded by the number of files, not their size.
+///
+/// Files are read and digested by `jobs` threads, and zstd and xz compress
+/// with `jobs` threads. The output does not depend upon `jobs`.
+pub struct StreamPackage<'a> {
+    config: &'a ConfigFile,
+    name: String,
+    desc: String,
+    pkg: SubPackage,
+    /// source packages are flat, and carry neither dependencies nor scripts
+    source: bool,
+    err: Err,
+}
+impl<'a> StreamPackage<'a> {
+    pub fn new(
+        config: &'a ConfigFile,
+        name: &str,
+        desc: &str,
+        pkg: SubPackage,
+        err: Err,
+    ) -> StreamPackage<'a> {
+        StreamPackage {
+            config,
+            name: name.to_string(),
+            desc: desc.to_string(),
+            pkg,
+            source: false,
+            err,
+        }
+    }
+
+    /// the source package of `config`, its payload holds the contents of
+    /// `pkg` which must each be installed at `/<file name>`
+    pub fn source(config: &'a ConfigFile, pkg: SubPackage, err: Err) -> StreamPackage<'a> {
+        StreamPackage {
+            config,
+            name: config.rpm.name.clone(),
+            desc: config.rpm.desc.clone(),
+            pkg,
+            source: true,
+            err,
+        }
+    }
+
+    /// builds the package with `jobs` threads, writing it to `output`.
+    /// Returns a warning for everything which was skipped.
+    pub fn write<W: Write>(&self, output: &mut W, jobs: usize) -> Result<Vec<String>, Err> {
+        let err = &self.err;
+        if self.config.signature.is_some() {
+            let error = if self.source {
+                "source packages can not be signed"
+            } else {
+                "signing is not supported when streaming"
+            };
+            return Err(err.clone().note("error", error));
+        }
+        let epoch = self.config.source_date_epoch(err)?;
+        let compression = self.config.rpm.compression(err)?;
+        let (algo, level) = (compression.algo, compression.level(err)?);
+
+        // files are written in sorted order so the payload is reproducible,
+        // symlinks have no contents to read
+        let sources = self.pkg.sources(&self.err)?;
+        let reads: Vec<PathBuf> = sources
+            .values()
+            .filter(|(_, opts)| opts.symlink.is_none())
+            .map(|(src, _)| src.clone())
+            .collect();
+        let large = has_large_file(&reads, err)?;
+        let (files, payload) = scope(|scope| -> Result<(Vec<FileEntry>, Finished), Err> {
+            let mut readers = Readers::spawn(scope, &reads, jobs);
+            let mut payload = Payload::new(algo, level, jobs, large)
+                .map_err(|e| err.clone().note("failed to create payload", e))?;
+            let mut files = Vec::with_capacity(sources.len());
+            for (ino, (dst, (src, opts))) in sources.iter().enumerate() {
+                let ino = ino as u32 + 1;
+                let dst = if self.source {
+                    dst.trim_start_matches('/')
+                } else {
+                    dst.as_str()
+                };
+                files.push(payload.add(ino, dst, src, opts, epoch, &mut readers, err)?);
+            }
+            let payload = payload
+                .finish()
+                .map_err(|e| err.clone().note("failed to finish payload", e))?;
+            Ok((files, payload))
+        })?;
+
+        let mut warnings = Vec::new();
+        let encoded = self
+            .header(&files, &payload, epoch, algo, level, large, &mut warnings)?
+            .encode(header::HEADERIMMUTABLE);
+        let signature = signature(&encoded, &payload);
+
+        let write_err = |e: IOError| err.clone().note("failed to write rpm", e);
+        output
+            .write_all(&header::lead(&self.lead_name(), self.source))
+            .map_err(write_err)?;
+        output.write_all(&signature).map_err(write_err)?;
+        output.write_all(&encoded).map_err(write_err)?;
+        let mut file = payload.file;
+        copy(&mut file, output).map_err(write_err)?;
+        Ok(warnings)
+    }
+
+    fn release(&self) -> String {
+        match self.config.rpm.release {
+            Option::None => "1".to_string(),
+            Option::Some(release) => release.to_string(),
+        }
+    }
+
+    fn evr(&self) -> String {
+        match self.config.rpm.epoch {
+            Option::None => format!("{}-{}", self.config.rpm.version, self.release()),
+            Option::Some(epoch) => {
+                format!("{}:{}-{}", epoch, self.config.rpm.version, self.release())
+            }
+        }
+    }
+
+    fn lead_name(&self) -> String {
+        format!(
+            "{}-{}-{}",
+            self.name,
+            self.config.rpm.version,
+            self.release()
+        )
+    }
+
+    fn header(
+        &self,
+        files: &[FileEntry],
+        payload: &Finished,
+        epoch: Option<u32>,
+        algo: Algorithm,
+        level: i32,
+        large: bool,
+        warnings: &mut Vec<String>,
+    ) -> Result<Header, Err> {
+        use self::Value::*;
+
+        let rpm = &self.config.rpm;
+        let mut h = Header::default();
+        h.insert(header::HEADERI18NTABLE, StrArray(vec!["C".to_string()]));
+
+        // package metadata
+        h.insert(header::NAME, Str(self.name.clone()));
+        h.insert(header::VERSION, Str(rpm.version.clone()));
+        h.insert(header::RELEASE, Str(self.release()));
+        if let Option::Some(e) = rpm.epoch {
+            h.insert(header::EPOCH, Int32(vec![e]));
+        }
+        h.insert(header::SUMMARY, I18n(self.desc.clone()));
+        let description = self.pkg.description.as_ref().unwrap_or(&self.desc);
+        h.insert(header::DESCRIPTION, I18n(description.clone()));
+        h.insert(header::LICENSE, Str(rpm.license.clone()));
+        h.insert(header::GROUP, I18n("Unspecified".to_string()));
+        h.insert(header::OS, Str("linux".to_string()));
+        if self.source {
+            h.insert(header::ARCH, Str("src".to_string()));
+            h.insert(header::SOURCEPACKAGE, Int32(vec![1]));
+        } else {
+            h.insert(header::ARCH, Str(rpm.arch.clone()));
+        }
+        h.insert(
+            header::RPMVERSION,
+            Str(concat!("rpmbuilder ", env!("CARGO_PKG_VERSION")).to_string()),
+        );
+        h.insert(header::ENCODING, Str("utf-8".to_string()));
+        match epoch {
+            Option::Some(epoch) => {
+                h.insert(header::BUILDTIME, Int32(vec![epoch]));
+                h.insert(header::BUILDHOST, Str("localhost".to_string()));
+            }
+            Option::None => {
+                h.insert(header::BUILDTIME, Int32(vec![now()]));
+                h.insert(header::BUILDHOST, Str(hostname()));
+            }
+        };
+        if self.config.srpm.is_some() && !self.source {
+            h.insert(header::SOURCERPM, Str(self.config.srpm_file_name()));
+        }
+
+        // payload
+        h.insert(header::PAYLOADFORMAT, Str("cpio".to_string()));
+        h.insert(header::PAYLOADCOMPRESSOR, Str(algo.as_str().to_string()));
+        let flags = match algo {
+            Algorithm::None => String::new(),
+            _ => level.to_string(),
+        };
+        h.insert(header::PAYLOADFLAGS, Str(flags));
+        h.insert(
+            header::PAYLOADDIGEST,
+            StrArray(vec![payload.digest.clone()]),
+        );
+        h.insert(
+            header::PAYLOADDIGESTALGO,
+            Int32(vec![header::DIGEST_SHA256]),
+        );
+
+        // files
+        let size: u64 = files.iter().map(|f| f.size).sum();
+        if size > u32::MAX as u64 {
+            h.insert(header::LONGSIZE, Int64(vec![size]));
+        } else {
+            h.insert(header::SIZE, Int32(vec![size as u32]));
+        }
+        let mut dirs: BTreeMap<String, u32> = BTreeMap::new();
+        let mut dirnames = Vec::new();
+        let mut dirindexes = Vec::with_capacity(files.len());
+        let mut basenames = Vec::with_capacity(files.len());
+        for file in files {
+            let split = file.path.rfind('/').map(|i| i + 1).unwrap_or(0);
+            let (dir, base) = file.path.split_at(split);
+            let next = dirnames.len() as u32;
+            let index = *dirs.entry(dir.to_string()).or_insert(next);
+            if index == next {
+                dirnames.push(dir.to_string());
+            }
+            dirindexes.push(index);
+            basenames.push(base.to_string());
+        }
+        h.insert(header::DIRNAMES, StrArray(dirnames));
+        h.insert(header::DIRINDEXES, Int32(dirindexes));
+        h.insert(header::BASENAMES, StrArray(basenames));
+        // rpm records every size as 64 bits once any file needs it
+        if large {
+            h.insert(
+                header::LONGFILESIZES,
+                Int64(files.iter().map(|f| f.size).collect()),
+            );
+        } else {
+            h.insert(
+                header::FILESIZES,
+                Int32(files.iter().map(|f| f.size as u32).collect()),
+            );
+        }
+        h.insert(
+            header::FILEMODES,
+            Int16(files.iter().map(|f| f.mode).collect()),
+        );
+        h.insert(header::FILERDEVS, Int16(files.iter().map(|_| 0).collect()));
+        h.insert(
+            header::FILEMTIMES,
+            Int32(files.iter().map(|f| f.mtime).collect()),
+        );
+        h.insert(
+            header::FILEDIGESTS,
+            StrArray(files.iter().map(|f| f.digest.clone()).collect()),
+        );
+        h.insert(
+            header::FILELINKTOS,
+            StrArray(files.iter().map(|f| f.linkto.clone()).collect()),
+        );
+        h.insert(
+            header::FILEFLAGS,
+            Int32(files.iter().map(|f| f.flags).collect()),
+        );
+        h.insert(
+            header::FILEUSERNAME,
+            StrArray(files.iter().map(|f| f.user.clone()).collect()),
+        );
+        h.insert(
+            header::FILEGROUPNAME,
+            StrArray(files.iter().map(|f| f.group.clone()).collect()),
+        );
+        h.insert(
+            header::FILEVERIFYFLAGS,
+            Int32(files.iter().map(|_| u32::MAX).collect()),
+        );
+        h.insert(
+            header::FILEDEVICES,
+            Int32(files.iter().map(|_| 1).collect()),
+        );
+        h.insert(
+            header::FILEINODES,
+            Int32((1..=files.len() as u32).collect()),
+        );
+        h.insert(
+            header::FILELANGS,
+            StrArray(files.iter().map(|_| String::new()).collect()),
+        );
+        h.insert(header::FILEDIGESTALGO, Int32(vec![header::DIGEST_SHA256]));
+
+        // dependencies
+        let deps = self.dependencies(algo, large, warnings)?;
+        for kind in Kind::ALL.iter() {
+            let (name_tag, flags_tag, version_tag) = tags(*kind);
+            let deps: Vec<&Dependency> = deps
+                .iter()
+                .filter(|(k, _)| k == kind)
+                .map(|(_, dep)| dep)
+                .collect();
+            h.insert(
+                name_tag,
+                StrArray(deps.iter().map(|dep| dep.name.clone()).collect()),
+            );
+            h.insert(
+                flags_tag,
+                Int32(deps.iter().map(|dep| dep.flags.bits()).collect()),
+            );
+            h.insert(
+                version_tag,
+                StrArray(deps.iter().map(|dep| dep.version.clone()).collect()),
+            );
+        }
+
+        // scripts
+        if let Option::Some(ref scripts) = self.pkg.scripts {
+            for (scriptlet, script) in scripts.read_all(&self.err)? {
+                let (tag, prog) = script_tags(scriptlet);
+                h.insert(tag, Str(script));
+                h.insert(prog, Str("/bin/sh".to_string()));
+            }
+        }
+
+        // change log, newest first
+        let changelog: Vec<_> = self.config.changelog.iter().rev().collect();
+        h.insert(
+            header::CHANGELOGTIME,
+            Int32(
+                changelog
+                    .iter()
+                    .map(|(when, _)| timestamp(when, &self.err))
+                    .collect::<Result<_, _>>()?,
+            ),
+        );
+        h.insert(
+            header::CHANGELOGNAME,
+            StrArray(changelog.iter().map(|(_, e)| e.author.clone()).collect()),
+        );
+        h.insert(
+            header::CHANGELOGTEXT,
+            StrArray(changelog.iter().map(|(_, e)| e.entry.clone()).collect()),
+        );
+
+        Ok(h)
+    }
+
+    /// every dependency of the package, as `SubPackage` declares them, and
+    /// those the rpm crate adds itself such as the package providing its
+    /// own name. Source packages only require the features of their payload.
+    fn dependencies(
+        &self,
+        algo: Algorithm,
+        large: bool,
+        warnings: &mut Vec<String>,
+    ) -> Result<Vec<(Kind, Dependency)>, Err> {
+        let mut deps = Vec::new();
+        if !self.source {
+            let auto_deps = self.config.rpm.auto_deps == Option::Some(true);
+            deps.extend(self.pkg.dependencies(auto_deps, warnings, &self.err)?);
+
+            // the package provides itself
+            let evr = self.evr();
+            deps.push((
+                Kind::Provides,
+                Dependency::eq(self.name.as_str(), evr.as_str()),
+            ));
+            deps.push((
+                Kind::Provides,
+                Dependency::eq(
+                    format!("{}({})", self.name, self.config.rpm.arch),
+                    evr.as_str(),
+                ),
+            ));
+        }
+
+        // features of the payload which the installing rpm must support
+        let mut features = vec![
+            ("rpmlib(CompressedFileNames)", "3.0.4-1"),
+            ("rpmlib(FileDigests)", "4.6.0-1"),
+        ];
+        if !self.source {
+            features.push(("rpmlib(PayloadFilesHavePrefix)", "4.0-1"));
+        }
+        match algo {
+            Algorithm::Xz => features.push(("rpmlib(PayloadIsXz)", "5.2-1")),
+            Algorithm::Zstd => features.push(("rpmlib(PayloadIsZstd)", "5.4.18-1")),
+            _ => {}
+        };
+        if large {
+            features.push(("rpmlib(LargeFiles)", "4.12.0-1"));
+        }
+        for (name, version) in features {
+            deps.push((Kind::Requires, Dependency::rpmlib(name, version)));
+        }
+        Ok(deps)
+    }
+}
+
+/// the signature header, which records the digest of the header and the
+/// size of the package.
+fn signature(encoded: &[u8], payload: &Finished) -> Vec<u8> {
+    let mut h = Header::default();
+    h.insert(
+        header::SIGTAG_SHA256,
+        Value::Str(hex(&Sha256::digest(encoded))),
+    );
+    let total = encoded.len() as u64 + payload.size;
+    if total > u32::MAX as u64 {
+        h.insert(header::SIGTAG_LONGSIZE, Value::Int64(vec![total]));
+    } else {
+        h.insert(header::SIGTAG_SIZE, Value::Int32(vec![total as u32]));
+    }
+    if payload.archive_size > u32::MAX as u64 {
+        h.insert(
+            header::SIGTAG_LONGARCHIVESIZE,
+            Value::Int64(vec![payload.archive_size]),
+        );
+    } else {
+        h.insert(
+            header::SIGTAG_PAYLOADSIZE,
+            Value::Int32(vec![payload.archive_size as u32]),
+        );
+    }
+    h.encode_signature()
+}
+
+/// the name, flags, and version tags of a dependency table
+fn tags(kind: Kind) -> (u32, u32, u32) {
+    match kind {
+        Kind::Requires => (
+            header::REQUIRENAME,
+            header::REQUIREFLAGS,
+            header::REQUIREVERSION,
+        ),
+        Kind::Provides => (
+            header::PROVIDENAME,
+            header::PROVIDEFLAGS,
+            header::PROVIDEVERSION,
+        ),
+        Kind::Conflicts => (
+            header::CONFLICTNAME,
+            header::CONFLICTFLAGS,
+            header::CONFLICTVERSION,
+        ),
+        Kind::Obsoletes => (
+            header::OBSOLETENAME,
+            header::OBSOLETEFLAGS,
+            header::OBSOLETEVERSION,
+        ),
+        Kind::Recommends => (
+            header::RECOMMENDNAME,
+            header::RECOMMENDFLAGS,
+            header::RECOMMENDVERSION,
+        ),
+        Kind::Suggests => (
+            header::SUGGESTNAME,
+            header::SUGGESTFLAGS,
+            header::SUGGESTVERSION,
+        ),
+        Kind::Supplements => (
+            header::SUPPLEMENTNAME,
+            header::SUPPLEMENTFLAGS,
+            header::SUPPLEMENTVERSION,
+        ),
+        Kind::Enhances => (
+            header::ENHANCENAME,
+            header::ENHANCEFLAGS,
+            header::ENHANCEVERSION,
+        ),
+    }
+}
+
+/// the script and interpreter tags of a scriptlet
+fn script_tags(scriptlet: Scriptlet) -> (u32, u32) {
+    match scriptlet {
+        Scriptlet::PreInstall => (header::PREIN, header::PREINPROG),
+        Scriptlet::PostInstall => (header::POSTIN, header::POSTINPROG),
+        Scriptlet::PreUninstall => (header::PREUN, header::PREUNPROG),
+        Scriptlet::PostUninstall => (header::POSTUN, header::POSTUNPROG),
+    }
+}
+
+/// whether any file is too large for a `newc` cpio header, packages
+/// with one record 64 bit sizes and use rpm's stripped cpio format.
+fn has_large_file(paths: &[PathBuf], err: &Err) -> Result<bool, Err> {
+    for path in paths {
+        let meta = metadata(path).map_err(|e| {
+            err.clone()
+                .note("failed to stat file", e)
+                .note("src", path.display())
+        })?;
+        if meta.len() >= u32::MAX as u64 {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+/// FileEntry is what the header records about a packaged file
+struct FileEntry {
+    path: String,
+    size: u64,
+    mode: u16,
+    mtime: u32,
+    digest: String,
+    linkto: String,
+    flags: u32,
+    user: String,
+    group: String,
+}
+
+/// Payload is the cpio archive under construction, compressed into a
+/// temporary file as it is written.
+struct Payload {
+    writer: Digester<Encoder<Digester<BufWriter<File>>>>,
+    /// entries are written with stripped headers, which only record the
+    /// file's index within the header
+    stripped: bool,
+}
+
+/// Finished is the compressed payload, ready to be copied after the header
+struct Finished {
+    file: File,
+    digest: String,
+    size: u64,
+    archive_size: u64,
+}
+
+impl Payload {
+    fn new(algo: Algorithm, level: i32, jobs: usize, stripped: bool) -> IOResult<Payload> {
+        let file = Digester::new(BufWriter::new(tempfile()?));
+        Ok(Payload {
+            writer: Digester::counting(Encoder::new(algo, level, jobs, file)?),
+            stripped,
+        })
+    }
+
+    /// writes the cpio header of the entry, `ino` is one past the file's
+    /// index within the header
+    fn write_header(&mut self, entry: &Entry) -> IOResult<()> {
+        if self.stripped {
+            write_stripped(&mut self.writer, entry.ino - 1)
+        } else {
+            entry.write(&mut self.writer)
+        }
+    }
+
+    /// appends a file to the archive, returning its header record
+    fn add(
+        &mut self,
+        ino: u32,
+        dst: &str,
+        src: &Path,
+        opts: &ComplexFileOptions,
+        epoch: Option<u32>,
+        readers: &mut Readers,
+        err: &Err,
+    ) -> Result<FileEntry, Err> {
+        const S_IFREG: u32 = 0o100000;
+        const S_IFLNK: u32 = 0o120000;
+
+        let err = err.clone().note("src", src.display()).note("dst", dst);
+        let write_err = |e: IOError| err.clone().note("failed to write payload", e);
+        let meta = metadata(src).map_err(|e| err.clone().note("failed to stat file", e))?;
+
+        let mtime = match epoch {
+            Option::Some(epoch) if meta.mtime() > epoch as i64 => epoch,
+            _ => u32::try_from(meta.mtime()).map_err(|_| {
+                err.clone()
+                    .note("mtime", meta.mtime())
+                    .note("error", "rpm only records times from 1970 until 2106")
+            })?,
+        };
+        let mut flags = 0;
+        if opts.config == Option::Some(true) {
+            flags |= 1;
+        }
+        if opts.doc == Option::Some(true) {
+            flags |= 2;
+        }
+        let mut file = FileEntry {
+            path: dst.to_string(),
+            size: 0,
+            mode: 0,
+            mtime,
+            digest: String::new(),
+            linkto: String::new(),
+            flags,
+            user: opts.user.clone().unwrap_or_else(|| "root".to_string()),
+            group: opts.group.clone().unwrap_or_else(|| "root".to_string()),
+        };
+        // binary payloads name files `./path`, source payloads are flat
+        let mut entry = Entry {
+            name: if dst.starts_with('/') {
+                format!(".{}", dst)
+            } else {
+                dst.to_string()
+            },
+            ino,
+            nlink: 1,
+            mtime,
+            ..Entry::default()
+        };
+
+        // symlinks store their target as their contents
+        if let Option::Some(ref target) = opts.symlink {
+            file.mode = (S_IFLNK | 0o777) as u16;
+            file.size = target.len() as u64;
+            file.linkto = target.clone();
+            entry.mode = file.mode as u32;
+            entry.size = file.size as u32;
+            self.write_header(&entry).map_err(write_err)?;
+            self.writer
+                .write_all(target.as_bytes())
+                .map_err(write_err)?;
+            self.writer
+                .write_all(padding(file.size))
+                .map_err(write_err)?;
+            return Ok(file);
+        }
+
+        let perms = match opts.mode {
+            Option::Some(mode) => mode as u32 & 0o7777,
+            Option::None => meta.mode() & 0o7777,
+        };
+        file.mode = (S_IFREG | perms) as u16;
+        file.size = meta.len();
+        entry.mode = file.mode as u32;
+        // stripped entries do not record the size, so it may not fit
+        entry.size = file.size as u32;
+        self.write_header(&entry).map_err(write_err)?;
+
+        // the contents, and their digest, arrive from a reader
+        let chunks = readers.next();
+        let mut copied = 0u64;
+        file.digest = loop {
+            match chunks.recv() {
+                Ok(Ok(Chunk::Data(data))) => {
+                    self.writer.write_all(&data).map_err(write_err)?;
+                    copied += data.len() as u64;
+                }
+                Ok(Ok(Chunk::Digest(digest))) => break digest,
+                Ok(Err(e)) => return Err(err.clone().note("failed to read file", e)),
+                Err(_) => {
+                    return Err(err
+                        .clone()
+                        .note("error", "reader exited before the file was read"))
+                }
+            }
+        };
+        if copied != file.size {
+            return Err(err
+                .clone()
+                .note("expected size", file.size)
+                .note("read size", copied)
+                .note("error", "file changed while it was being packaged"));
+        }
+        self.writer.write_all(padding(copied)).map_err(write_err)?;
+        Ok(file)
+    }
+
+    /// closes the archive and compressor, rewinding the temporary file
+    fn finish(mut self) -> IOResult<Finished> {
+        write_trailer(&mut self.writer)?;
+        let archive_size = self.writer.len;
+        let compressed = self.writer.inner.finish()?;
+        let size = compressed.len;
+        let digest = match compressed.hasher {
+            Option::None => String::new(),
+            Option::Some(hasher) => hex(&hasher.finalize()),
+        };
+        let mut file = compressed.inner.into_inner()?;
+        file.seek(SeekFrom::Start(0))?;
+        Ok(Finished {
+            file,
+            digest,
+            size,
+            archive_size,
+        })
+    }
+}
+
+/// Chunk is a piece of a file read by a worker, the last chunk of every
+/// file is its digest.
+enum Chunk {
+    Data(Vec<u8>),
+    Digest(String),
+}
+
+/// Readers read and digest files on worker threads.
+///
+/// Files are dispatched in payload order, and only `FILES_IN_FLIGHT` per
+/// job ahead of the writer, so memory stays bounded however quickly the
+/// workers run. Dropping the readers stops the workers.
+struct Readers {
+    jobs: Sender<(usize, SyncSender<IOResult<Chunk>>)>,
+    pending: VecDeque<Receiver<IOResult<Chunk>>>,
+    dispatched: usize,
+    total: usize,
+    lookahead: usize,
+}
+impl Readers {
+    fn spawn<'scope, 'env>(
+        scope: &'scope Scope<'scope, 'env>,
+        paths: &'env [PathBuf],
+        jobs: usize,
+    ) -> Readers {
+        let (tx, rx) = channel::<(usize, SyncSender<IOResult<Chunk>>)>();
+        let rx = Arc::new(Mutex::new(rx));
+        for _ in 0..jobs {
+            let rx = Arc::clone(&rx);
+            scope.spawn(move || read_worker(&rx, paths));
+        }
+        Readers {
+            jobs: tx,
+            pending: VecDeque::new(),
+            dispatched: 0,
+            total: paths.len(),
+            lookahead: jobs * FILES_IN_FLIGHT,
+        }
+    }
+
+    /// the chunks of the next file, in the order the paths were given
+    fn next(&mut self) -> Receiver<IOResult<Chunk>> {
+        while self.dispatched < self.total && self.pending.len() < self.lookahead {
+            let (tx, rx) = sync_channel(CHUNKS_IN_FLIGHT);
+            // workers only exit once `jobs` is dropped, so this cannot fail
+            let _ = self.jobs.send((self.dispatched, tx));
+            self.pending.push_back(rx);
+            self.dispatched += 1;
+        }
+        self.pending
+            .pop_front()
+            .expect("more files were written than were read")
+    }
+}
+
+fn read_worker(jobs: &Mutex<Receiver<(usize, SyncSender<IOResult<Chunk>>)>>, paths: &[PathBuf]) {
+    loop {
+        let (index, tx) = match jobs.lock() {
+            Ok(rx) => match rx.recv() {
+                Ok(job) => job,
+                Err(_) => return,
+            },
+            Err(_) => return,
+        };
+        if let Err(e) = read_file(&paths[index], &tx) {
+            let _ = tx.send(Err(e));
+        }
+    }
+}
+
+fn read_file(path: &Path, tx: &SyncSender<IOResult<Chunk>>) -> IOResult<()> {
+    let mut input = File::open(path)?;
+    let mut hasher = Sha256::new();
+    loop {
+        let mut buffer = vec![0u8; READ_BUFFER];
+        let n = input.read(&mut buffer)?;
+        if n == 0 {
+            break;
+        }
+        buffer.truncate(n);
+        hasher.update(&buffer);
+        if tx.send(Ok(Chunk::Data(buffer))).is_err() {
+            // the writer has given up
+            return Ok(());
+        }
+    }
+    let _ = tx.send(Ok(Chunk::Digest(hex(&hasher.finalize()))));
+    Ok(())
+}
+
+/// Digester counts, and optionally digests, everything written through it
+struct Digester<W: Write> {
+    inner: W,
+    hasher: Option<Sha256>,
+    len: u64,
+}
+impl<W: Write> Digester<W> {
+    fn new(inner: W) -> Digester<W> {
+        Digester {
+            inner,
+            hasher: Option::Some(Sha256::new()),
+            len: 0,
+        }
+    }
+
+    fn counting(inner: W) -> Digester<W> {
+        Digester {
+            inner,
+            hasher: Option::None,
+            len: 0,
+        }
+    }
+}
+impl<W: Write> Write for Digester<W> {
+    fn write(&mut self, buf: &[u8]) -> IOResult<usize> {
+        let n = self.inner.write(buf)?;
+        if let Option::Some(ref mut hasher) = self.hasher {
+            hasher.update(&buf[..n]);
+        }
+        self.len += n as u64;
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> IOResult<()> {
+        self.inner.flush()
+    }
+}
+
+/// Encoder is one of the payload compressors
+pub enum Encoder<W: Write> {
+    None(W),
+    Gzip(GzEncoder<W>),
+    Xz(XzEncoder<W>),
+    Zstd(ZstdEncoder<'static, W>),
+}
+impl<W: Write> Encoder<W> {
+    /// zstd and xz always use their multithreaded encoders, as their output
+    /// is then the same whatever the number of threads.
+    pub fn new(algo: Algorithm, level: i32, jobs: usize, inner: W) -> IOResult<Encoder<W>> {
+        Ok(match algo {
+            Algorithm::None => Encoder::None(inner),
+            Algorithm::Gzip => Encoder::Gzip(GzEncoder::new(inner, GzLevel::new(level as u32))),
+            Algorithm::Xz => {
+                let stream = MtStreamBuilder::new()
+                    .threads(jobs as u32)
+                    .preset(level as u32)
+                    .check(Check::Crc64)
+                    .encoder()?;
+                Encoder::Xz(XzEncoder::new_stream(inner, stream))
+            }
+            Algorithm::Zstd => {
+                let mut encoder = ZstdEncoder::new(inner, level)?;
+                encoder.multithread(jobs as u32)?;
+                Encoder::Zstd(encoder)
+            }
+        })
+    }
+
+    pub fn finish(self) -> IOResult<W> {
+        match self {
+            Encoder::None(w) => Ok(w),
+            Encoder::Gzip(e) => e.finish(),
+            Encoder::Xz(e) => e.finish(),
+            Encoder::Zstd(e) => e.finish(),
+        }
+    }
+}
+impl<W: Write> Write for Encoder<W> {
+    fn write(&mut self, buf: &[u8]) -> IOResult<usize> {
+        match self {
+            &mut Encoder::None(ref mut w) => w.write(buf),
+            &mut Encoder::Gzip(ref mut e) => e.write(buf),
+            &mut Encoder::Xz(ref mut e) => e.write(buf),
+            &mut Encoder::Zstd(ref mut e) => e.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> IOResult<()> {
+        match self {
+            &mut Encoder::None(ref mut w) => w.flush(),
+            &mut Encoder::Gzip(ref mut e) => e.flush(),
+            &mut Encoder::Xz(ref mut e) => e.flush(),
+            &mut Encoder::Zstd(ref mut e) => e.flush(),
+        }
+    }
+}
+
+/// lowercase hex, as rpm writes digests
+pub fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// the current time, as rpm records it
+pub fn now() -> u32 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() as u32)
+        .unwrap_or(0)
+}
+
+fn hostname() -> String {
+    match read_to_string("/proc/sys/kernel/hostname") {
+        Ok(name) if !name.trim().is_empty() => name.trim().to_string(),
+        _ => "localhost".to_string(),
+    }
+}
@@ -0,0 +1,239 @@
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+use super::errors::Err;
+use super::rpm::{Dependency, DependencyFlags, PackageBuilder};
+use super::serde::{Deserialize, Serialize};
+
+use super::elfdeps::ElfDeps;
+use super::fileopts::{ComplexFileOptions, FileOptions};
+use super::scripts::Scripts;
+use super::versions::{
+    into_dependencies, into_rich_dependencies, rich_dependencies_feature, Constraints,
+};
+
+/// SubPackage describes everything which differs between the packages
+/// built from one config, the `rpm` metadata and changelog are shared.
+///
+/// Within a config `[packages.devel]` builds `<name>-devel`.
+#[derive(Clone, Hash, Debug, Deserialize, Serialize, Default)]
+pub struct SubPackage {
+    /*
+     * plain values must be serialized before any table
+     *
+     */
+    #[serde(default)]
+    pub desc: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub rich_requires: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub rich_conflicts: Vec<String>,
+    #[serde(default)]
+    pub contents: BTreeMap<String, FileOptions>,
+    #[serde(default)]
+    pub requires: BTreeMap<String, Constraints>,
+    #[serde(default)]
+    pub requires_pre: BTreeMap<String, Constraints>,
+    #[serde(default)]
+    pub requires_post: BTreeMap<String, Constraints>,
+    #[serde(default)]
+    pub requires_preun: BTreeMap<String, Constraints>,
+    #[serde(default)]
+    pub requires_postun: BTreeMap<String, Constraints>,
+    #[serde(default)]
+    pub obsoletes: BTreeMap<String, Constraints>,
+    #[serde(default)]
+    pub conflicts: BTreeMap<String, Constraints>,
+    #[serde(default)]
+    pub provides: BTreeMap<String, Constraints>,
+    #[serde(default)]
+    pub recommends: BTreeMap<String, Constraints>,
+    #[serde(default)]
+    pub suggests: BTreeMap<String, Constraints>,
+    #[serde(default)]
+    pub supplements: BTreeMap<String, Constraints>,
+    #[serde(default)]
+    pub enhances: BTreeMap<String, Constraints>,
+    #[serde(default)]
+    pub scripts: Option<Scripts>,
+}
+impl SubPackage {
+    /// constructs a lambda which adds the contents, dependencies and
+    /// scripts of this package to the builder, anything skipped is
+    /// reported within `warnings`
+    pub fn build<'a>(
+        &'a self,
+        auto_deps: bool,
+        warnings: &'a mut Vec<String>,
+    ) -> impl FnOnce(PackageBuilder, &Err) -> Result<PackageBuilder, Err> + 'a {
+        move |builder: PackageBuilder, err: &Err| -> Result<PackageBuilder, Err> {
+            let mut builder = builder;
+
+            // package the contents
+            builder = self
+                .contents
+                .iter()
+                .map(|(source, options)| options.build(source))
+                .fold(Ok(builder), |builder_res, opts_bundle| {
+                    (opts_bundle)(builder_res, err)
+                })?;
+
+            // package database interactions
+            builder = self
+                .dependencies(auto_deps, warnings, err)?
+                .into_iter()
+                .fold(builder, |builder, (kind, dep)| kind.add(builder, dep));
+
+            // load scripts if we need to
+            builder = (Scripts::build(&self.scripts))(builder, err)?;
+
+            Ok(builder)
+        }
+    }
+
+    /// every dependency of this package, and with `auto_deps` those of its
+    /// ELF files. Both the in-memory and streaming writers record exactly
+    /// these entries.
+    pub fn dependencies(
+        &self,
+        auto_deps: bool,
+        warnings: &mut Vec<String>,
+        err: &Err,
+    ) -> Result<Vec<(Kind, Dependency)>, Err> {
+        let tables = [
+            (Kind::Requires, &self.requires, DependencyFlags::empty()),
+            // requirements which must be installed before a scriptlet runs
+            (
+                Kind::Requires,
+                &self.requires_pre,
+                DependencyFlags::SCRIPT_PRE,
+            ),
+            (
+                Kind::Requires,
+                &self.requires_post,
+                DependencyFlags::SCRIPT_POST,
+            ),
+            (
+                Kind::Requires,
+                &self.requires_preun,
+                DependencyFlags::SCRIPT_PREUN,
+            ),
+            (
+                Kind::Requires,
+                &self.requires_postun,
+                DependencyFlags::SCRIPT_POSTUN,
+            ),
+            (Kind::Obsoletes, &self.obsoletes, DependencyFlags::empty()),
+            (Kind::Conflicts, &self.conflicts, DependencyFlags::empty()),
+            (Kind::Provides, &self.provides, DependencyFlags::empty()),
+            // weak dependencies
+            (Kind::Recommends, &self.recommends, DependencyFlags::empty()),
+            (Kind::Suggests, &self.suggests, DependencyFlags::empty()),
+            (
+                Kind::Supplements,
+                &self.supplements,
+                DependencyFlags::empty(),
+            ),
+            (Kind::Enhances, &self.enhances, DependencyFlags::empty()),
+        ];
+        let mut deps = Vec::new();
+        for &(kind, table, flags) in tables.iter() {
+            for (name, constraints) in table.iter() {
+                let resolved = into_dependencies(name, constraints, flags, err)?;
+                deps.extend(resolved.into_iter().map(|dep| (kind, dep)));
+            }
+        }
+
+        // boolean dependencies
+        let rich_requires = into_rich_dependencies(&self.rich_requires, err)?;
+        let rich_conflicts = into_rich_dependencies(&self.rich_conflicts, err)?;
+        if !rich_requires.is_empty() || !rich_conflicts.is_empty() {
+            deps.push((Kind::Requires, rich_dependencies_feature()));
+        }
+        deps.extend(rich_requires.into_iter().map(|dep| (Kind::Requires, dep)));
+        deps.extend(rich_conflicts.into_iter().map(|dep| (Kind::Conflicts, dep)));
+
+        // derive shared library dependencies from the packaged ELF files
+        if auto_deps {
+            let mut sources = Vec::new();
+            for (source, options) in self.contents.iter() {
+                sources.extend(options.expand(source, err)?.into_iter().map(|(src, _)| src));
+            }
+            let elf = ElfDeps::scan(&sources, warnings, err)?;
+            for name in elf.provides.iter() {
+                deps.push((Kind::Provides, Dependency::any(name.as_str())));
+            }
+            // requirements satisfied from within the package are skipped
+            for name in elf.requires.difference(&elf.provides) {
+                deps.push((Kind::Requires, Dependency::any(name.as_str())));
+            }
+        }
+        Ok(deps)
+    }
+
+    /// every file which is packaged, keyed by its absolute install path
+    pub fn sources(
+        &self,
+        err: &Err,
+    ) -> Result<BTreeMap<String, (PathBuf, ComplexFileOptions)>, Err> {
+        let mut output = BTreeMap::new();
+        for (source, options) in self.contents.iter() {
+            let opts = options.complex().cloned().unwrap_or_default();
+            for (src, dst) in options.expand(source, err)? {
+                let dst = if dst.starts_with('/') {
+                    dst
+                } else {
+                    format!("/{}", dst)
+                };
+                if let Option::Some((first, _)) = output.insert(dst.clone(), (src, opts.clone())) {
+                    return Err(err
+                        .clone()
+                        .note("duplicate install path", &dst)
+                        .note("first", first.display()));
+                }
+            }
+        }
+        Ok(output)
+    }
+}
+
+/// Kind is the dependency table an entry is recorded within
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Kind {
+    Requires,
+    Provides,
+    Conflicts,
+    Obsoletes,
+    Recommends,
+    Suggests,
+    Supplements,
+    Enhances,
+}
+impl Kind {
+    pub const ALL: [Kind; 8] = [
+        Kind::Requires,
+        Kind::Provides,
+        Kind::Conflicts,
+        Kind::Obsoletes,
+        Kind::Recommends,
+        Kind::Suggests,
+        Kind::Supplements,
+        Kind::Enhances,
+    ];
+
+    /// adds the dependency to the builder's table of this kind
+    pub fn add(self, builder: PackageBuilder, dep: Dependency) -> PackageBuilder {
+        match self {
+            Kind::Requires => builder.requires(dep),
+            Kind::Provides => builder.provides(dep),
+            Kind::Conflicts => builder.conflicts(dep),
+            Kind::Obsoletes => builder.obsoletes(dep),
+            Kind::Recommends => builder.recommends(dep),
+            Kind::Suggests => builder.suggests(dep),
+            Kind::Supplements => builder.supplements(dep),
+            Kind::Enhances => builder.enhances(dep),
+        }
+    }
+}
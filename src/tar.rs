@@ -0,0 +1,240 @@
+use std::io::{Error as IOError, ErrorKind, Result as IOResult, Write};
+
+const BLOCK: usize = 512;
+
+/// the largest size an octal size field of 11 digits records
+const MAX_SIZE: u64 = 0o77777777777;
+
+/// Entry is the header of a single file within a `ustar` archive, the
+/// format of the `control` and `data` members of a `.deb`.
+#[derive(Clone, Debug, Default)]
+pub struct Entry {
+    pub name: String,
+    pub mode: u32,
+    pub uid: u32,
+    pub gid: u32,
+    pub size: u64,
+    pub mtime: u64,
+    pub kind: Kind,
+    pub linkname: String,
+    pub uname: String,
+    pub gname: String,
+}
+
+/// Kind is the type flag of an entry
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum Kind {
+    #[default]
+    File,
+    Symlink,
+    Dir,
+}
+
+impl Entry {
+    /// writes the header block, the caller writes `size` bytes of data
+    /// followed by `padding(size)`.
+    ///
+    /// Names longer than 100 bytes are split into the `prefix` field, so
+    /// paths of up to 255 bytes are supported without extensions.
+    pub fn write<W: Write>(&self, w: &mut W) -> IOResult<()> {
+        let invalid = |msg: &str| IOError::new(ErrorKind::InvalidInput, msg.to_string());
+        let (prefix, name) = split_name(&self.name)
+            .ok_or_else(|| invalid("path is too long for a ustar archive"))?;
+        if self.linkname.len() > 100 {
+            return Err(invalid("symlink target is too long for a ustar archive"));
+        }
+        if self.uname.len() > 31 || self.gname.len() > 31 {
+            return Err(invalid(
+                "user or group name is too long for a ustar archive",
+            ));
+        }
+        if self.size > MAX_SIZE {
+            return Err(invalid("files of 8GiB or larger cannot be stored"));
+        }
+
+        let mut header = [0u8; BLOCK];
+        field(&mut header[0..100], name.as_bytes());
+        field(&mut header[100..108], octal(self.mode as u64, 7).as_bytes());
+        field(&mut header[108..116], octal(self.uid as u64, 7).as_bytes());
+        field(&mut header[116..124], octal(self.gid as u64, 7).as_bytes());
+        field(&mut header[124..136], octal(self.size, 11).as_bytes());
+        field(&mut header[136..148], octal(self.mtime, 11).as_bytes());
+        header[156] = match self.kind {
+            Kind::File => b'0',
+            Kind::Symlink => b'2',
+            Kind::Dir => b'5',
+        };
+        field(&mut header[157..257], self.linkname.as_bytes());
+        field(&mut header[257..263], b"ustar\0");
+        field(&mut header[263..265], b"00");
+        field(&mut header[265..297], self.uname.as_bytes());
+        field(&mut header[297..329], self.gname.as_bytes());
+        field(&mut header[345..500], prefix.as_bytes());
+
+        // the checksum is computed as if its own field were spaces
+        field(&mut header[148..156], b"        ");
+        let sum: u64 = header.iter().map(|b| *b as u64).sum();
+        field(
+            &mut header[148..156],
+            format!("{}\0 ", octal(sum, 6)).as_bytes(),
+        );
+        w.write_all(&header)
+    }
+}
+
+/// the zeros which align `len` bytes to the next block
+pub fn padding(len: u64) -> &'static [u8] {
+    const ZEROS: [u8; BLOCK] = [0; BLOCK];
+    &ZEROS[..(BLOCK - (len as usize % BLOCK)) % BLOCK]
+}
+
+/// writes the two empty blocks which end every archive
+pub fn write_trailer<W: Write>(w: &mut W) -> IOResult<()> {
+    w.write_all(&[0u8; BLOCK * 2])
+}
+
+/// splits a path into the `prefix` and `name` fields, at a `/`
+fn split_name(path: &str) -> Option<(&str, &str)> {
+    if path.len() <= 100 {
+        return Option::Some(("", path));
+    }
+    path.char_indices()
+        .filter(|&(_, c)| c == '/')
+        .map(|(idx, _)| (&path[..idx], &path[idx + 1..]))
+        .find(|&(prefix, name)| prefix.len() <= 155 && name.len() <= 100 && !name.is_empty())
+}
+
+fn octal(value: u64, digits: usize) -> String {
+    format!("{:0width$o}", value, width = digits)
+}
+
+fn field(dst: &mut [u8], value: &[u8]) {
+    dst[..value.len()].copy_from_slice(value);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// the text of a header field, up to its first nul
+    fn text(field: &[u8]) -> &str {
+        let end = field.iter().position(|b| *b == 0).unwrap_or(field.len());
+        std::str::from_utf8(&field[..end]).unwrap()
+    }
+
+    #[test]
+    fn headers_are_ustar() {
+        let entry = Entry {
+            name: "./usr/bin/demo".to_string(),
+            mode: 0o755,
+            uid: 1000,
+            gid: 100,
+            size: 1234,
+            mtime: 1_600_000_000,
+            uname: "demo".to_string(),
+            gname: "users".to_string(),
+            ..Entry::default()
+        };
+        let mut header = Vec::new();
+        entry.write(&mut header).unwrap();
+        assert_eq!(header.len(), BLOCK);
+        assert_eq!(text(&header[0..100]), "./usr/bin/demo");
+        assert_eq!(text(&header[100..108]), "0000755");
+        assert_eq!(text(&header[108..116]), "0001750");
+        assert_eq!(text(&header[116..124]), "0000144");
+        assert_eq!(text(&header[124..136]), "00000002322");
+        assert_eq!(text(&header[136..148]), "13727410000");
+        assert_eq!(header[156], b'0');
+        assert_eq!(&header[257..265], b"ustar\x0000");
+        assert_eq!(text(&header[265..297]), "demo");
+        assert_eq!(text(&header[297..329]), "users");
+        assert_eq!(text(&header[345..500]), "");
+
+        // the checksum is six octal digits, a nul and a space, summing the
+        // header with its own field as spaces
+        assert_eq!(&header[154..156], b"\0 ");
+        let recorded = u64::from_str_radix(text(&header[148..154]), 8).unwrap();
+        let sum: u64 = header[..148]
+            .iter()
+            .chain(b"        ".iter())
+            .chain(header[156..].iter())
+            .map(|b| *b as u64)
+            .sum();
+        assert_eq!(recorded, sum);
+    }
+
+    #[test]
+    fn kinds_set_the_type_flag() {
+        let mut header = Vec::new();
+        let link = Entry {
+            name: "./lib/libdemo.so".to_string(),
+            kind: Kind::Symlink,
+            linkname: "libdemo.so.1".to_string(),
+            ..Entry::default()
+        };
+        link.write(&mut header).unwrap();
+        let dir = Entry {
+            name: "./lib/".to_string(),
+            kind: Kind::Dir,
+            ..Entry::default()
+        };
+        dir.write(&mut header).unwrap();
+        assert_eq!(header[156], b'2');
+        assert_eq!(text(&header[157..257]), "libdemo.so.1");
+        assert_eq!(header[BLOCK + 156], b'5');
+        assert_eq!(Entry::default().kind, Kind::File);
+    }
+
+    #[test]
+    fn long_names_use_the_prefix() {
+        let name = "n".repeat(100);
+        assert_eq!(split_name(&name), Option::Some(("", name.as_str())));
+
+        // 101 bytes must be split, at the only `/`
+        let name = format!("{}/{}", "d".repeat(50), "n".repeat(50));
+        assert_eq!(name.len(), 101);
+        assert_eq!(
+            split_name(&name),
+            Option::Some(("d".repeat(50).as_str(), "n".repeat(50).as_str()))
+        );
+        // a name of 101 bytes without a `/` can not be split
+        assert_eq!(split_name(&"n".repeat(101)), Option::None);
+
+        // the longest name which fits, 155 bytes of prefix and 100 of name
+        let name = format!("{}/{}", "d".repeat(155), "n".repeat(100));
+        assert_eq!(name.len(), 256);
+        assert_eq!(
+            split_name(&name),
+            Option::Some(("d".repeat(155).as_str(), "n".repeat(100).as_str()))
+        );
+        let name = format!("{}/{}", "d".repeat(156), "n".repeat(99));
+        assert_eq!(name.len(), 256);
+        assert_eq!(split_name(&name), Option::None);
+
+        let entry = Entry {
+            name: format!("./{}/{}", "d".repeat(150), "n".repeat(100)),
+            ..Entry::default()
+        };
+        let mut header = Vec::new();
+        entry.write(&mut header).unwrap();
+        assert_eq!(text(&header[0..100]), "n".repeat(100));
+        assert_eq!(text(&header[345..500]), format!("./{}", "d".repeat(150)));
+
+        let entry = Entry {
+            name: format!("./{}", "n".repeat(256)),
+            ..Entry::default()
+        };
+        assert!(entry.write(&mut Vec::new()).is_err());
+    }
+
+    #[test]
+    fn padding_aligns_to_blocks() {
+        assert_eq!(padding(0).len(), 0);
+        assert_eq!(padding(1).len(), 511);
+        assert_eq!(padding(512).len(), 0);
+        assert_eq!(padding(513).len(), 511);
+        let mut trailer = Vec::new();
+        write_trailer(&mut trailer).unwrap();
+        assert_eq!(trailer, vec![0u8; 1024]);
+    }
+}
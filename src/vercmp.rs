@@ -0,0 +1,264 @@
+use std::cmp::Ordering;
+
+use super::versions::{Constraint, Evr, Operator};
+
+/// compares two version (or release) strings exactly as rpm's
+/// `rpmvercmp` does.
+///
+/// Strings are split into alternating numeric and alphabetic segments,
+/// numeric segments are newer than alphabetic ones. A `~` sorts before
+/// everything, even the end of the string, so `1.0~rc1 < 1.0`. A `^`
+/// sorts after the end of the string but before any other segment, so
+/// `1.0 < 1.0^git1 < 1.0.1`.
+pub fn rpmvercmp(a: &str, b: &str) -> Ordering {
+    if a == b {
+        return Ordering::Equal;
+    }
+    let (a, b) = (a.as_bytes(), b.as_bytes());
+    let (mut one, mut two) = (0, 0);
+
+    let is_separator = |c: u8| !c.is_ascii_alphanumeric() && c != b'~' && c != b'^';
+
+    while one < a.len() || two < b.len() {
+        while one < a.len() && is_separator(a[one]) {
+            one += 1;
+        }
+        while two < b.len() && is_separator(b[two]) {
+            two += 1;
+        }
+
+        // tilde sorts before everything else
+        if a.get(one) == Some(&b'~') || b.get(two) == Some(&b'~') {
+            if a.get(one) != Some(&b'~') {
+                return Ordering::Greater;
+            }
+            if b.get(two) != Some(&b'~') {
+                return Ordering::Less;
+            }
+            one += 1;
+            two += 1;
+            continue;
+        }
+
+        // caret sorts after the end of the string, but before any segment
+        if a.get(one) == Some(&b'^') || b.get(two) == Some(&b'^') {
+            if one == a.len() {
+                return Ordering::Less;
+            }
+            if two == b.len() {
+                return Ordering::Greater;
+            }
+            if a[one] != b'^' {
+                return Ordering::Greater;
+            }
+            if b[two] != b'^' {
+                return Ordering::Less;
+            }
+            one += 1;
+            two += 1;
+            continue;
+        }
+
+        if one == a.len() || two == b.len() {
+            break;
+        }
+
+        let is_num = a[one].is_ascii_digit();
+        let in_segment = |c: u8| {
+            if is_num {
+                c.is_ascii_digit()
+            } else {
+                c.is_ascii_alphabetic()
+            }
+        };
+        let end_one = one + a[one..].iter().take_while(|c| in_segment(**c)).count();
+        let end_two = two + b[two..].iter().take_while(|c| in_segment(**c)).count();
+
+        // segments of different types, numeric is always newer
+        if end_two == two {
+            return if is_num {
+                Ordering::Greater
+            } else {
+                Ordering::Less
+            };
+        }
+
+        let (mut seg_one, mut seg_two) = (&a[one..end_one], &b[two..end_two]);
+        if is_num {
+            while seg_one.first() == Some(&b'0') {
+                seg_one = &seg_one[1..];
+            }
+            while seg_two.first() == Some(&b'0') {
+                seg_two = &seg_two[1..];
+            }
+            match seg_one.len().cmp(&seg_two.len()) {
+                Ordering::Equal => {}
+                other => return other,
+            };
+        }
+        match seg_one.cmp(seg_two) {
+            Ordering::Equal => {}
+            other => return other,
+        };
+
+        one = end_one;
+        two = end_two;
+    }
+
+    match (one == a.len(), two == b.len()) {
+        (true, true) => Ordering::Equal,
+        (true, false) => Ordering::Less,
+        (false, _) => Ordering::Greater,
+    }
+}
+
+/// compares two `[epoch:]version[-release]` triples, a missing epoch is
+/// zero and a missing release sorts before any release.
+pub fn compare_evr(a: &Evr, b: &Evr) -> Ordering {
+    a.epoch
+        .unwrap_or(0)
+        .cmp(&b.epoch.unwrap_or(0))
+        .then_with(|| rpmvercmp(&a.version, &b.version))
+        .then_with(|| {
+            rpmvercmp(
+                a.release.as_ref().map(|r| r.as_str()).unwrap_or(""),
+                b.release.as_ref().map(|r| r.as_str()).unwrap_or(""),
+            )
+        })
+}
+
+/// checks if a package at `evr` satisfies the constraint.
+///
+/// Like rpm, the release is only compared when the constraint names one,
+/// so `= 1.2` is satisfied by `1.2-3`.
+pub fn satisfies(evr: &Evr, constraint: &Constraint) -> bool {
+    let wanted = &constraint.evr;
+    let ord = evr
+        .epoch
+        .unwrap_or(0)
+        .cmp(&wanted.epoch.unwrap_or(0))
+        .then_with(|| rpmvercmp(&evr.version, &wanted.version))
+        .then_with(|| match (&evr.release, &wanted.release) {
+            (&Option::Some(ref have), &Option::Some(ref want)) => rpmvercmp(have, want),
+            _ => Ordering::Equal,
+        });
+    match constraint.op {
+        Operator::Eq => ord == Ordering::Equal,
+        Operator::Less => ord == Ordering::Less,
+        Operator::LessEq => ord != Ordering::Greater,
+        Operator::Greater => ord == Ordering::Greater,
+        Operator::GreaterEq => ord != Ordering::Less,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// the cases from rpm's own `tests/rpmvercmp.at`
+    const CASES: &[(&str, &str, Ordering)] = &[
+        ("1.0", "1.0", Ordering::Equal),
+        ("1.0", "2.0", Ordering::Less),
+        ("2.0", "1.0", Ordering::Greater),
+        ("2.0.1", "2.0.1", Ordering::Equal),
+        ("2.0", "2.0.1", Ordering::Less),
+        ("2.0.1", "2.0", Ordering::Greater),
+        ("2.0.1a", "2.0.1a", Ordering::Equal),
+        ("2.0.1a", "2.0.1", Ordering::Greater),
+        ("2.0.1", "2.0.1a", Ordering::Less),
+        ("5.5p1", "5.5p1", Ordering::Equal),
+        ("5.5p1", "5.5p2", Ordering::Less),
+        ("5.5p2", "5.5p1", Ordering::Greater),
+        ("5.5p10", "5.5p10", Ordering::Equal),
+        ("5.5p1", "5.5p10", Ordering::Less),
+        ("5.5p10", "5.5p1", Ordering::Greater),
+        ("10xyz", "10.1xyz", Ordering::Less),
+        ("10.1xyz", "10xyz", Ordering::Greater),
+        ("xyz10", "xyz10", Ordering::Equal),
+        ("xyz10", "xyz10.1", Ordering::Less),
+        ("xyz10.1", "xyz10", Ordering::Greater),
+        ("xyz.4", "xyz.4", Ordering::Equal),
+        ("xyz.4", "8", Ordering::Less),
+        ("8", "xyz.4", Ordering::Greater),
+        ("xyz.4", "2", Ordering::Less),
+        ("2", "xyz.4", Ordering::Greater),
+        ("5.5p2", "5.6p1", Ordering::Less),
+        ("5.6p1", "5.5p2", Ordering::Greater),
+        ("5.6p1", "6.5p1", Ordering::Less),
+        ("6.5p1", "5.6p1", Ordering::Greater),
+        ("6.0.rc1", "6.0", Ordering::Greater),
+        ("6.0", "6.0.rc1", Ordering::Less),
+        ("10b2", "10a1", Ordering::Greater),
+        ("10a2", "10b2", Ordering::Less),
+        ("1.0aa", "1.0aa", Ordering::Equal),
+        ("1.0a", "1.0aa", Ordering::Less),
+        ("1.0aa", "1.0a", Ordering::Greater),
+        // leading zeros
+        ("10.0001", "10.0001", Ordering::Equal),
+        ("10.0001", "10.1", Ordering::Equal),
+        ("10.1", "10.0001", Ordering::Equal),
+        ("10.0001", "10.0039", Ordering::Less),
+        ("10.0039", "10.0001", Ordering::Greater),
+        ("4.999.9", "5.0", Ordering::Less),
+        ("5.0", "4.999.9", Ordering::Greater),
+        ("20101121", "20101121", Ordering::Equal),
+        ("20101121", "20101122", Ordering::Less),
+        ("20101122", "20101121", Ordering::Greater),
+        // separators
+        ("2_0", "2_0", Ordering::Equal),
+        ("2.0", "2_0", Ordering::Equal),
+        ("2_0", "2.0", Ordering::Equal),
+        ("a", "a", Ordering::Equal),
+        ("a+", "a+", Ordering::Equal),
+        ("a+", "a_", Ordering::Equal),
+        ("a_", "a+", Ordering::Equal),
+        ("+a", "+a", Ordering::Equal),
+        ("+a", "_a", Ordering::Equal),
+        ("_a", "+a", Ordering::Equal),
+        ("+_", "+_", Ordering::Equal),
+        ("_+", "+_", Ordering::Equal),
+        ("_+", "_+", Ordering::Equal),
+        ("+", "_", Ordering::Equal),
+        ("_", "+", Ordering::Equal),
+        // tilde
+        ("1.0~rc1", "1.0~rc1", Ordering::Equal),
+        ("1.0~rc1", "1.0", Ordering::Less),
+        ("1.0", "1.0~rc1", Ordering::Greater),
+        ("1.0~rc1", "1.0~rc2", Ordering::Less),
+        ("1.0~rc2", "1.0~rc1", Ordering::Greater),
+        ("1.0~rc1~git123", "1.0~rc1~git123", Ordering::Equal),
+        ("1.0~rc1~git123", "1.0~rc1", Ordering::Less),
+        ("1.0~rc1", "1.0~rc1~git123", Ordering::Greater),
+        // caret
+        ("1.0^", "1.0^", Ordering::Equal),
+        ("1.0^", "1.0", Ordering::Greater),
+        ("1.0", "1.0^", Ordering::Less),
+        ("1.0^git1", "1.0^git1", Ordering::Equal),
+        ("1.0^git1", "1.0", Ordering::Greater),
+        ("1.0", "1.0^git1", Ordering::Less),
+        ("1.0^git1", "1.0^git2", Ordering::Less),
+        ("1.0^git2", "1.0^git1", Ordering::Greater),
+        ("1.0^git1", "1.01", Ordering::Less),
+        ("1.01", "1.0^git1", Ordering::Greater),
+        ("1.0^20160101", "1.0^20160101", Ordering::Equal),
+        ("1.0^20160101", "1.0.1", Ordering::Less),
+        ("1.0.1", "1.0^20160101", Ordering::Greater),
+        ("1.0^20160101^git1", "1.0^20160101^git1", Ordering::Equal),
+        ("1.0^20160102", "1.0^20160101^git1", Ordering::Greater),
+        ("1.0^20160101^git1", "1.0^20160102", Ordering::Less),
+        // tilde and caret together
+        ("1.0~rc1^git1", "1.0~rc1^git1", Ordering::Equal),
+        ("1.0~rc1^git1", "1.0~rc1", Ordering::Greater),
+        ("1.0~rc1", "1.0~rc1^git1", Ordering::Less),
+        ("1.0^git1~pre", "1.0^git1~pre", Ordering::Equal),
+        ("1.0^git1", "1.0^git1~pre", Ordering::Greater),
+        ("1.0^git1~pre", "1.0^git1", Ordering::Less),
+    ];
+
+    #[test]
+    fn rpmvercmp_matches_rpm() {
+        for &(a, b, expected) in CASES.iter() {
+            assert_eq!(rpmvercmp(a, b), expected, "rpmvercmp({:?}, {:?})", a, b);
+        }
+    }
+}
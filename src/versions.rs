@@ -1,77 +1,696 @@
-use super::rpm::Dependency;
+use std::fmt;
 
-pub fn into_dependency(name: &str, version: &str) -> Dependency {
-    Option::None
+use super::errors::Err;
+use super::rpm::{Dependency, DependencyFlags};
+use super::serde::{Deserialize, Serialize};
+
+/// Constraints are the version requirements attached to a dependency name.
+///
+/// This is either a single string or an array of strings. Each string may
+/// hold several comma separated constraints (`">= 1.2, < 2.0"`), and every
+/// constraint becomes its own dependency entry.
+#[derive(Clone, Hash, Debug, Deserialize, Serialize)]
+#[serde(untagged)]
+pub enum Constraints {
+    One(String),
+    Many(Vec<String>),
+}
+impl Constraints {
+    /// every individual constraint, an empty set accepts any version
+    pub fn each(&self) -> Vec<&str> {
+        let list: Vec<&str> = match self {
+            &Constraints::One(ref arg) => vec![arg.as_str()],
+            &Constraints::Many(ref args) => args.iter().map(|arg| arg.as_str()).collect(),
+        };
+        let list: Vec<&str> = list
+            .into_iter()
+            .flat_map(|arg| arg.split(','))
+            .map(|arg| arg.trim())
+            .filter(|arg| !arg.is_empty())
+            .collect();
+        if list.is_empty() {
+            vec![""]
+        } else {
+            list
+        }
+    }
+
+    /// these constraints, and one more. Accepting any version is implied
+    /// by the new constraint, so it is dropped.
+    pub fn and(&self, constraint: String) -> Constraints {
+        let mut list: Vec<String> = self
+            .each()
+            .into_iter()
+            .filter(|arg| !arg.is_empty())
+            .map(|arg| arg.to_string())
+            .collect();
+        list.push(constraint);
+        Constraints::Many(list)
+    }
+}
+
+/// converts a name, and all of its constraints into dependencies
+pub fn into_dependencies(
+    name: &str,
+    constraints: &Constraints,
+    flags: DependencyFlags,
+    err: &Err,
+) -> Result<Vec<Dependency>, Err> {
+    constraints
+        .each()
         .into_iter()
-        /*
-         * attempt to infer a standard prefix
-         *
-         */
-        .chain(magic("=", eq, name, version))
-        .chain(magic("==", eq, name, version))
-        .chain(magic("<", less, name, version))
-        .chain(magic("<=", less_eq, name, version))
-        .chain(magic(">", greater, name, version))
-        .chain(magic(">=", greater_eq, name, version))
-        .chain(magic("^", greater_eq, name, version))
-        .chain(magic("~", greater_eq, name, version))
-        /*
-         * allow some wild cards, and empty space
-         *
-         */
-        .chain(any_prefix("*", any, name, version))
-        .chain(any_prefix("", any, name, version))
-        /*
-         * fallback to exactly what was given
-         *
-         */
-        .chain(Some(Dependency::eq(name, version)))
-        .next()
-        .unwrap()
-}
-
-fn any_prefix<F>(prefix: &str, lambda: F, name: &str, version: &str) -> Option<Dependency>
-where
-    F: Fn(&str) -> Dependency,
-{
-    if prefix == version {
-        Some(lambda(name.trim()))
-    } else {
-        None
-    }
-}
-
-fn magic<F>(prefix: &str, lambda: F, name: &str, version: &str) -> Option<Dependency>
-where
-    F: Fn(&str, &str) -> Dependency,
-{
-    let version_info = version.trim_start_matches(prefix);
-    if (version_info.len() + prefix.len()) == version.len() {
-        Some(lambda(name.trim(), version_info.trim()))
-    } else {
-        None
+        .map(|version| into_dependency(name, version, flags, err))
+        .collect()
+}
+
+/// converts a name, and version constraint into a dependency
+///
+/// The constraint is an optional operator followed by an
+/// `[epoch:]version[-release]`, for example `>= 1:1.2-3`. A bare version
+/// means exactly that version, while an empty constraint (or `*`) accepts
+/// any version.
+///
+/// `flags` are merged into the sense of the dependency, this is how
+/// scriptlet ordering (`Requires(pre)` and friends) is expressed.
+pub fn into_dependency(
+    name: &str,
+    version: &str,
+    flags: DependencyFlags,
+    err: &Err,
+) -> Result<Dependency, Err> {
+    let name = name.trim();
+    let fail = |reason: &str| -> Err {
+        err.clone()
+            .note("dependency", name)
+            .note("constraint", version)
+            .note("failed to parse", reason)
+    };
+
+    if name.is_empty() || name.contains(char::is_whitespace) {
+        return Err(fail(
+            "dependency name must be non-empty and contain no whitespace",
+        ));
+    }
+    let mut dep = match Constraint::parse(version) {
+        Ok(Option::None) => Dependency::any(name),
+        Ok(Option::Some(constraint)) => constraint.into_dependency(name),
+        Err(reason) => return Err(fail(&reason)),
+    };
+    dep.flags |= flags;
+    Ok(dep)
+}
+
+/// Operator is the comparison applied to a version
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Operator {
+    Eq,
+    Less,
+    LessEq,
+    Greater,
+    GreaterEq,
+}
+impl Operator {
+    /// the canonical spelling of this operator within RPM
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            &Operator::Eq => "=",
+            &Operator::Less => "<",
+            &Operator::LessEq => "<=",
+            &Operator::Greater => ">",
+            &Operator::GreaterEq => ">=",
+        }
     }
 }
 
 /*
- * make some logical lambdas
+ * operators are tokenized longest first, so `>=` is never
+ * read as `>` followed by a version of `= 1.2`
  *
  */
-fn eq(name: &str, version: &str) -> Dependency {
-    Dependency::eq(name, version)
+const OPERATORS: &[(&str, Operator)] = &[
+    (">=", Operator::GreaterEq),
+    ("<=", Operator::LessEq),
+    ("==", Operator::Eq),
+    ("=", Operator::Eq),
+    ("<", Operator::Less),
+    (">", Operator::Greater),
+];
+
+/// Constraint is a single parsed version requirement
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Constraint {
+    pub op: Operator,
+    pub evr: Evr,
 }
-fn less_eq(name: &str, version: &str) -> Dependency {
-    Dependency::less_eq(name, version)
+impl Constraint {
+    /// parses a constraint, `None` is returned when any version is accepted
+    pub fn parse(arg: &str) -> Result<Option<Constraint>, String> {
+        let arg = arg.trim();
+        if arg.is_empty() || arg == "*" {
+            return Ok(None);
+        }
+
+        let (op, version) = OPERATORS
+            .iter()
+            .filter(|(token, _)| arg.starts_with(token))
+            .map(|(token, op)| (*op, arg[token.len()..].trim()))
+            .next()
+            .unwrap_or((Operator::Eq, arg));
+
+        if version.is_empty() {
+            return Err("operator is not followed by a version".to_string());
+        }
+        if version.starts_with('~') || version.starts_with('^') {
+            return Err(
+                "`~` and `^` are not operators, they mark pre and post releases within a version"
+                    .to_string(),
+            );
+        }
+        Ok(Some(Constraint {
+            op,
+            evr: Evr::parse(version)?,
+        }))
+    }
+
+    /// builds the dependency this constraint describes
+    pub fn into_dependency(&self, name: &str) -> Dependency {
+        let version = self.evr.to_string();
+        match self.op {
+            Operator::Eq => Dependency::eq(name, version),
+            Operator::Less => Dependency::less(name, version),
+            Operator::LessEq => Dependency::less_eq(name, version),
+            Operator::Greater => Dependency::greater(name, version),
+            Operator::GreaterEq => Dependency::greater_eq(name, version),
+        }
+    }
 }
-fn greater_eq(name: &str, version: &str) -> Dependency {
-    Dependency::greater_eq(name, version)
+
+/// Evr is an `[epoch:]version[-release]` triple
+#[derive(Clone, Debug, PartialEq, Eq, Default)]
+pub struct Evr {
+    pub epoch: Option<u32>,
+    pub version: String,
+    pub release: Option<String>,
 }
-fn greater(name: &str, version: &str) -> Dependency {
-    Dependency::greater(name, version)
+impl Evr {
+    /// parses, and validates each component against the characters RPM
+    /// permits within a version or release.
+    pub fn parse(arg: &str) -> Result<Evr, String> {
+        let (epoch, rest) = match arg.find(':') {
+            Option::None => (Option::None, arg),
+            Option::Some(idx) => {
+                let epoch = &arg[..idx];
+                if epoch.is_empty() || !epoch.chars().all(|c| c.is_ascii_digit()) {
+                    return Err(format!("epoch `{}` must be an unsigned integer", epoch));
+                }
+                match epoch.parse::<u32>() {
+                    Ok(epoch) => (Option::Some(epoch), &arg[idx + 1..]),
+                    Err(_) => return Err(format!("epoch `{}` is too large", epoch)),
+                }
+            }
+        };
+        let (version, release) = match rest.rfind('-') {
+            Option::None => (rest, Option::None),
+            Option::Some(idx) => (&rest[..idx], Option::Some(&rest[idx + 1..])),
+        };
+        validate_component("version", version)?;
+        if let Option::Some(release) = release {
+            validate_component("release", release)?;
+        }
+        Ok(Evr {
+            epoch,
+            version: version.to_string(),
+            release: release.map(|release| release.to_string()),
+        })
+    }
 }
-fn less(name: &str, version: &str) -> Dependency {
-    Dependency::less(name, version)
+impl fmt::Display for Evr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let Option::Some(epoch) = self.epoch {
+            write!(f, "{}:", epoch)?;
+        }
+        write!(f, "{}", self.version)?;
+        if let Option::Some(ref release) = self.release {
+            write!(f, "-{}", release)?;
+        }
+        Ok(())
+    }
+}
+
+fn validate_component(component: &str, arg: &str) -> Result<(), String> {
+    if arg.is_empty() {
+        return Err(format!("{} must not be empty", component));
+    }
+    match arg.chars().find(|c| !is_version_char(*c)) {
+        Option::None => Ok(()),
+        Option::Some(c) => Err(format!(
+            "{} `{}` contains `{}`, only alphanumerics and `._+~^` are permitted",
+            component, arg, c
+        )),
+    }
+}
+
+fn is_version_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || "._+~^".contains(c)
+}
+
+/// converts a list of boolean dependency expressions into dependencies
+pub fn into_rich_dependencies(exprs: &[String], err: &Err) -> Result<Vec<Dependency>, Err> {
+    exprs
+        .iter()
+        .map(|expr| match RichDependency::parse(expr) {
+            Ok(rich) => Ok(Dependency::any(rich.to_string())),
+            Err(reason) => Err(err
+                .clone()
+                .note("rich dependency", expr)
+                .note("failed to parse", reason)),
+        })
+        .collect()
+}
+
+/// the rpmlib feature a package must require when it uses boolean
+/// dependencies, older versions of rpm refuse to install it.
+pub fn rich_dependencies_feature() -> Dependency {
+    Dependency::rpmlib("rpmlib(RichDependencies)", "4.12.0-1")
+}
+
+/// RichDependency is a boolean dependency expression as supported by
+/// RPM 4.13+, for example `(foo >= 1.0 or bar)`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum RichDependency {
+    Atom(String, Option<Constraint>),
+    And(Vec<RichDependency>),
+    Or(Vec<RichDependency>),
+    With(Vec<RichDependency>),
+    Without(Box<RichDependency>, Box<RichDependency>),
+    If(
+        Box<RichDependency>,
+        Box<RichDependency>,
+        Option<Box<RichDependency>>,
+    ),
+    Unless(
+        Box<RichDependency>,
+        Box<RichDependency>,
+        Option<Box<RichDependency>>,
+    ),
+}
+impl RichDependency {
+    /// parses and validates an expression, the outer most term must be
+    /// surrounded by parentheses.
+    pub fn parse(arg: &str) -> Result<RichDependency, String> {
+        let tokens = tokenize(arg);
+        if tokens.first() != Some(&"(") {
+            return Err("a rich dependency must start with `(`".to_string());
+        }
+        let mut pos = 0;
+        let expr = parse_expr(&tokens, &mut pos)?;
+        if pos != tokens.len() {
+            return Err(format!("unexpected `{}` after expression", tokens[pos]));
+        }
+        Ok(expr)
+    }
+}
+impl fmt::Display for RichDependency {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fn join(f: &mut fmt::Formatter, keyword: &str, terms: &[&RichDependency]) -> fmt::Result {
+            write!(f, "(")?;
+            for (i, term) in terms.iter().enumerate() {
+                if i != 0 {
+                    write!(f, " {} ", keyword)?;
+                }
+                write!(f, "{}", term)?;
+            }
+            write!(f, ")")
+        }
+        fn conditional(
+            f: &mut fmt::Formatter,
+            keyword: &str,
+            then: &RichDependency,
+            cond: &RichDependency,
+            other: &Option<Box<RichDependency>>,
+        ) -> fmt::Result {
+            write!(f, "({} {} {}", then, keyword, cond)?;
+            if let &Option::Some(ref other) = other {
+                write!(f, " else {}", other)?;
+            }
+            write!(f, ")")
+        }
+
+        match self {
+            &RichDependency::Atom(ref name, Option::None) => write!(f, "{}", name),
+            &RichDependency::Atom(ref name, Option::Some(ref c)) => {
+                write!(f, "{} {} {}", name, c.op.as_str(), c.evr)
+            }
+            &RichDependency::And(ref terms) => join(f, "and", &terms.iter().collect::<Vec<_>>()),
+            &RichDependency::Or(ref terms) => join(f, "or", &terms.iter().collect::<Vec<_>>()),
+            &RichDependency::With(ref terms) => join(f, "with", &terms.iter().collect::<Vec<_>>()),
+            &RichDependency::Without(ref a, ref b) => join(f, "without", &[&**a, &**b]),
+            &RichDependency::If(ref a, ref b, ref c) => conditional(f, "if", a, b, c),
+            &RichDependency::Unless(ref a, ref b, ref c) => conditional(f, "unless", a, b, c),
+        }
+    }
+}
+
+const KEYWORDS: &[&str] = &["and", "or", "if", "unless", "else", "with", "without"];
+
+/// splits an expression into parentheses, and whitespace separated words
+fn tokenize(arg: &str) -> Vec<&str> {
+    let mut tokens = Vec::new();
+    let mut start = Option::None;
+    for (i, c) in arg.char_indices() {
+        if c == '(' || c == ')' || c.is_whitespace() {
+            if let Option::Some(s) = start.take() {
+                tokens.push(&arg[s..i]);
+            }
+            if !c.is_whitespace() {
+                tokens.push(&arg[i..i + 1]);
+            }
+        } else if start.is_none() {
+            start = Option::Some(i);
+        }
+    }
+    if let Option::Some(s) = start {
+        tokens.push(&arg[s..]);
+    }
+    tokens
 }
-fn any(name: &str) -> Dependency {
-    Dependency::any(name)
+
+/// parses a parenthesized expression, `pos` points at the opening `(`
+fn parse_expr(tokens: &[&str], pos: &mut usize) -> Result<RichDependency, String> {
+    *pos += 1;
+    let first = parse_term(tokens, pos)?;
+
+    let keyword = match tokens.get(*pos) {
+        Option::Some(&")") => {
+            *pos += 1;
+            return Ok(first);
+        }
+        Option::Some(keyword) if KEYWORDS.contains(keyword) => *keyword,
+        Option::Some(other) => return Err(format!("expected an operator, found `{}`", other)),
+        Option::None => return Err("unterminated expression, expected `)`".to_string()),
+    };
+    *pos += 1;
+    let second = parse_term(tokens, pos)?;
+
+    let expr = match keyword {
+        "and" | "or" | "with" => {
+            let mut terms = vec![first, second];
+            while tokens.get(*pos) == Some(&keyword) {
+                *pos += 1;
+                terms.push(parse_term(tokens, pos)?);
+            }
+            match keyword {
+                "and" => RichDependency::And(terms),
+                "or" => RichDependency::Or(terms),
+                _ => RichDependency::With(terms),
+            }
+        }
+        "if" | "unless" => {
+            let other = if tokens.get(*pos) == Some(&"else") {
+                *pos += 1;
+                Option::Some(Box::new(parse_term(tokens, pos)?))
+            } else {
+                Option::None
+            };
+            if keyword == "if" {
+                RichDependency::If(Box::new(first), Box::new(second), other)
+            } else {
+                RichDependency::Unless(Box::new(first), Box::new(second), other)
+            }
+        }
+        "without" => RichDependency::Without(Box::new(first), Box::new(second)),
+        _ => return Err(format!("`{}` must follow `if` or `unless`", keyword)),
+    };
+
+    match tokens.get(*pos) {
+        Option::Some(&")") => {
+            *pos += 1;
+            Ok(expr)
+        }
+        Option::Some(other) => Err(format!(
+            "unexpected `{}`, operators cannot be mixed without parentheses",
+            other
+        )),
+        Option::None => Err("unterminated expression, expected `)`".to_string()),
+    }
+}
+
+/// parses either a nested expression, or a `name [op version]` atom
+fn parse_term(tokens: &[&str], pos: &mut usize) -> Result<RichDependency, String> {
+    let name = match tokens.get(*pos) {
+        Option::Some(&"(") => return parse_expr(tokens, pos),
+        Option::Some(&")") => return Err("empty term, expected a package name".to_string()),
+        Option::Some(name) if KEYWORDS.contains(name) => {
+            return Err(format!("expected a package name, found `{}`", name))
+        }
+        Option::Some(name) => *name,
+        Option::None => return Err("unterminated expression, expected `)`".to_string()),
+    };
+    *pos += 1;
+
+    let is_operator = |token: &str| OPERATORS.iter().any(|(op, _)| *op == token);
+    match (tokens.get(*pos), tokens.get(*pos + 1)) {
+        (Option::Some(op), Option::Some(version)) if is_operator(*op) => {
+            *pos += 2;
+            let arg = format!("{} {}", op, version);
+            match Constraint::parse(&arg) {
+                Ok(constraint) => Ok(RichDependency::Atom(name.to_string(), constraint)),
+                Err(reason) => Err(format!("{} in `{} {}`", reason, name, arg)),
+            }
+        }
+        (Option::Some(op), _) if is_operator(*op) => Err(format!(
+            "operator after `{}` is not followed by a version",
+            name
+        )),
+        _ => Ok(RichDependency::Atom(name.to_string(), Option::None)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn op(arg: &str) -> Operator {
+        Constraint::parse(arg).unwrap().unwrap().op
+    }
+
+    #[test]
+    fn longest_operator_wins() {
+        let constraint = Constraint::parse(">= 1.2").unwrap().unwrap();
+        assert_eq!(constraint.op, Operator::GreaterEq);
+        assert_eq!(constraint.evr.to_string(), "1.2");
+        assert_eq!(op("<=2"), Operator::LessEq);
+        assert_eq!(op("=1"), Operator::Eq);
+        assert_eq!(op("==1"), Operator::Eq);
+        assert_eq!(op("> 1"), Operator::Greater);
+        assert_eq!(op("<1"), Operator::Less);
+        assert_eq!(op("1.0"), Operator::Eq);
+        assert_eq!(Constraint::parse("").unwrap(), Option::None);
+        assert_eq!(Constraint::parse(" * ").unwrap(), Option::None);
+    }
+
+    #[test]
+    fn dependencies_carry_their_operator() {
+        let err = Err::default();
+        let dep = into_dependency("foo", ">=1.2", DependencyFlags::ANY, &err).unwrap();
+        assert_eq!(dep.name, "foo");
+        assert_eq!(dep.version, "1.2");
+        assert_eq!(dep.flags, DependencyFlags::GE);
+        let dep = into_dependency("foo", "<= 2", DependencyFlags::ANY, &err).unwrap();
+        assert_eq!(dep.flags, DependencyFlags::LE);
+    }
+
+    #[test]
+    fn garbage_is_rejected() {
+        for garbage in [">=", "> !!", "~1.0", "= 1.0 2.0", "1:"].iter() {
+            assert!(Constraint::parse(garbage).is_err(), "{} parsed", garbage);
+            let err = into_dependency("libfoo", garbage, DependencyFlags::ANY, &Err::default())
+                .unwrap_err();
+            let notes = format!("{:?}", err);
+            assert!(notes.contains("libfoo"), "{} lacks the name", notes);
+        }
+        assert!(
+            into_dependency("two words", "1.0", DependencyFlags::ANY, &Err::default()).is_err()
+        );
+    }
+
+    #[test]
+    fn constraints_split_on_commas() {
+        let one = Constraints::One(">= 1.2, < 2.0".to_string());
+        assert_eq!(one.each(), vec![">= 1.2", "< 2.0"]);
+        let many = Constraints::Many(vec![">= 1.2".to_string(), "< 2.0, != ".to_string()]);
+        assert_eq!(many.each(), vec![">= 1.2", "< 2.0", "!="]);
+    }
+
+    #[test]
+    fn empty_constraints_accept_any_version() {
+        for empty in [
+            Constraints::One(String::new()),
+            Constraints::One(" , ,".to_string()),
+            Constraints::Many(Vec::new()),
+            Constraints::Many(vec!["".to_string(), " ".to_string()]),
+        ]
+        .iter()
+        {
+            assert_eq!(empty.each(), vec![""]);
+            let deps =
+                into_dependencies("foo", empty, DependencyFlags::ANY, &Err::default()).unwrap();
+            assert_eq!(deps.len(), 1);
+            assert_eq!(deps[0].flags, DependencyFlags::ANY);
+        }
+        let and = Constraints::One(String::new()).and(">= 1".to_string());
+        assert_eq!(and.each(), vec![">= 1"]);
+    }
+
+    #[test]
+    fn every_constraint_is_a_dependency() {
+        #[derive(Deserialize)]
+        struct Table {
+            string: Constraints,
+            array: Constraints,
+        }
+        let table: Table =
+            toml::from_str("string = '>= 1.2, < 2.0'\narray = ['>= 1.2', '< 2.0']").unwrap();
+        for constraints in [table.string, table.array].iter() {
+            let deps = into_dependencies("foo", constraints, DependencyFlags::ANY, &Err::default())
+                .unwrap();
+            let deps: Vec<(&str, DependencyFlags)> = deps
+                .iter()
+                .map(|dep| (dep.version.as_str(), dep.flags))
+                .collect();
+            assert_eq!(
+                deps,
+                vec![("1.2", DependencyFlags::GE), ("2.0", DependencyFlags::LESS)]
+            );
+        }
+    }
+
+    fn atom(name: &str) -> RichDependency {
+        RichDependency::Atom(name.to_string(), Option::None)
+    }
+
+    #[test]
+    fn rich_dependencies_nest() {
+        let expr = RichDependency::parse(
+            "(foo >= 1.0 and (bar or baz) and (qux if (quux with corge) else grault))",
+        )
+        .unwrap();
+        let foo = RichDependency::Atom("foo".to_string(), Constraint::parse(">= 1.0").unwrap());
+        let qux = RichDependency::If(
+            Box::new(atom("qux")),
+            Box::new(RichDependency::With(vec![atom("quux"), atom("corge")])),
+            Option::Some(Box::new(atom("grault"))),
+        );
+        assert_eq!(
+            expr,
+            RichDependency::And(vec![
+                foo,
+                RichDependency::Or(vec![atom("bar"), atom("baz")]),
+                qux,
+            ])
+        );
+
+        let expr = RichDependency::parse("((a without b) unless c)").unwrap();
+        assert_eq!(
+            expr,
+            RichDependency::Unless(
+                Box::new(RichDependency::Without(
+                    Box::new(atom("a")),
+                    Box::new(atom("b"))
+                )),
+                Box::new(atom("c")),
+                Option::None,
+            )
+        );
+    }
+
+    #[test]
+    fn rich_operators_need_parentheses_to_mix() {
+        // a version operator binds to its name before any keyword
+        let expr = RichDependency::parse("(a < 2 or b)").unwrap();
+        match expr {
+            RichDependency::Or(ref terms) => assert_eq!(terms[0].to_string(), "a < 2"),
+            ref other => panic!("{:?} is not an or", other),
+        };
+        assert!(RichDependency::parse("(a and b or c)").is_err());
+        assert!(RichDependency::parse("(a or b and c)").is_err());
+        assert!(RichDependency::parse("(a without b without c)").is_err());
+        assert!(RichDependency::parse("((a and b) or c)").is_ok());
+        assert!(RichDependency::parse("(a else b)").is_err());
+        assert!(RichDependency::parse("(a if b else c else d)").is_err());
+    }
+
+    #[test]
+    fn rich_parentheses_must_balance() {
+        for expr in [
+            "a or b",
+            "(a or b",
+            "((a or b)",
+            "(a or b))",
+            "(a or (b and c)",
+            "()",
+            "(a or)",
+            "(a >=)",
+            "(and b)",
+        ]
+        .iter()
+        {
+            assert!(RichDependency::parse(expr).is_err(), "{} parsed", expr);
+        }
+    }
+
+    #[test]
+    fn rich_dependencies_round_trip() {
+        for expr in [
+            "(foo >= 1:1.0-2 or bar)",
+            "(a and (b or c) and d)",
+            "(a if b else (c with d))",
+            "(a unless b)",
+            "(a without b)",
+            "((a < 2 or b > 3) unless (c = 1 and d))",
+        ]
+        .iter()
+        {
+            let parsed = RichDependency::parse(expr).unwrap();
+            assert_eq!(parsed.to_string(), *expr);
+            assert_eq!(RichDependency::parse(&parsed.to_string()).unwrap(), parsed);
+        }
+        // spacing, redundant parentheses and `==` are normalized
+        let parsed = RichDependency::parse("( (foo == 1)  or(bar) )").unwrap();
+        assert_eq!(parsed.to_string(), "(foo = 1 or bar)");
+    }
+
+    #[test]
+    fn evr_splits_epoch_version_release() {
+        let evr = Evr::parse("2:1.0-3.fc38").unwrap();
+        assert_eq!(evr.epoch, Option::Some(2));
+        assert_eq!(evr.version, "1.0");
+        assert_eq!(evr.release.as_deref(), Option::Some("3.fc38"));
+        assert_eq!(evr.to_string(), "2:1.0-3.fc38");
+
+        let evr = Evr::parse("1.0~rc1^git2").unwrap();
+        assert_eq!(evr.epoch, Option::None);
+        assert_eq!(evr.version, "1.0~rc1^git2");
+        assert_eq!(evr.release, Option::None);
+
+        // only the last `-` separates the release
+        assert!(Evr::parse("1.0-2-3").is_err());
+        let reason = Evr::parse("0:1/0").unwrap_err();
+        assert!(reason.contains("contains `/`"), "{}", reason);
+        assert_eq!(Evr::parse("0:1").unwrap().epoch, Option::Some(0));
+    }
+
+    #[test]
+    fn evr_epochs_are_unsigned_integers() {
+        for epoch in ["a:1.0", "-1:1.0", "1.5:1.0", " 1:1.0", "99999999999:1.0"].iter() {
+            assert!(Evr::parse(epoch).is_err(), "{} parsed", epoch);
+        }
+    }
+
+    #[test]
+    fn evr_components_are_not_empty() {
+        for evr in ["", ":1.0", "1:", "1:-1", "1.0-", "-1"].iter() {
+            assert!(Evr::parse(evr).is_err(), "{} parsed", evr);
+        }
+    }
 }
@@ -0,0 +1,43 @@
+use std::fs::write;
+use std::process::Command;
+
+/// a package whose symlink `/a` points outside of the extraction
+/// directory, followed by a file `/a/passwd` beneath it
+#[test]
+fn files_beneath_symlinks_are_rejected() {
+    let dir = tempfile::tempdir().unwrap();
+    let outside = tempfile::tempdir().unwrap();
+    let root = dir.path();
+    write(root.join("link"), b"").unwrap();
+    write(root.join("passwd"), b"root::0:0::/root:/bin/sh\n").unwrap();
+    write(
+        root.join("config.toml"),
+        format!(
+            "[rpm]\n\
+             name = 'escape'\n\
+             version = '1.0.0'\n\
+             license = 'MIT'\n\
+             arch = 'noarch'\n\
+             desc = 'a malicious payload'\n\
+             \n\
+             [contents]\n\
+             'link' = {{ dst = '/a', symlink = '{}' }}\n\
+             'passwd' = '/a/passwd'\n",
+            outside.path().display()
+        ),
+    )
+    .unwrap();
+
+    let run = |args: &[&str]| {
+        Command::new(env!("CARGO_BIN_EXE_rpmbuilder"))
+            .current_dir(root)
+            .args(args)
+            .status()
+            .unwrap()
+    };
+    assert!(run(&["pkg", "--stream", "config.toml", "escape.rpm"]).success());
+    assert!(!run(&["extract", "escape.rpm", "out"]).success());
+    assert!(!outside.path().join("passwd").exists());
+    assert!(!run(&["simulate", "escape.rpm", "--root", "root"]).success());
+    assert!(!outside.path().join("passwd").exists());
+}
@@ -0,0 +1,12 @@
+/*
+ * the source of libdemo.so.1, rebuild with
+ *
+ *   gcc -shared -fPIC -Os -s -Wl,-soname,libdemo.so.1 \
+ *       -Wl,--version-script,demo.map -Wl,--build-id=none \
+ *       -o libdemo.so.1 demo.c
+ */
+#include <stdio.h>
+
+int demo_hello(void) {
+    return puts("hello");
+}
@@ -0,0 +1,64 @@
+use std::fs::{create_dir_all, write};
+use std::path::Path;
+use std::process::Command;
+
+fn rpmbuilder(root: &Path, args: &[&str]) -> String {
+    let output = Command::new(env!("CARGO_BIN_EXE_rpmbuilder"))
+        .current_dir(root)
+        .args(args)
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "rpmbuilder {:?} failed: {}",
+        args,
+        String::from_utf8_lossy(&output.stderr)
+    );
+    String::from_utf8(output.stdout).unwrap()
+}
+
+#[test]
+fn imported_packages_rebuild_identically() {
+    let dir = tempfile::tempdir().unwrap();
+    let root = dir.path();
+    create_dir_all(root.join("data")).unwrap();
+    write(root.join("data/demo.conf"), b"answer = 42\n").unwrap();
+    // wild card characters are escaped within the imported config
+    write(root.join("data/notes[1].txt"), b"first notes\n").unwrap();
+    write(root.join("post.sh"), b"echo installed $1\n").unwrap();
+    write(
+        root.join("config.toml"),
+        "[rpm]\n\
+         name = 'demo'\n\
+         version = '1.0'\n\
+         license = 'MIT'\n\
+         arch = 'noarch'\n\
+         desc = 'import round trip'\n\
+         description = 'a longer description of the demo package'\n\
+         \n\
+         [contents]\n\
+         'data/demo.conf' = { dst = '/etc/demo.conf', config = true, mode = 0o640 }\n\
+         'data/notes[[]1].txt' = '/usr/share/demo/notes[1].txt'\n\
+         \n\
+         [requires]\n\
+         'bash' = '>= 4.0'\n\
+         \n\
+         [requires_pre]\n\
+         'shadow-utils' = ''\n\
+         \n\
+         [scripts]\n\
+         post_install = 'post.sh'\n\
+         \n\
+         [changelog]\n\
+         '2020-01-01T00:00:00' = { author = 'Packager <packager@example.com>', entry = '- first' }\n",
+    )
+    .unwrap();
+    rpmbuilder(root, &["pkg", "config.toml", "original.rpm"]);
+
+    let imported = root.join("imported");
+    rpmbuilder(root, &["import", "original.rpm", "imported"]);
+    rpmbuilder(&imported, &["pkg", "config.toml", "../rebuilt.rpm"]);
+
+    let diff = rpmbuilder(root, &["diff", "original.rpm", "rebuilt.rpm"]);
+    assert_eq!(diff, "no differences\n");
+}
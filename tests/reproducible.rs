@@ -0,0 +1,60 @@
+use std::fs::{create_dir_all, read, write};
+use std::path::Path;
+use std::process::Command;
+use std::thread::sleep;
+use std::time::Duration;
+
+use sha2::{Digest, Sha256};
+
+/// (re)writes the contents, giving them a fresh mtime
+fn contents(root: &Path) {
+    create_dir_all(root.join("data/bin")).unwrap();
+    write(root.join("data/bin/tool"), b"#!/bin/sh\necho tool\n").unwrap();
+    write(root.join("data/README"), b"read me\n").unwrap();
+}
+
+fn build(root: &Path, extra: &[&str], output: &str) -> Vec<u8> {
+    let status = Command::new(env!("CARGO_BIN_EXE_rpmbuilder"))
+        .current_dir(root)
+        .env("SOURCE_DATE_EPOCH", "1600000000")
+        .args(&["pkg", "--reproducible"])
+        .args(extra)
+        .args(&["config.toml", output])
+        .status()
+        .unwrap();
+    assert!(status.success());
+    Sha256::digest(&read(root.join(output)).unwrap()).to_vec()
+}
+
+#[test]
+fn builds_are_byte_identical() {
+    let dir = tempfile::tempdir().unwrap();
+    let root = dir.path();
+    write(
+        root.join("config.toml"),
+        "[rpm]\n\
+         name = 'reproducible'\n\
+         version = '1.0.0'\n\
+         license = 'MIT'\n\
+         arch = 'noarch'\n\
+         desc = 'reproducible build test'\n\
+         compression = { algo = 'gzip' }\n\
+         \n\
+         [contents]\n\
+         'data' = '/usr/share/reproducible'\n\
+         \n\
+         [changelog]\n\
+         '2020-01-01T00:00:00' = { author = 'Packager <packager@example.com>', entry = '- first' }\n",
+    )
+    .unwrap();
+
+    for extra in [&[][..], &["--stream"][..]].iter() {
+        contents(root);
+        let first = build(root, extra, "first.rpm");
+        // a second apart, so every mtime and the build time differ
+        sleep(Duration::from_millis(1100));
+        contents(root);
+        let second = build(root, extra, "second.rpm");
+        assert_eq!(first, second, "pkg {:?} is not reproducible", extra);
+    }
+}
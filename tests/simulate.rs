@@ -0,0 +1,128 @@
+use std::fs::{read_to_string, write};
+use std::path::Path;
+use std::process::{Command, Output};
+
+fn rpmbuilder(root: &Path, args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_rpmbuilder"))
+        .current_dir(root)
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+/// streams a package of `/etc/demo.conf` with the given scriptlets
+fn build(root: &Path, pre: &str, post: &str) {
+    write(root.join("demo.conf"), b"answer = 42\n").unwrap();
+    write(root.join("pre.sh"), pre).unwrap();
+    write(root.join("post.sh"), post).unwrap();
+    write(
+        root.join("config.toml"),
+        "[rpm]\n\
+         name = 'demo'\n\
+         version = '1.0'\n\
+         license = 'MIT'\n\
+         arch = 'noarch'\n\
+         desc = 'scriptlets'\n\
+         \n\
+         [contents]\n\
+         'demo.conf' = '/etc/demo.conf'\n\
+         \n\
+         [scripts]\n\
+         pre_install = 'pre.sh'\n\
+         post_install = 'post.sh'\n",
+    )
+    .unwrap();
+    let output = rpmbuilder(root, &["pkg", "--stream", "config.toml", "demo.rpm"]);
+    assert!(output.status.success(), "{:?}", output);
+}
+
+/// pre_install runs before the payload is extracted, post_install after
+const PRE: &str = "test -e etc/demo.conf && exit 3\necho \"pre $1\" > order\n";
+const POST: &str = "cat etc/demo.conf\necho \"post $1\" >> order\n";
+
+#[test]
+fn scriptlets_run_around_extraction() {
+    let dir = tempfile::tempdir().unwrap();
+    let root = dir.path();
+    build(root, PRE, POST);
+
+    let output = rpmbuilder(
+        root,
+        &["simulate", "demo.rpm", "--root", "root", "--unsafe-host"],
+    );
+    assert!(output.status.success(), "{:?}", output);
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert_eq!(
+        stdout,
+        "demo-1.0-1.noarch: 1 files extracted\n\
+         pre_install: exit 0\n  \
+         created:\n    \
+         /order\n\
+         post_install: exit 0\n  \
+         stdout:\n    \
+         answer = 42\n"
+    );
+    let order = read_to_string(root.join("root/order")).unwrap();
+    assert_eq!(order, "pre 1\npost 1\n");
+}
+
+#[test]
+fn upgrades_pass_an_install_count_of_two() {
+    let dir = tempfile::tempdir().unwrap();
+    let root = dir.path();
+    build(root, PRE, POST);
+
+    let output = rpmbuilder(
+        root,
+        &[
+            "simulate",
+            "demo.rpm",
+            "--root",
+            "root",
+            "--upgrade",
+            "--unsafe-host",
+        ],
+    );
+    assert!(output.status.success(), "{:?}", output);
+    let order = read_to_string(root.join("root/order")).unwrap();
+    assert_eq!(order, "pre 2\npost 2\n");
+}
+
+#[test]
+fn failing_pre_install_aborts() {
+    let dir = tempfile::tempdir().unwrap();
+    let root = dir.path();
+    build(root, "echo refused >&2\nexit 1\n", POST);
+
+    let output = rpmbuilder(
+        root,
+        &["simulate", "demo.rpm", "--root", "root", "--unsafe-host"],
+    );
+    assert!(!output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(
+        stdout.contains("pre_install: exit 1\n  stderr:\n    refused\n"),
+        "{}",
+        stdout
+    );
+    assert!(!stdout.contains("post_install"), "{}", stdout);
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(
+        stderr.contains("pre_install failed, the payload was not extracted"),
+        "{}",
+        stderr
+    );
+    assert!(!root.join("root/etc/demo.conf").exists());
+    assert!(!root.join("root/order").exists());
+}
+
+#[test]
+fn scriptlets_need_unsafe_host() {
+    let dir = tempfile::tempdir().unwrap();
+    let root = dir.path();
+    build(root, PRE, POST);
+
+    let output = rpmbuilder(root, &["simulate", "demo.rpm", "--root", "root"]);
+    assert!(!output.status.success());
+    assert!(!root.join("root/etc/demo.conf").exists());
+}
@@ -0,0 +1,64 @@
+use std::fs::{read, write};
+use std::process::Command;
+
+use rpm::{IndexTag, Package};
+
+#[test]
+fn source_packages_read_back() {
+    let dir = tempfile::tempdir().unwrap();
+    let root = dir.path();
+    write(root.join("install.sh"), b"echo installed\n").unwrap();
+    write(root.join("hello-1.0.tar.gz"), b"not really a tarball").unwrap();
+    write(
+        root.join("config.toml"),
+        "[rpm]\n\
+         name = 'hello'\n\
+         version = '1.0'\n\
+         license = 'MIT'\n\
+         arch = 'x86_64'\n\
+         desc = 'source package test'\n\
+         \n\
+         [scripts]\n\
+         post_install = 'install.sh'\n\
+         \n\
+         [srpm]\n\
+         sources = ['hello-1.0.tar.gz']\n",
+    )
+    .unwrap();
+
+    let status = Command::new(env!("CARGO_BIN_EXE_rpmbuilder"))
+        .current_dir(root)
+        .args(&["srpm", "config.toml", "hello-1.0-1.src.rpm"])
+        .status()
+        .unwrap();
+    assert!(status.success());
+
+    // the lead's type is 1 for source packages
+    let bytes = read(root.join("hello-1.0-1.src.rpm")).unwrap();
+    assert_eq!(&bytes[6..8], &[0, 1]);
+
+    let pkg = Package::open(root.join("hello-1.0-1.src.rpm")).unwrap();
+    let header = &pkg.metadata.header;
+    assert_eq!(pkg.metadata.get_name().unwrap(), "hello");
+    assert_eq!(pkg.metadata.get_arch().unwrap(), "src");
+    assert_eq!(
+        header
+            .get_entry_data_as_u32(IndexTag::RPMTAG_SOURCEPACKAGE)
+            .unwrap(),
+        1
+    );
+    assert!(pkg.metadata.get_source_rpm().is_err());
+
+    let mut files: Vec<(String, Vec<u8>)> = pkg
+        .files()
+        .unwrap()
+        .map(|file| {
+            let file = file.unwrap();
+            (file.metadata.path.display().to_string(), file.content)
+        })
+        .collect();
+    files.sort();
+    let names: Vec<&str> = files.iter().map(|(name, _)| name.as_str()).collect();
+    assert_eq!(names, ["config.toml", "hello-1.0.tar.gz", "install.sh"]);
+    assert_eq!(files[1].1, b"not really a tarball");
+}
@@ -0,0 +1,68 @@
+use std::fs::{create_dir_all, read, write};
+use std::path::Path;
+use std::process::Command;
+
+use tempfile::TempDir;
+
+/// writes a config packaging a few files, large enough for every reader
+/// and compressor thread to see work
+fn project() -> TempDir {
+    let dir = tempfile::tempdir().unwrap();
+    let root = dir.path();
+    create_dir_all(root.join("data/nested")).unwrap();
+    for i in 0..16u32 {
+        let contents: Vec<u8> = (0..64 * 1024u32).map(|x| (x * (i + 1)) as u8).collect();
+        write(root.join(format!("data/file{}", i)), &contents).unwrap();
+    }
+    write(root.join("data/nested/small"), b"small\n").unwrap();
+    write(
+        root.join("config.toml"),
+        "[rpm]\n\
+         name = 'jobs'\n\
+         version = '1.0.0'\n\
+         license = 'MIT'\n\
+         arch = 'noarch'\n\
+         desc = 'stream jobs test'\n\
+         compression = { algo = 'zstd' }\n\
+         \n\
+         [contents]\n\
+         'data' = '/usr/share/jobs'\n",
+    )
+    .unwrap();
+    dir
+}
+
+fn stream(root: &Path, flags: &[&str], output: &str) -> Vec<u8> {
+    let status = Command::new(env!("CARGO_BIN_EXE_rpmbuilder"))
+        .current_dir(root)
+        .env("SOURCE_DATE_EPOCH", "1600000000")
+        .args(&["pkg", "--reproducible"])
+        .args(flags)
+        .args(&["config.toml", output])
+        .status()
+        .unwrap();
+    assert!(status.success());
+    read(root.join(output)).unwrap()
+}
+
+#[test]
+fn jobs_do_not_change_the_package() {
+    let dir = project();
+    let one = stream(dir.path(), &["--stream", "--jobs", "1"], "one.rpm");
+    let many = stream(dir.path(), &["--stream", "--jobs", "4"], "many.rpm");
+    assert!(
+        one == many,
+        "--jobs 1 and --jobs 4 built different packages"
+    );
+}
+
+#[test]
+fn jobs_imply_streaming() {
+    let dir = project();
+    let streamed = stream(dir.path(), &["--stream"], "streamed.rpm");
+    let jobs = stream(dir.path(), &["--jobs", "4"], "jobs.rpm");
+    assert!(
+        streamed == jobs,
+        "--jobs 4 did not build the package --stream does"
+    );
+}